@@ -0,0 +1,94 @@
+use tonic_build::manual::{Builder, Method, MethodBuilder, Service};
+
+/// A method of a service whose messages are defined in `module`.
+fn method(module: &str, name: &str, route: &str, input: &str, output: &str) -> MethodBuilder {
+    Method::builder()
+        .name(name)
+        .route_name(route)
+        .input_type(format!("{}::{}", module, input))
+        .output_type(format!("{}::{}", module, output))
+        .codec_path("tonic::codec::ProstCodec")
+}
+
+/// Generates the gRPC services from the message types in `src/grpc/messages.rs` and
+/// `src/grpc/flight.rs`, so the build needs no `protoc`.
+fn main() {
+    let candles = |name: &str, route: &str, input: &str, output: &str| {
+        method("crate::grpc::messages", name, route, input, output)
+    };
+    let candle_service = Service::builder()
+        .name("CandleService")
+        .package("spark.candles.v1")
+        .method(
+            candles(
+                "get_candles",
+                "GetCandles",
+                "GetCandlesRequest",
+                "GetCandlesResponse",
+            )
+            .build(),
+        )
+        .method(
+            candles(
+                "stream_candles",
+                "StreamCandles",
+                "StreamCandlesRequest",
+                "CandleUpdate",
+            )
+            .server_streaming()
+            .build(),
+        )
+        .method(
+            candles(
+                "list_symbols",
+                "ListSymbols",
+                "ListSymbolsRequest",
+                "ListSymbolsResponse",
+            )
+            .build(),
+        )
+        .build();
+
+    // The read-only part of Arrow Flight; clients get `Unimplemented` for the rest.
+    let flight = |name: &str, route: &str, input: &str, output: &str| {
+        method("crate::grpc::flight", name, route, input, output)
+    };
+    let flight_service = Service::builder()
+        .name("FlightService")
+        .package("arrow.flight.protocol")
+        .method(
+            flight("list_flights", "ListFlights", "Criteria", "FlightInfo")
+                .server_streaming()
+                .build(),
+        )
+        .method(
+            flight(
+                "get_flight_info",
+                "GetFlightInfo",
+                "FlightDescriptor",
+                "FlightInfo",
+            )
+            .build(),
+        )
+        .method(
+            flight(
+                "get_schema",
+                "GetSchema",
+                "FlightDescriptor",
+                "SchemaResult",
+            )
+            .build(),
+        )
+        .method(
+            flight("do_get", "DoGet", "Ticket", "FlightData")
+                .server_streaming()
+                .build(),
+        )
+        .build();
+
+    Builder::new()
+        .build_client(false)
+        .build_transport(false)
+        .compile(&[candle_service, flight_service]);
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,125 @@
+//! Bars that close on price movement rather than time, built from a path of prices,
+//! oldest first: raw trades, or the open, extremes and close of fine candles.
+
+use serde::Serialize;
+
+/// A price on the path, with the volume traded at it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    /// Seconds since the epoch.
+    pub time: i64,
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// A finished Renko brick or range bar. `start` and `end` are the times of the ticks it
+/// was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Brick {
+    pub start: i64,
+    pub end: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl Brick {
+    fn at(tick: &Tick, price: f64) -> Self {
+        Self {
+            start: tick.time,
+            end: tick.time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0.0,
+        }
+    }
+}
+
+/// Distance the price travels along `ticks`. Every brick or bar of `size` takes at
+/// least `size` of it, so this bounds how many `ticks` make.
+pub fn travel(ticks: &[Tick]) -> f64 {
+    ticks
+        .windows(2)
+        .map(|pair| (pair[1].price - pair[0].price).abs())
+        .sum()
+}
+
+/// Renko bricks of `size` on the grid of its multiples. A brick closes once the price
+/// reaches one box above the top of the last brick or one below its bottom, so
+/// continuing takes one box and reversing two. The forming brick is left out.
+pub fn renko(ticks: &[Tick], size: f64) -> Vec<Brick> {
+    let mut bricks = Vec::new();
+    let Some(first) = ticks.first() else {
+        return bricks;
+    };
+    // The last brick spans boxes `low..high`, both at the first price's box to start.
+    let mut low = (first.price / size).floor() as i64;
+    let mut high = low;
+    let mut brick = Brick::at(first, first.price);
+    for tick in ticks {
+        brick.volume += tick.volume;
+        loop {
+            let (open, close) = if tick.price >= (high + 1) as f64 * size {
+                high += 1;
+                low = high - 1;
+                (low, high)
+            } else if tick.price <= (low - 1) as f64 * size {
+                low -= 1;
+                high = low + 1;
+                (high, low)
+            } else {
+                break;
+            };
+            let (open, close) = (open as f64 * size, close as f64 * size);
+            bricks.push(Brick {
+                end: tick.time,
+                open,
+                high: open.max(close),
+                low: open.min(close),
+                close,
+                ..brick
+            });
+            brick = Brick::at(tick, close);
+        }
+    }
+    bricks
+}
+
+/// Range bars spanning `range` from low to high. A bar closes at its limit once the
+/// price moves past it, and the next opens there. The forming bar is left out.
+pub fn range_bars(ticks: &[Tick], range: f64) -> Vec<Brick> {
+    let mut bars = Vec::new();
+    let Some(first) = ticks.first() else {
+        return bars;
+    };
+    let mut bar = Brick::at(first, first.price);
+    for tick in ticks {
+        loop {
+            let close = if tick.price > bar.low + range {
+                bar.low + range
+            } else if tick.price < bar.high - range {
+                bar.high - range
+            } else {
+                break;
+            };
+            bars.push(Brick {
+                end: tick.time,
+                high: bar.high.max(close),
+                low: bar.low.min(close),
+                close,
+                ..bar
+            });
+            bar = Brick::at(tick, close);
+        }
+        bar.end = tick.time;
+        bar.high = bar.high.max(tick.price);
+        bar.low = bar.low.min(tick.price);
+        bar.close = tick.price;
+        bar.volume += tick.volume;
+    }
+    bars
+}
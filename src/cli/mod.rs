@@ -0,0 +1,27 @@
+pub mod simulate;
+
+use crate::error::{Error, ParsingError};
+
+pub fn invalid_value(name: &str, value: &str) -> Error {
+    ParsingError::StringParsingError(format!("Invalid value for --{}: {}", name, value)).into()
+}
+
+/// Reads `--name value` pairs; every flag must be one of `known`.
+pub fn parse_flags<'a>(
+    args: &'a [String],
+    known: &[&str],
+) -> Result<Vec<(&'a str, &'a str)>, Error> {
+    let mut flags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let name = arg
+            .strip_prefix("--")
+            .filter(|name| known.contains(name))
+            .ok_or_else(|| ParsingError::StringParsingError(format!("Unknown argument {}", arg)))?;
+        let value = args.next().ok_or_else(|| {
+            ParsingError::StringParsingError(format!("Missing value for --{}", name))
+        })?;
+        flags.push((name, value.as_str()));
+    }
+    Ok(flags)
+}
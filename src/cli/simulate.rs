@@ -0,0 +1,149 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+use crate::cli::{invalid_value, parse_flags};
+use crate::error::Error;
+use crate::storage::candles::CandleStore;
+
+/// Bars requested per simulated `/history` call.
+const QUERY_BARS: i64 = 300;
+const QUERIES_PER_INTERVAL: usize = 200;
+
+struct SimulateArgs {
+    pairs: usize,
+    tps: f64,
+    days: u64,
+    /// Pairs actually ingested; the footprint of the rest is extrapolated.
+    sample_pairs: usize,
+    seed: u64,
+}
+
+impl SimulateArgs {
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut parsed = Self {
+            pairs: 10,
+            tps: 50.0,
+            days: 7,
+            sample_pairs: 1,
+            seed: 1,
+        };
+        let flags = parse_flags(args, &["pairs", "tps", "days", "sample-pairs", "seed"])?;
+        for (name, value) in flags {
+            match name {
+                "pairs" => parsed.pairs = value.parse()?,
+                "tps" => parsed.tps = value.parse().map_err(|_| invalid_value(name, value))?,
+                "days" => parsed.days = value.parse()?,
+                "sample-pairs" => parsed.sample_pairs = value.parse()?,
+                _ => parsed.seed = value.parse()?,
+            }
+        }
+        parsed.pairs = parsed.pairs.max(1);
+        parsed.sample_pairs = parsed.sample_pairs.clamp(1, parsed.pairs);
+        Ok(parsed)
+    }
+}
+
+/// `spark-candles simulate --pairs 50 --tps 200 --days 30`: feeds synthetic trades
+/// through `CandleStore::add_trade` and reports the projected footprint and query latency.
+pub fn run(args: &[String]) -> Result<(), Error> {
+    let args = SimulateArgs::parse(args)?;
+    let pair_tps = args.tps / args.pairs as f64;
+    let span_ms = args.days as i64 * 86_400_000;
+    let trades_per_pair = (pair_tps * (args.days * 86_400) as f64) as u64;
+    let mean_gap_ms = if trades_per_pair > 0 {
+        span_ms as f64 / trades_per_pair as f64
+    } else {
+        0.0
+    };
+
+    println!(
+        "Simulating {} of {} pairs: {:.2} trades/s per pair over {} days ({} trades each)",
+        args.sample_pairs, args.pairs, pair_tps, args.days, trades_per_pair
+    );
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let start_ms = chrono::Utc::now().timestamp_millis() - span_ms;
+    let mut ingest_time = Duration::ZERO;
+    let mut total_bytes = 0;
+    let mut last_store = None;
+
+    for pair in 0..args.sample_pairs {
+        let symbol = format!("SIM{}", pair);
+        let store = CandleStore::new();
+        let mut price: u128 = 1_000_000_000_000;
+        let mut event_time = start_ms as f64;
+
+        let started = Instant::now();
+        for _ in 0..trades_per_pair {
+            event_time += rng.gen_range(0.0..2.0 * mean_gap_ms);
+            let step = price / 1000;
+            price = (price + rng.gen_range(0..=2 * step))
+                .saturating_sub(step)
+                .max(1);
+            let volume = rng.gen_range(1..1_000_000_000u128);
+            store.add_trade(&symbol, price, volume, event_time as i64);
+        }
+        ingest_time += started.elapsed();
+
+        let footprint = store.footprint();
+        println!(
+            "{}: {} candles, {} archived trades, {:.1} MiB",
+            symbol,
+            footprint.candles,
+            footprint.trades,
+            mib(footprint.bytes)
+        );
+        total_bytes += footprint.bytes;
+        last_store = Some((symbol, store));
+    }
+
+    let ingested = trades_per_pair * args.sample_pairs as u64;
+    if ingested > 0 {
+        println!(
+            "Ingestion: {:.0} trades/s on one core ({:.0}x the target rate)",
+            ingested as f64 / ingest_time.as_secs_f64(),
+            ingested as f64 / ingest_time.as_secs_f64() / args.tps.max(f64::EPSILON)
+        );
+    }
+
+    let per_pair = total_bytes / args.sample_pairs;
+    println!(
+        "Projected memory: {:.1} MiB per pair, {:.1} MiB for {} pairs",
+        mib(per_pair),
+        mib(per_pair * args.pairs),
+        args.pairs
+    );
+
+    let Some((symbol, store)) = last_store else {
+        return Ok(());
+    };
+    let now = chrono::Utc::now().timestamp();
+    println!("Query latency for {} bars (p50 / p99):", QUERY_BARS);
+    for interval in store.intervals() {
+        let window = interval as i64 * QUERY_BARS;
+        let mut samples: Vec<Duration> = (0..QUERIES_PER_INTERVAL)
+            .map(|_| {
+                let to = now - rng.gen_range(0..=(span_ms / 1000 - window).max(0));
+                let started = Instant::now();
+                let candles = store.get_candles_in_time_range(&symbol, interval, to - window, to);
+                let elapsed = started.elapsed();
+                std::hint::black_box(candles);
+                elapsed
+            })
+            .collect();
+        samples.sort_unstable();
+        println!(
+            "  {:>8}s: {:>9.1?} / {:>9.1?}",
+            interval,
+            samples[samples.len() / 2],
+            samples[samples.len() * 99 / 100]
+        );
+    }
+
+    Ok(())
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
@@ -0,0 +1,336 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::error::Error;
+use crate::web::format::Rounding;
+use crate::web::params::{CandleType, PriceSource, VolumeIn};
+use crate::web::routes::history::AdvancedChartResponse;
+pub use crate::web::routes::ws::{WsBar, WsMessage, WsRequest};
+
+/// Parameters of `GET /history`; `resolution` uses the TradingView codes the server
+/// accepts, e.g. `"1"`, `"60"`, `"1D"` or `"1S"`.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryRequest {
+    pub symbol: String,
+    pub resolution: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub countback: Option<usize>,
+    pub extended: bool,
+    pub volume_in: Option<VolumeIn>,
+    /// Overrides the server's rounding policy for this response.
+    pub rounding: Option<Rounding>,
+    pub fill_gaps: bool,
+    pub closed_only: bool,
+    pub price_source: Option<PriceSource>,
+    pub candle_type: Option<CandleType>,
+}
+
+impl HistoryRequest {
+    pub fn new(symbol: impl Into<String>, resolution: impl Into<String>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            resolution: Some(resolution.into()),
+            ..Default::default()
+        }
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("symbol", self.symbol.clone())];
+        let optional = [
+            ("resolution", self.resolution.clone()),
+            ("from", self.from.map(|from| from.to_string())),
+            ("to", self.to.map(|to| to.to_string())),
+            ("countback", self.countback.map(|n| n.to_string())),
+            ("extended", self.extended.then(|| "true".to_string())),
+            (
+                "volume_in",
+                self.volume_in.map(|volume_in| match volume_in {
+                    VolumeIn::Base => "base".to_string(),
+                    VolumeIn::Quote => "quote".to_string(),
+                }),
+            ),
+            (
+                "rounding",
+                self.rounding.map(|rounding| match rounding {
+                    Rounding::HalfEven => "half_even".to_string(),
+                    Rounding::HalfUp => "half_up".to_string(),
+                    Rounding::Truncate => "truncate".to_string(),
+                }),
+            ),
+            ("fill_gaps", self.fill_gaps.then(|| "true".to_string())),
+            ("closed_only", self.closed_only.then(|| "true".to_string())),
+            (
+                "price_source",
+                self.price_source.map(|price_source| match price_source {
+                    PriceSource::Trade => "trade".to_string(),
+                    PriceSource::Mid => "mid".to_string(),
+                }),
+            ),
+            (
+                "candle_type",
+                self.candle_type.map(|candle_type| match candle_type {
+                    CandleType::Candles => "candles".to_string(),
+                    CandleType::HeikinAshi => "heikin_ashi".to_string(),
+                }),
+            ),
+        ];
+        query.extend(
+            optional
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, value?))),
+        );
+        query
+    }
+}
+
+/// An entry of `GET /symbols`. Only the fields bots rely on are typed; the rest of the
+/// TradingView symbol info is ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub description: String,
+    pub supported_resolutions: Vec<String>,
+    #[serde(default)]
+    pub inactive: Option<bool>,
+    /// Seconds since the epoch.
+    #[serde(default)]
+    pub last_trade_at: Option<i64>,
+}
+
+/// An entry of `GET /symbols_meta`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SymbolMeta {
+    pub symbol: String,
+    pub contract_id: String,
+    pub start_block: i64,
+    pub description: String,
+    pub first_trade_block: Option<i64>,
+    /// Seconds since the epoch.
+    pub first_trade_at: Option<i64>,
+    pub listing_date: Option<String>,
+}
+
+/// The bar still forming in a `Ticker`, in the units of the REST endpoints.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TickerCandle {
+    /// Bar length in seconds.
+    pub interval: u64,
+    /// Start of the bar in seconds since the epoch.
+    pub timestamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub trade_count: u64,
+}
+
+/// `GET /ticker`: the last trade of a pair and its forming bar.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ticker {
+    pub symbol: String,
+    pub last_price: Option<f64>,
+    /// Seconds since the epoch.
+    pub last_trade_at: Option<i64>,
+    /// Seconds since the epoch.
+    #[serde(default)]
+    pub first_trade: Option<i64>,
+    #[serde(default)]
+    pub listing_date: Option<String>,
+    pub candle: Option<TickerCandle>,
+}
+
+/// An entry of `GET /tickers`; figures are over the last 24 hours.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TickerSummary {
+    pub symbol: String,
+    pub last_price: Option<f64>,
+    pub change_percent: Option<f64>,
+    pub volume: f64,
+    pub quote_volume: f64,
+    #[serde(default)]
+    pub inactive: Option<bool>,
+}
+
+/// Client for one server, or one tenant of it when `base_url` ends in `/t/<tenant>`.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: None,
+        }
+    }
+
+    /// Sends `X-API-Key` with every request, as tenants with keys require.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Bars for a symbol. A `no_data` status comes back as an empty response rather
+    /// than an error.
+    pub async fn history(&self, request: &HistoryRequest) -> Result<AdvancedChartResponse, Error> {
+        let response: AdvancedChartResponse = self.get("/history", &request.query()).await?;
+        if response.s == "error" {
+            return Err(Error::ApiError(format!(
+                "history request for {} failed",
+                request.symbol
+            )));
+        }
+        Ok(response)
+    }
+
+    pub async fn symbols(&self) -> Result<Vec<SymbolInfo>, Error> {
+        #[derive(Deserialize)]
+        struct Symbols {
+            symbols: Vec<SymbolInfo>,
+        }
+        let value = self.get_ok("/symbols", &[]).await?;
+        Ok(serde_json::from_value::<Symbols>(value)?.symbols)
+    }
+
+    pub async fn symbol(&self, symbol: &str) -> Result<SymbolInfo, Error> {
+        let value = self
+            .get_ok("/symbols", &[("symbol", symbol.to_string())])
+            .await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub async fn symbols_meta(&self) -> Result<Vec<SymbolMeta>, Error> {
+        #[derive(Deserialize)]
+        struct Metadata {
+            symbols_meta: Vec<SymbolMeta>,
+        }
+        #[derive(Deserialize)]
+        struct SymbolsMeta {
+            metadata: Metadata,
+        }
+        let value = self.get_ok("/symbols_meta", &[]).await?;
+        Ok(serde_json::from_value::<SymbolsMeta>(value)?
+            .metadata
+            .symbols_meta)
+    }
+
+    /// Last price of `symbol` with its forming one-minute bar.
+    pub async fn ticker(&self, symbol: &str) -> Result<Ticker, Error> {
+        let value = self
+            .get_ok("/ticker", &[("symbol", symbol.to_string())])
+            .await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Last price, 24-hour change and volume of every pair.
+    pub async fn tickers(&self) -> Result<Vec<TickerSummary>, Error> {
+        #[derive(Deserialize)]
+        struct Tickers {
+            tickers: Vec<TickerSummary>,
+        }
+        let value = self.get_ok("/tickers", &[]).await?;
+        Ok(serde_json::from_value::<Tickers>(value)?.tickers)
+    }
+
+    /// Server time in seconds since the epoch.
+    pub async fn time(&self) -> Result<u64, Error> {
+        self.get("/time", &[]).await
+    }
+
+    /// Opens a `/ws` connection for live bars.
+    pub async fn candle_feed(&self) -> Result<CandleFeed, Error> {
+        let url = match self.base_url.strip_prefix("http") {
+            Some(rest) => format!("ws{}/ws", rest),
+            None => format!("{}/ws", self.base_url),
+        };
+        let mut request = url.into_client_request()?;
+        if let Some(api_key) = &self.api_key {
+            let value = api_key
+                .parse()
+                .map_err(|_| Error::ApiError("invalid API key".to_string()))?;
+            request.headers_mut().insert("X-API-Key", value);
+        }
+        let (socket, _) = connect_async(request).await?;
+        Ok(CandleFeed { socket })
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Error> {
+        let mut request = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .query(query);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("X-API-Key", api_key);
+        }
+        Ok(request.send().await?.error_for_status()?.json().await?)
+    }
+
+    /// Fetches a JSON object, turning `{"status": "error", "message": ...}` into an error.
+    async fn get_ok(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<serde_json::Value, Error> {
+        let value: serde_json::Value = self.get(path, query).await?;
+        if value["status"] == "error" {
+            let message = value["message"].as_str().unwrap_or("request failed");
+            return Err(Error::ApiError(message.to_string()));
+        }
+        Ok(value)
+    }
+}
+
+/// A `/ws` connection: subscribe to series, then read their bars with `next`.
+pub struct CandleFeed {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl CandleFeed {
+    pub async fn subscribe(&mut self, symbol: &str, resolution: &str) -> Result<(), Error> {
+        self.send(WsRequest::Subscribe {
+            symbol: symbol.to_string(),
+            resolution: resolution.to_string(),
+        })
+        .await
+    }
+
+    pub async fn unsubscribe(&mut self, symbol: &str, resolution: &str) -> Result<(), Error> {
+        self.send(WsRequest::Unsubscribe {
+            symbol: symbol.to_string(),
+            resolution: resolution.to_string(),
+        })
+        .await
+    }
+
+    /// The next message from the server, or `None` once it closed the connection.
+    pub async fn next(&mut self) -> Option<Result<WsMessage, Error>> {
+        loop {
+            return match self.socket.next().await? {
+                Ok(Message::Text(text)) => Some(serde_json::from_str(&text).map_err(Error::from)),
+                Ok(Message::Close(_)) => None,
+                Ok(_) => continue,
+                Err(e) => Some(Err(e.into())),
+            };
+        }
+    }
+
+    async fn send(&mut self, request: WsRequest) -> Result<(), Error> {
+        let text = serde_json::to_string(&request)?;
+        Ok(self.socket.send(Message::Text(text)).await?)
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::web::deprecation::{default_deprecated_routes, DeprecatedRoute};
+use crate::web::format::NumberFormat;
+
+/// Process-wide settings read from the JSON file named by `SERVER_CONFIG`.
+/// Every field is optional so deployments without the file keep the defaults.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub about: AboutConfig,
+    /// Extra headers added to every response, e.g. attribution or terms links.
+    pub response_headers: HashMap<String, String>,
+    pub number_format: NumberFormat,
+    /// Legacy routes answered with deprecation headers; defaults to `/candles` and `/symbols_meta`.
+    pub deprecated_routes: Vec<DeprecatedRoute>,
+    /// Seconds of quiet after which streams resend the forming candle with a fresh
+    /// `updated_at`, so countdowns keep moving; no heartbeats when unset.
+    pub heartbeat_secs: Option<u64>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            about: AboutConfig::default(),
+            response_headers: HashMap::new(),
+            number_format: NumberFormat::default(),
+            deprecated_routes: default_deprecated_routes(),
+            heartbeat_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, schemars::JsonSchema)]
+#[serde(default)]
+pub struct AboutConfig {
+    pub name: Option<String>,
+    pub data_source: Option<String>,
+    pub attribution: Option<String>,
+    pub terms: Option<String>,
+    pub terms_url: Option<String>,
+    pub license: Option<String>,
+    pub contact: Option<String>,
+}
+
+impl ServerConfig {
+    pub fn load_from_env() -> Result<Self, Error> {
+        match ev("SERVER_CONFIG") {
+            Ok(path) => Self::load(&path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+}
@@ -30,15 +30,39 @@ pub enum Error {
     #[error("Tokio tungstenite stream error {0}")]
     TokioTungsteniteStreamError(#[from] std::io::Error),
 
+    #[error("HTTP client error {0}")]
+    HttpClientError(#[from] reqwest::Error),
+
+    #[error("GraphQL error: {0}")]
+    GraphQlError(String),
+
     #[error("Pangea client error {0}")]
     PangeaClientError(#[from] pangea_client::Error),
 
     #[error("Parsing error: {0}")]
     ParsingError(#[from] ParsingError),
 
+    #[error("Invalid candle intervals: {0}")]
+    InvalidIntervals(String),
+
+    #[error("Failed to render chart: {0}")]
+    ChartRenderError(String),
+
+    #[error("Parquet error: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
+    #[error("gRPC server error: {0}")]
+    GrpcError(#[from] tonic::transport::Error),
+
+    #[error("API error: {0}")]
+    ApiError(String),
+
     #[error("Unknown chain id")]
     UnknownChainIdError,
 
+    #[error("Timed out waiting for the event subscription")]
+    SubscriptionTimeout,
+
     #[error("Pangea ws max retries exceeded")]
     MaxRetriesExceeded,
 }
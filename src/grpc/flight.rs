@@ -0,0 +1,362 @@
+//! Bulk candle transfer over Arrow Flight: `DoGet` streams a series as Arrow record
+//! batches, a page of candles each, for pipelines that read millions of bars without
+//! JSON in between. Flights are described by a JSON `FlightQuery` as the command of a
+//! descriptor, or by the path `[symbol, interval]` for a whole series, and the ticket
+//! of a flight is its query.
+
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, TimestampMillisecondArray};
+use arrow_ipc::writer::{
+    write_message, DictionaryTracker, EncodedData, IpcDataGenerator, IpcWriteOptions,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock};
+use tonic::{Request, Response, Status};
+
+use crate::storage::candles::Candle;
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::export::CandlePages;
+use crate::web::format::{Formatter, NumberFormat};
+
+mod service {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/arrow.flight.protocol.FlightService.rs"
+    ));
+}
+
+pub use service::flight_service_server::{FlightService, FlightServiceServer};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Criteria {
+    #[prost(bytes = "vec", tag = "1")]
+    pub expression: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FlightDescriptor {
+    /// 1 for `path`, 2 for `cmd`.
+    #[prost(int32, tag = "1")]
+    pub r#type: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub cmd: Vec<u8>,
+    #[prost(string, repeated, tag = "3")]
+    pub path: Vec<String>,
+}
+
+const DESCRIPTOR_PATH: i32 = 1;
+const DESCRIPTOR_CMD: i32 = 2;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Ticket {
+    #[prost(bytes = "vec", tag = "1")]
+    pub ticket: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Location {
+    #[prost(string, tag = "1")]
+    pub uri: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FlightEndpoint {
+    #[prost(message, optional, tag = "1")]
+    pub ticket: Option<Ticket>,
+    /// Empty: the ticket is redeemed on this server.
+    #[prost(message, repeated, tag = "2")]
+    pub location: Vec<Location>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FlightInfo {
+    /// The IPC-encapsulated schema.
+    #[prost(bytes = "vec", tag = "1")]
+    pub schema: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub flight_descriptor: Option<FlightDescriptor>,
+    #[prost(message, repeated, tag = "3")]
+    pub endpoint: Vec<FlightEndpoint>,
+    /// -1 when unknown.
+    #[prost(int64, tag = "4")]
+    pub total_records: i64,
+    /// -1 when unknown.
+    #[prost(int64, tag = "5")]
+    pub total_bytes: i64,
+    #[prost(bool, tag = "6")]
+    pub ordered: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SchemaResult {
+    /// The IPC-encapsulated schema.
+    #[prost(bytes = "vec", tag = "1")]
+    pub schema: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FlightData {
+    #[prost(message, optional, tag = "1")]
+    pub flight_descriptor: Option<FlightDescriptor>,
+    /// The flatbuffer IPC message: the schema, then a record batch per message.
+    #[prost(bytes = "vec", tag = "2")]
+    pub data_header: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub app_metadata: Vec<u8>,
+    /// The buffers of a record batch.
+    #[prost(bytes = "vec", tag = "1000")]
+    pub data_body: Vec<u8>,
+}
+
+/// Columns of a flight, as in Parquet exports; prices and volumes are in display units.
+static SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    let double = |name| Field::new(name, DataType::Float64, false);
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        double("open"),
+        double("high"),
+        double("low"),
+        double("close"),
+        double("volume"),
+        double("quote_volume"),
+        Field::new("trade_count", DataType::Int64, false),
+    ]))
+});
+
+/// The candles of a flight: a stored series in `[from, to]`, to the present by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightQuery {
+    pub symbol: String,
+    pub interval: u64,
+    #[serde(default)]
+    pub from: i64,
+    #[serde(default)]
+    pub to: Option<i64>,
+}
+
+impl FlightQuery {
+    fn of_descriptor(descriptor: &FlightDescriptor) -> Result<Self, Status> {
+        match descriptor.r#type {
+            DESCRIPTOR_CMD => serde_json::from_slice(&descriptor.cmd)
+                .map_err(|e| Status::invalid_argument(format!("Invalid command: {}", e))),
+            DESCRIPTOR_PATH => match descriptor.path.as_slice() {
+                [symbol, interval] => Ok(Self {
+                    symbol: symbol.clone(),
+                    interval: interval.parse().map_err(|_| {
+                        Status::invalid_argument(format!("Invalid interval {}", interval))
+                    })?,
+                    from: 0,
+                    to: None,
+                }),
+                _ => Err(Status::invalid_argument(
+                    "Expected the path [symbol, interval]",
+                )),
+            },
+            _ => Err(Status::invalid_argument("Unknown descriptor type")),
+        }
+    }
+
+    fn of_ticket(ticket: &Ticket) -> Result<Self, Status> {
+        serde_json::from_slice(&ticket.ticket)
+            .map_err(|e| Status::invalid_argument(format!("Invalid ticket: {}", e)))
+    }
+
+    fn descriptor(&self) -> FlightDescriptor {
+        FlightDescriptor {
+            r#type: DESCRIPTOR_CMD,
+            cmd: self.to_json(),
+            path: vec![],
+        }
+    }
+
+    fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("queries serialize")
+    }
+}
+
+fn internal(e: ArrowError) -> Status {
+    Status::internal(e.to_string())
+}
+
+/// `SCHEMA` as an IPC message with its length prefix, as `FlightInfo` carries it.
+fn encapsulated_schema() -> Result<Vec<u8>, Status> {
+    let options = IpcWriteOptions::default();
+    let encoded = IpcDataGenerator::default().schema_to_bytes_with_dictionary_tracker(
+        &SCHEMA,
+        &mut DictionaryTracker::new(false),
+        &options,
+    );
+    let mut schema = Vec::new();
+    write_message(&mut schema, encoded, &options).map_err(internal)?;
+    Ok(schema)
+}
+
+fn flight_data(encoded: EncodedData) -> FlightData {
+    FlightData {
+        data_header: encoded.ipc_message,
+        data_body: encoded.arrow_data,
+        ..Default::default()
+    }
+}
+
+fn record_batch(candles: &[Candle], formatter: &Formatter) -> Result<RecordBatch, ArrowError> {
+    let doubles = |value: &dyn Fn(&Candle) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(candles.iter().map(value)))
+    };
+    let timestamps = TimestampMillisecondArray::from_iter_values(
+        candles.iter().map(|c| c.timestamp.timestamp_millis()),
+    )
+    .with_timezone("UTC");
+    let trade_counts = Int64Array::from_iter_values(candles.iter().map(|c| c.trade_count as i64));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(timestamps),
+        doubles(&|c| formatter.price(c.open)),
+        doubles(&|c| formatter.price(c.high)),
+        doubles(&|c| formatter.price(c.low)),
+        doubles(&|c| formatter.price(c.close)),
+        doubles(&|c| formatter.size(c.volume)),
+        doubles(&|c| formatter.quote(c.quote_volume)),
+        Arc::new(trade_counts),
+    ];
+    RecordBatch::try_new(Arc::clone(&SCHEMA), columns)
+}
+
+type FlightStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// `FlightService` over the default engine's pairs.
+pub struct Flights {
+    trading_engine: Arc<TradingEngine>,
+    number_format: NumberFormat,
+}
+
+impl Flights {
+    pub fn new(trading_engine: Arc<TradingEngine>, number_format: NumberFormat) -> Self {
+        Self {
+            trading_engine,
+            number_format,
+        }
+    }
+
+    /// The pages of the candles `query` asks for.
+    fn pages(&self, query: &FlightQuery) -> Result<CandlePages, Status> {
+        let store = self
+            .trading_engine
+            .get_store(&query.symbol)
+            .ok_or_else(|| Status::not_found(format!("Unknown symbol {}", query.symbol)))?;
+        if !store.stores_interval(query.interval) {
+            return Err(Status::invalid_argument(format!(
+                "Interval {} is not stored for {}",
+                query.interval, query.symbol
+            )));
+        }
+        let to = query.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+        Ok(CandlePages::new(
+            store,
+            &query.symbol,
+            query.interval,
+            query.from,
+            to,
+        ))
+    }
+
+    fn flight_info(&self, query: &FlightQuery) -> Result<FlightInfo, Status> {
+        Ok(FlightInfo {
+            schema: encapsulated_schema()?,
+            flight_descriptor: Some(query.descriptor()),
+            endpoint: vec![FlightEndpoint {
+                ticket: Some(Ticket {
+                    ticket: query.to_json(),
+                }),
+                location: vec![],
+            }],
+            total_records: -1,
+            total_bytes: -1,
+            ordered: true,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for Flights {
+    type ListFlightsStream = FlightStream<FlightInfo>;
+
+    /// Every stored series of every pair, in full.
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let mut flights = Vec::new();
+        for pair in self.trading_engine.pairs() {
+            for interval in pair.store.intervals() {
+                let query = FlightQuery {
+                    symbol: pair.config.symbol.clone(),
+                    interval,
+                    from: 0,
+                    to: None,
+                };
+                flights.push(self.flight_info(&query));
+            }
+        }
+        Ok(Response::new(Box::pin(stream::iter(flights))))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let query = FlightQuery::of_descriptor(request.get_ref())?;
+        self.pages(&query)?;
+        Ok(Response::new(self.flight_info(&query)?))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let query = FlightQuery::of_descriptor(request.get_ref())?;
+        self.pages(&query)?;
+        Ok(Response::new(SchemaResult {
+            schema: encapsulated_schema()?,
+        }))
+    }
+
+    type DoGetStream = FlightStream<FlightData>;
+
+    /// The schema, then a record batch per page of candles.
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let query = FlightQuery::of_ticket(request.get_ref())?;
+        let pages = self.pages(&query)?;
+        let formatter = Formatter::new(
+            &self.number_format,
+            self.trading_engine.config(&query.symbol).as_ref(),
+        );
+
+        let generator = IpcDataGenerator::default();
+        let options = IpcWriteOptions::default();
+        let mut dictionaries = DictionaryTracker::new(false);
+        let schema = flight_data(generator.schema_to_bytes_with_dictionary_tracker(
+            &SCHEMA,
+            &mut dictionaries,
+            &options,
+        ));
+        let batches = pages.into_stream(move |page| {
+            let batch = record_batch(page, &formatter).map_err(internal)?;
+            let (_, encoded) = generator
+                .encoded_batch(&batch, &mut dictionaries, &options)
+                .map_err(internal)?;
+            Ok(flight_data(encoded))
+        });
+        Ok(Response::new(Box::pin(
+            stream::once(async { Ok(schema) }).chain(batches),
+        )))
+    }
+}
@@ -0,0 +1,90 @@
+//! Messages of the `spark.candles.v1.CandleService` gRPC service, in protobuf field
+//! order. Prices and volumes are in display units.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Candle {
+    /// Start of the bar in seconds since the epoch.
+    #[prost(int64, tag = "1")]
+    pub timestamp: i64,
+    #[prost(double, tag = "2")]
+    pub open: f64,
+    #[prost(double, tag = "3")]
+    pub high: f64,
+    #[prost(double, tag = "4")]
+    pub low: f64,
+    #[prost(double, tag = "5")]
+    pub close: f64,
+    #[prost(double, tag = "6")]
+    pub volume: f64,
+    #[prost(double, tag = "7")]
+    pub quote_volume: f64,
+    #[prost(uint64, tag = "8")]
+    pub trade_count: u64,
+    #[prost(bool, tag = "9")]
+    pub closed: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetCandlesRequest {
+    #[prost(string, tag = "1")]
+    pub symbol: String,
+    /// Bar length in seconds.
+    #[prost(uint64, tag = "2")]
+    pub interval: u64,
+    /// Range start in seconds; 0 for the beginning of the data.
+    #[prost(int64, tag = "3")]
+    pub from: i64,
+    /// Range end in seconds; 0 for now.
+    #[prost(int64, tag = "4")]
+    pub to: i64,
+    /// Bars returned at most, the oldest first; 0 for the server's limit.
+    #[prost(uint32, tag = "5")]
+    pub limit: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetCandlesResponse {
+    /// Oldest first.
+    #[prost(message, repeated, tag = "1")]
+    pub candles: Vec<Candle>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamCandlesRequest {
+    #[prost(string, tag = "1")]
+    pub symbol: String,
+    #[prost(uint64, tag = "2")]
+    pub interval: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CandleUpdate {
+    #[prost(string, tag = "1")]
+    pub symbol: String,
+    #[prost(uint64, tag = "2")]
+    pub interval: u64,
+    #[prost(message, optional, tag = "3")]
+    pub candle: Option<Candle>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListSymbolsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SymbolInfo {
+    #[prost(string, tag = "1")]
+    pub symbol: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+    #[prost(string, tag = "3")]
+    pub exchange: String,
+    /// Stored bar lengths in seconds, shortest first.
+    #[prost(uint64, repeated, tag = "4")]
+    pub intervals: Vec<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListSymbolsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub symbols: Vec<SymbolInfo>,
+}
@@ -0,0 +1,213 @@
+//! Typed, low-overhead access for backend consumers such as risk engines and market
+//! makers: a gRPC `CandleService` and an Arrow Flight service, served next to the HTTP
+//! API on `GRPC_PORT`.
+
+pub mod flight;
+pub mod messages;
+
+mod service {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/spark.candles.v1.CandleService.rs"
+    ));
+}
+
+use futures_util::stream::{self, Stream};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+
+use crate::error::Error;
+use crate::storage::candles::CandleStore;
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::format::{Formatter, NumberFormat};
+use flight::{FlightServiceServer, Flights};
+use messages::{
+    Candle, CandleUpdate, GetCandlesRequest, GetCandlesResponse, ListSymbolsRequest,
+    ListSymbolsResponse, StreamCandlesRequest, SymbolInfo,
+};
+pub use service::candle_service_server::{CandleService, CandleServiceServer};
+
+/// Bars returned by one `GetCandles` call at most.
+const MAX_CANDLES: usize = 10000;
+/// Updates queued per stream before the sender waits for the client.
+const STREAM_CAPACITY: usize = 64;
+
+/// `CandleService` over the default engine's pairs.
+pub struct Candles {
+    trading_engine: Arc<TradingEngine>,
+    number_format: NumberFormat,
+}
+
+impl Candles {
+    pub fn new(trading_engine: Arc<TradingEngine>, number_format: NumberFormat) -> Self {
+        Self {
+            trading_engine,
+            number_format,
+        }
+    }
+
+    /// The store of `symbol` if it keeps `interval` bars.
+    fn store(&self, symbol: &str, interval: u64) -> Result<Arc<CandleStore>, Status> {
+        let store = self
+            .trading_engine
+            .get_store(symbol)
+            .ok_or_else(|| Status::not_found(format!("Unknown symbol {}", symbol)))?;
+        if !store.stores_interval(interval) {
+            return Err(Status::invalid_argument(format!(
+                "Interval {} is not stored for {}",
+                interval, symbol
+            )));
+        }
+        Ok(store)
+    }
+
+    fn formatter(&self, symbol: &str) -> Formatter {
+        Formatter::new(
+            &self.number_format,
+            self.trading_engine.config(symbol).as_ref(),
+        )
+    }
+}
+
+fn candle(
+    candle: &crate::storage::candles::Candle,
+    interval: u64,
+    formatter: &Formatter,
+) -> Candle {
+    Candle {
+        timestamp: candle.timestamp.timestamp(),
+        open: formatter.price(candle.open),
+        high: formatter.price(candle.high),
+        low: formatter.price(candle.low),
+        close: formatter.price(candle.close),
+        volume: formatter.size(candle.volume),
+        quote_volume: formatter.quote(candle.quote_volume),
+        trade_count: candle.trade_count,
+        closed: candle.is_closed(interval, chrono::Utc::now()),
+    }
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<CandleUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl CandleService for Candles {
+    async fn get_candles(
+        &self,
+        request: Request<GetCandlesRequest>,
+    ) -> Result<Response<GetCandlesResponse>, Status> {
+        let request = request.into_inner();
+        let store = self.store(&request.symbol, request.interval)?;
+        let to = match request.to {
+            0 => chrono::Utc::now().timestamp(),
+            to => to,
+        };
+        let limit = match request.limit as usize {
+            0 => MAX_CANDLES,
+            limit => limit.min(MAX_CANDLES),
+        };
+        let formatter = self.formatter(&request.symbol);
+        let candles = store
+            .get_candles_from(&request.symbol, request.interval, request.from, to, limit)
+            .iter()
+            .map(|c| candle(c, request.interval, &formatter))
+            .collect();
+        Ok(Response::new(GetCandlesResponse { candles }))
+    }
+
+    type StreamCandlesStream = UpdateStream;
+
+    /// The newest bar, then every update and close of the series. Updates missed by a
+    /// slow client are covered by resending the bar as it stands.
+    async fn stream_candles(
+        &self,
+        request: Request<StreamCandlesRequest>,
+    ) -> Result<Response<Self::StreamCandlesStream>, Status> {
+        let StreamCandlesRequest { symbol, interval } = request.into_inner();
+        let store = self.store(&symbol, interval)?;
+        let formatter = self.formatter(&symbol);
+        let mut events = self.trading_engine.candle_events.subscribe();
+        let (updates, received) = mpsc::channel(STREAM_CAPACITY);
+
+        tokio::spawn(async move {
+            let update = |bar: &crate::storage::candles::Candle| {
+                Ok(CandleUpdate {
+                    symbol: symbol.clone(),
+                    interval,
+                    candle: Some(candle(bar, interval, &formatter)),
+                })
+            };
+            let newest = || store.get_candles(&symbol, interval, 1).into_iter().next();
+            if let Some(bar) = newest() {
+                if updates.send(update(&bar)).await.is_err() {
+                    return;
+                }
+            }
+            loop {
+                let sent = match events.recv().await {
+                    Ok(event) if event.is_for(&symbol, interval) => {
+                        updates.send(update(event.candle())).await.is_ok()
+                    }
+                    Ok(_) => true,
+                    Err(RecvError::Lagged(_)) => match newest() {
+                        Some(bar) => updates.send(update(&bar)).await.is_ok(),
+                        None => true,
+                    },
+                    Err(RecvError::Closed) => false,
+                };
+                if !sent {
+                    return;
+                }
+            }
+        });
+
+        let stream = stream::unfold(received, |mut received| async move {
+            received.recv().await.map(|update| (update, received))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn list_symbols(
+        &self,
+        _request: Request<ListSymbolsRequest>,
+    ) -> Result<Response<ListSymbolsResponse>, Status> {
+        let symbols = self
+            .trading_engine
+            .pairs()
+            .iter()
+            .map(|pair| SymbolInfo {
+                symbol: pair.config.symbol.clone(),
+                description: pair.config.description.clone(),
+                exchange: pair.config.exchange().to_string(),
+                intervals: pair.store.intervals(),
+            })
+            .collect();
+        Ok(Response::new(ListSymbolsResponse { symbols }))
+    }
+}
+
+/// Serves `CandleService` and Arrow Flight over `trading_engine` on `port` until
+/// `shutdown` fires.
+pub async fn serve(
+    port: u16,
+    trading_engine: Arc<TradingEngine>,
+    number_format: NumberFormat,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<(), Error> {
+    let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port));
+    let flights = Flights::new(Arc::clone(&trading_engine), number_format);
+    tonic::transport::Server::builder()
+        .add_service(CandleServiceServer::new(Candles::new(
+            trading_engine,
+            number_format,
+        )))
+        .add_service(FlightServiceServer::new(flights))
+        .serve_with_shutdown(address, async move {
+            let _ = shutdown.recv().await;
+        })
+        .await?;
+    Ok(())
+}
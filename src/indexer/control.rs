@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// Pause switches for the pairs of one engine, flipped from the admin API. A paused pair
+/// stops fetching and following its source until resumed; the switches are not persisted,
+/// so a restarted process indexes every pair again.
+#[derive(Debug, Default)]
+pub struct PairControls {
+    switches: Mutex<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl PairControls {
+    /// Returns whether the pair's state changed.
+    pub fn set_paused(&self, symbol: &str, paused: bool) -> bool {
+        self.switch(symbol, |switch| {
+            switch.send_if_modified(|current| std::mem::replace(current, paused) != paused)
+        })
+    }
+
+    pub fn is_paused(&self, symbol: &str) -> bool {
+        self.switch(symbol, |switch| *switch.borrow())
+    }
+
+    /// Follows the pause state of `symbol`, `true` while it is paused.
+    pub fn watch(&self, symbol: &str) -> watch::Receiver<bool> {
+        self.switch(symbol, |switch| switch.subscribe())
+    }
+
+    fn switch<R>(&self, symbol: &str, f: impl FnOnce(&watch::Sender<bool>) -> R) -> R {
+        let mut switches = self.switches.lock().unwrap();
+        f(switches
+            .entry(symbol.to_string())
+            .or_insert_with(|| watch::channel(false).0))
+    }
+}
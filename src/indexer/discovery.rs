@@ -0,0 +1,243 @@
+use log::{error, info, warn};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::fuel_node::{ContractLog, FuelNode, LogReader};
+use crate::indexer::metadata::apply_contract_metadata;
+use crate::indexer::source::{normalize_hex, SourceKind};
+use crate::storage::trading_engine::{TradingEngine, TradingPairConfig};
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const DEFAULT_DECIMALS: i32 = 9;
+/// Hex digits of an asset id standing in for a ticker that is not configured.
+const UNKNOWN_TICKER_DIGITS: usize = 6;
+
+/// A market as Spark's registry logs it when registered: `MarketRegisterEvent` with the
+/// base and quote asset ids and the market contract.
+struct RegisteredMarket {
+    base_asset: String,
+    quote_asset: String,
+    contract_id: String,
+}
+
+impl RegisteredMarket {
+    fn decode(data: &[u8]) -> Result<Self, String> {
+        let mut reader = LogReader::new(data);
+        Ok(Self {
+            base_asset: reader.b256()?,
+            quote_asset: reader.b256()?,
+            contract_id: reader.b256()?,
+        })
+    }
+}
+
+/// Finds markets registered with Spark's market registry contract and adds the ones no
+/// pair covers yet, so new markets are indexed and listed without a config change. The
+/// registry's logs are read from a Fuel node from `DISCOVERY_START_BLOCK` on and polled
+/// every `DISCOVERY_INTERVAL_SECS`. A discovered pair is named after the tickers in
+/// `DISCOVERY_ASSET_SYMBOLS`, a JSON object from asset id to ticker, or the leading
+/// digits of an asset id without one, and is indexed from its registration block.
+pub struct MarketDiscovery {
+    node: FuelNode,
+    registry_id: String,
+    register_log_id: String,
+    start_block: i64,
+    interval: Duration,
+    asset_symbols: HashMap<String, String>,
+    decimals: i32,
+    source: Option<SourceKind>,
+}
+
+impl MarketDiscovery {
+    /// Discovery is on when `MARKET_REGISTRY_ID` is set; it then needs `FUEL_NODE_URL`
+    /// and `MARKET_REGISTER_LOG_ID`, the log id of `MarketRegisterEvent` in the registry's
+    /// ABI. Discovered pairs read events from `DISCOVERY_SOURCE`, Pangea by default, and
+    /// fall back to `DISCOVERY_DECIMALS`, 9 by default, when their contract cannot be
+    /// read.
+    pub fn from_env() -> Result<Option<Self>, Error> {
+        let Ok(registry_id) = ev("MARKET_REGISTRY_ID") else {
+            return Ok(None);
+        };
+        let asset_symbols: HashMap<String, String> = match ev("DISCOVERY_ASSET_SYMBOLS") {
+            Ok(symbols) => serde_json::from_str(&symbols)?,
+            Err(_) => HashMap::new(),
+        };
+        let source = match ev("DISCOVERY_SOURCE") {
+            Ok(kind) => Some(serde_json::from_value(serde_json::Value::String(kind))?),
+            Err(_) => None,
+        };
+        Ok(Some(Self {
+            node: FuelNode::new(ev("FUEL_NODE_URL")?),
+            registry_id,
+            register_log_id: ev("MARKET_REGISTER_LOG_ID")?,
+            start_block: match ev("DISCOVERY_START_BLOCK") {
+                Ok(block) => block.parse()?,
+                Err(_) => 0,
+            },
+            interval: Duration::from_secs(match ev("DISCOVERY_INTERVAL_SECS") {
+                Ok(secs) => secs.parse()?,
+                Err(_) => DEFAULT_INTERVAL_SECS,
+            }),
+            asset_symbols: asset_symbols
+                .into_iter()
+                .map(|(asset, ticker)| (normalize_hex(&asset), ticker))
+                .collect(),
+            decimals: match ev("DISCOVERY_DECIMALS") {
+                Ok(decimals) => decimals.parse()?,
+                Err(_) => DEFAULT_DECIMALS,
+            },
+            source,
+        }))
+    }
+
+    pub fn with_source(mut self, source: SourceKind) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Where discovered pairs read their events from.
+    pub fn source(&self) -> SourceKind {
+        self.source.unwrap_or_default()
+    }
+
+    /// Polls the registry until `shutdown` fires, adding each new market to
+    /// `trading_engine` and sending its config to `found` to be indexed.
+    pub async fn run(
+        self,
+        trading_engine: Arc<TradingEngine>,
+        found: mpsc::Sender<TradingPairConfig>,
+        mut shutdown: broadcast::Receiver<()>,
+    ) {
+        info!("Discovering markets from registry {}", self.registry_id);
+        let mut next_block = self.start_block;
+        loop {
+            if let Err(e) = self.poll(&trading_engine, &mut next_block, &found).await {
+                error!("Market discovery failed at block {}: {}", next_block, e);
+            }
+            tokio::select! {
+                _ = sleep(self.interval) => {}
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    /// Reads the registry's logs from `next_block` to the chain head, moving `next_block`
+    /// past every page read.
+    async fn poll(
+        &self,
+        trading_engine: &TradingEngine,
+        next_block: &mut i64,
+        found: &mpsc::Sender<TradingPairConfig>,
+    ) -> Result<(), Error> {
+        let head = self.node.latest_block().await?;
+        while *next_block <= head {
+            let (logs, following) = self
+                .node
+                .logs_page(&self.registry_id, &self.register_log_id, *next_block, head)
+                .await?;
+            for log in logs {
+                if let Some(config) = self.add(trading_engine, &log).await {
+                    if found.send(config).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            *next_block = following.unwrap_or(head + 1);
+        }
+        Ok(())
+    }
+
+    /// Adds the market registered by `log` unless a pair already covers it, with the
+    /// decimals its contract reports.
+    async fn add(
+        &self,
+        trading_engine: &TradingEngine,
+        log: &ContractLog,
+    ) -> Option<TradingPairConfig> {
+        let market = match RegisteredMarket::decode(&log.data) {
+            Ok(market) => market,
+            Err(e) => {
+                warn!(
+                    "Skipping undecodable market registration in block {}: {}",
+                    log.height, e
+                );
+                return None;
+            }
+        };
+        let contract_id = normalize_hex(&market.contract_id);
+        let known = trading_engine.pairs().iter().any(|pair| {
+            pair.config
+                .contracts()
+                .iter()
+                .any(|contract| normalize_hex(&contract.contract_id) == contract_id)
+        });
+        if known {
+            return None;
+        }
+
+        let mut config = match self.pair_config(&market, log.height) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Invalid config for market {}: {}", market.contract_id, e);
+                return None;
+            }
+        };
+        apply_contract_metadata(std::slice::from_mut(&mut config)).await;
+        match trading_engine.add_pair(config.clone()) {
+            Ok(Some(_)) => {
+                info!(
+                    "Discovered market {} as {} from block {}",
+                    market.contract_id, config.symbol, log.height
+                );
+                Some(config)
+            }
+            Ok(None) => {
+                warn!(
+                    "Discovered market {} but symbol {} is taken; add it to config.json",
+                    market.contract_id, config.symbol
+                );
+                None
+            }
+            Err(e) => {
+                error!("Failed to add market {}: {}", market.contract_id, e);
+                None
+            }
+        }
+    }
+
+    fn pair_config(
+        &self,
+        market: &RegisteredMarket,
+        registered_at: i64,
+    ) -> Result<TradingPairConfig, Error> {
+        let symbol = format!(
+            "{}{}",
+            self.ticker(&market.base_asset),
+            self.ticker(&market.quote_asset)
+        );
+        Ok(serde_json::from_value(json!({
+            "symbol": symbol,
+            "contract_id": market.contract_id,
+            "start_block": registered_at,
+            "description": symbol,
+            "decimals": self.decimals,
+            "base_asset": market.base_asset,
+            "quote_asset": market.quote_asset,
+            "source": self.source,
+        }))?)
+    }
+
+    fn ticker(&self, asset: &str) -> String {
+        let asset = normalize_hex(asset);
+        match self.asset_symbols.get(&asset) {
+            Some(ticker) => ticker.clone(),
+            None => asset[..UNKNOWN_TICKER_DIGITS].to_ascii_uppercase(),
+        }
+    }
+}
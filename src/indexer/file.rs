@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use log::warn;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::{mpsc, OnceCell};
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::order_event_handler::PangeaOrderEvent;
+use crate::indexer::source::{normalize_hex, EventSource, SourceEvent};
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// Recorded `PangeaOrderEvent`s replayed from NDJSON files, one event per line, for local
+/// development and reproducing bugs without a live source. Every pair reads the events
+/// of its own market from the same files; replay ends at the last recorded block.
+/// Events are replayed in chain order whatever order they were recorded in, and an event
+/// recorded more than once is replayed once.
+pub struct FileSource {
+    files: Vec<PathBuf>,
+    last_block: OnceCell<i64>,
+}
+
+impl FileSource {
+    /// Reads `EVENT_FILE_PATH`: one NDJSON file, or a directory whose `.ndjson` files are
+    /// replayed in name order.
+    pub fn from_env() -> Result<Self, Error> {
+        let path = PathBuf::from(ev("EVENT_FILE_PATH")?);
+        let files = if path.is_dir() {
+            let mut files: Vec<_> = std::fs::read_dir(&path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.extension().is_some_and(|ext| ext == "ndjson"))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path]
+        };
+        Ok(Self {
+            files,
+            last_block: OnceCell::new(),
+        })
+    }
+
+    /// Calls `handle` with each non-empty line of every file.
+    async fn for_each_line(&self, mut handle: impl FnMut(&str)) -> Result<(), Error> {
+        for file in &self.files {
+            let mut lines = open(file).await?.lines();
+            while let Some(line) = lines.next_line().await? {
+                if !line.trim().is_empty() {
+                    handle(&line);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn open(file: &Path) -> Result<BufReader<File>, Error> {
+    Ok(BufReader::new(File::open(file).await?))
+}
+
+#[async_trait]
+impl EventSource for FileSource {
+    /// The newest block in the files, found by one scan on first use. Lines that are not
+    /// events are counted then and skipped by every replay.
+    async fn latest_block(&self) -> Result<i64, Error> {
+        self.last_block
+            .get_or_try_init(|| async {
+                let (mut last_block, mut malformed) = (0, 0);
+                self.for_each_line(
+                    |line| match serde_json::from_str::<PangeaOrderEvent>(line) {
+                        Ok(event) => last_block = last_block.max(event.block_number),
+                        Err(_) => malformed += 1,
+                    },
+                )
+                .await?;
+                if malformed > 0 {
+                    warn!("Skipping {} malformed lines in the event files", malformed);
+                }
+                Ok(last_block)
+            })
+            .await
+            .copied()
+    }
+
+    async fn fetch_historical(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        let market_id = normalize_hex(&market.contract_id);
+        let mut matching = Vec::new();
+        self.for_each_line(|line| {
+            if let Ok(event) = serde_json::from_str::<PangeaOrderEvent>(line) {
+                if (from_block..=to_block).contains(&event.block_number)
+                    && normalize_hex(&event.market_id) == market_id
+                {
+                    matching.push(event);
+                }
+            }
+        })
+        .await?;
+        matching
+            .sort_by_key(|event| (event.block_number, event.transaction_index, event.log_index));
+        matching.dedup_by(|a, b| {
+            a.block_number == b.block_number
+                && a.transaction_hash == b.transaction_hash
+                && a.log_index == b.log_index
+        });
+
+        for event in matching {
+            if events
+                .send(SourceEvent::Order(Box::new(event)))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Recordings have no live tail: everything was delivered by the backfill, so this
+    /// waits until the pipeline stops listening.
+    async fn subscribe(
+        &self,
+        _market: &TradingPairConfig,
+        _from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        events.closed().await;
+        Ok(())
+    }
+}
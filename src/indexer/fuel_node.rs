@@ -0,0 +1,421 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::graphql::GraphQlClient;
+use crate::indexer::order_event_handler::PangeaOrderEvent;
+use crate::indexer::source::{normalize_hex, EventSource, SourceEvent};
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// Blocks requested per GraphQL page; the node caps how much a query may return.
+const BLOCK_PAGE: i64 = 40;
+/// How often a subscription asks the node for new blocks.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// TAI64 label of the Unix epoch: 2^62 plus the 10 seconds TAI was ahead in 1970.
+const TAI64_UNIX_EPOCH: u64 = (1 << 62) + 10;
+
+const BLOCKS_QUERY: &str = r#"
+query($first: Int, $after: String) {
+  blocks(first: $first, after: $after) {
+    nodes {
+      id
+      height
+      header { time }
+      transactions {
+        id
+        status {
+          ... on SuccessStatus {
+            receipts { receiptType id rb data }
+          }
+        }
+      }
+    }
+  }
+}"#;
+
+/// A log receipt of one contract, with where it was emitted.
+pub(crate) struct ContractLog {
+    pub height: i64,
+    pub block_hash: String,
+    /// Seconds since the epoch.
+    pub timestamp: i64,
+    pub transaction_id: String,
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub data: Vec<u8>,
+}
+
+/// A Fuel node's GraphQL API, read for the logs contracts emit.
+pub(crate) struct FuelNode {
+    client: GraphQlClient,
+}
+
+impl FuelNode {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: GraphQlClient::new(url),
+        }
+    }
+
+    pub async fn latest_block(&self) -> Result<i64, Error> {
+        let data: ChainData = self
+            .client
+            .query("{ chain { latestBlock { height } } }", json!({}))
+            .await?;
+        Ok(data.chain.latest_block.height.parse()?)
+    }
+
+    /// The id of the block at `height`, or `None` before the chain reaches it.
+    pub async fn block_hash(&self, height: i64) -> Result<Option<String>, Error> {
+        let data: BlockData = self
+            .client
+            .query(
+                "query($height: U32!) { block(height: $height) { id } }",
+                json!({ "height": height.to_string() }),
+            )
+            .await?;
+        Ok(data.block.map(|block| block.id))
+    }
+
+    /// The logs with `log_id` that `contract_id` emitted in one page of blocks from
+    /// `from_block`, up to `to_block`, and the block the next page starts at; `None`
+    /// once `to_block` or the chain head is reached.
+    pub async fn logs_page(
+        &self,
+        contract_id: &str,
+        log_id: &str,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<(Vec<ContractLog>, Option<i64>), Error> {
+        let contract_id = normalize_hex(contract_id);
+        let from_block = from_block.max(0);
+        let first = BLOCK_PAGE.min(to_block - from_block + 1);
+        if first <= 0 {
+            return Ok((Vec::new(), None));
+        }
+        // The blocks cursor is a height, and pages start after it.
+        let after = (from_block > 0).then(|| (from_block - 1).to_string());
+        let page: BlocksData = self
+            .client
+            .query(BLOCKS_QUERY, json!({ "first": first, "after": after }))
+            .await?;
+        if page.blocks.nodes.is_empty() {
+            return Ok((Vec::new(), None));
+        }
+        let mut logs = Vec::new();
+        let mut next = from_block;
+        for block in page.blocks.nodes {
+            let height: i64 = block.height.parse()?;
+            if height > to_block {
+                return Ok((logs, None));
+            }
+            logs.extend(logs_in_block(&block, height, &contract_id, log_id));
+            next = height + 1;
+        }
+        Ok((logs, (next <= to_block).then_some(next)))
+    }
+}
+
+fn logs_in_block(block: &Block, height: i64, contract_id: &str, log_id: &str) -> Vec<ContractLog> {
+    let timestamp = block
+        .header
+        .time
+        .parse::<u64>()
+        .map(|tai| tai.saturating_sub(TAI64_UNIX_EPOCH) as i64)
+        .unwrap_or_default();
+
+    let mut logs = Vec::new();
+    for (transaction_index, transaction) in block.transactions.iter().enumerate() {
+        let Some(receipts) = transaction
+            .status
+            .as_ref()
+            .and_then(|s| s.receipts.as_ref())
+        else {
+            continue;
+        };
+        for (log_index, receipt) in receipts.iter().enumerate() {
+            let matches = receipt.receipt_type == "LOG_DATA"
+                && receipt.id.as_deref().map(normalize_hex).as_deref() == Some(contract_id)
+                && receipt.rb.as_deref() == Some(log_id);
+            let Some(data) = receipt.data.as_deref().filter(|_| matches) else {
+                continue;
+            };
+            logs.push(ContractLog {
+                height,
+                block_hash: block.id.clone(),
+                timestamp,
+                transaction_id: transaction.id.clone(),
+                transaction_index: transaction_index as u64,
+                log_index: log_index as u64,
+                data: hex::decode(normalize_hex(data)).unwrap_or_default(),
+            });
+        }
+    }
+    logs
+}
+
+/// Spark trades read from a Fuel node's GraphQL API, for operators without Pangea
+/// credentials. Trades are the market contract's log receipts whose log id is the
+/// `TradeOrderEvent` one; other order events are not needed for candles.
+pub struct FuelNodeSource {
+    node: FuelNode,
+    trade_log_id: String,
+}
+
+impl FuelNodeSource {
+    /// Reads `FUEL_NODE_URL`, the node's GraphQL endpoint, and `FUEL_TRADE_LOG_ID`, the
+    /// log id of `TradeOrderEvent` in the market contract's ABI.
+    pub fn from_env() -> Result<Self, Error> {
+        Ok(Self {
+            node: FuelNode::new(ev("FUEL_NODE_URL")?),
+            trade_log_id: ev("FUEL_TRADE_LOG_ID")?,
+        })
+    }
+
+    /// Sends the trades of `market` in `from_block..=to_block`. Returns false once the
+    /// receiver is gone.
+    async fn send_range(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: &mpsc::Sender<SourceEvent>,
+    ) -> Result<bool, Error> {
+        let page = |from_block| async move {
+            let (logs, next) = self
+                .node
+                .logs_page(
+                    &market.contract_id,
+                    &self.trade_log_id,
+                    from_block,
+                    to_block,
+                )
+                .await?;
+            let fetched = logs
+                .into_iter()
+                .map(|log| trade_event(&market.contract_id, log))
+                .collect();
+            Ok((fetched, next))
+        };
+        GraphQlClient::send_pages(Some(from_block), page, events).await
+    }
+}
+
+fn trade_event(contract_id: &str, log: ContractLog) -> SourceEvent {
+    match TradeLog::decode(&log.data) {
+        Ok(trade) => SourceEvent::Order(Box::new(PangeaOrderEvent {
+            chain: 0,
+            block_number: log.height,
+            block_hash: log.block_hash,
+            block_timestamp: log.timestamp,
+            transaction_hash: log.transaction_id,
+            transaction_index: log.transaction_index,
+            log_index: log.log_index,
+            market_id: format!("0x{}", normalize_hex(contract_id)),
+            order_id: trade.sell_order_id,
+            event_type: Some("Trade".to_string()),
+            asset: None,
+            amount: Some(trade.size as u128),
+            asset_type: None,
+            order_type: None,
+            price: Some(trade.price as u128),
+            user: Some(trade.buyer),
+            order_matcher: Some(trade.matcher),
+            owner: Some(trade.seller),
+            limit_type: None,
+        })),
+        Err(error) => SourceEvent::Malformed {
+            payload: log.data,
+            error,
+        },
+    }
+}
+
+#[async_trait]
+impl EventSource for FuelNodeSource {
+    async fn latest_block(&self) -> Result<i64, Error> {
+        self.node.latest_block().await
+    }
+
+    async fn block_hash(&self, height: i64) -> Result<Option<String>, Error> {
+        self.node.block_hash(height).await
+    }
+
+    async fn fetch_historical(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        self.send_range(market, from_block, to_block, &events)
+            .await
+            .map(|_| ())
+    }
+
+    /// The node is polled for new blocks.
+    async fn subscribe(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        GraphQlClient::follow(
+            from_block,
+            POLL_INTERVAL,
+            || self.latest_block(),
+            |from, to| self.send_range(market, from, to, &events),
+            &events,
+        )
+        .await
+    }
+}
+
+/// The leading fields of Spark's `TradeOrderEvent` log: two order ids, their limit types,
+/// the matcher, size and price, block height, transaction id, and seller and buyer.
+struct TradeLog {
+    sell_order_id: String,
+    matcher: String,
+    size: u64,
+    price: u64,
+    seller: String,
+    buyer: String,
+}
+
+impl TradeLog {
+    fn decode(data: &[u8]) -> Result<Self, String> {
+        let mut reader = LogReader::new(data);
+        let sell_order_id = reader.b256()?;
+        let _buy_order_id = reader.b256()?;
+        let _sell_limit = reader.u64()?;
+        let _buy_limit = reader.u64()?;
+        let matcher = reader.identity()?;
+        let size = reader.u64()?;
+        let price = reader.u64()?;
+        let _block_height = reader.take(4)?;
+        let _tx_id = reader.b256()?;
+        let seller = reader.identity()?;
+        let buyer = reader.identity()?;
+        Ok(Self {
+            sell_order_id,
+            matcher,
+            size,
+            price,
+            seller,
+            buyer,
+        })
+    }
+}
+
+/// Reads fields in the Fuel ABI log encoding: big-endian integers, and enums as a `u64`
+/// variant index followed by the variant's value.
+pub(crate) struct LogReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> LogReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| format!("log data ends at byte {}", self.data.len()))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn b256(&mut self) -> Result<String, String> {
+        Ok(format!("0x{}", hex::encode(self.take(32)?)))
+    }
+
+    /// An `Identity`: an address or a contract id, both 32 bytes.
+    pub fn identity(&mut self) -> Result<String, String> {
+        match self.u64()? {
+            0 | 1 => self.b256(),
+            variant => Err(format!("unknown Identity variant {}", variant)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChainData {
+    chain: Chain,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Chain {
+    latest_block: BlockHeight,
+}
+
+#[derive(Deserialize)]
+struct BlockHeight {
+    height: String,
+}
+
+#[derive(Deserialize)]
+struct BlockData {
+    block: Option<BlockId>,
+}
+
+#[derive(Deserialize)]
+struct BlockId {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct BlocksData {
+    blocks: BlockConnection,
+}
+
+#[derive(Deserialize)]
+struct BlockConnection {
+    nodes: Vec<Block>,
+}
+
+#[derive(Deserialize)]
+struct Block {
+    id: String,
+    height: String,
+    header: BlockHeader,
+    transactions: Vec<Transaction>,
+}
+
+#[derive(Deserialize)]
+struct BlockHeader {
+    /// TAI64 timestamp as a decimal string.
+    time: String,
+}
+
+#[derive(Deserialize)]
+struct Transaction {
+    id: String,
+    status: Option<TransactionStatus>,
+}
+
+/// Only successful transactions carry receipts in the query; others come back empty.
+#[derive(Deserialize)]
+struct TransactionStatus {
+    receipts: Option<Vec<Receipt>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Receipt {
+    receipt_type: String,
+    id: Option<String>,
+    rb: Option<String>,
+    data: Option<String>,
+}
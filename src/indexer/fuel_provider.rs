@@ -0,0 +1,72 @@
+use fuels::accounts::provider::Provider;
+use log::{info, warn};
+use pangea_client::ChainId;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tokio::sync::Mutex;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::pangea::fuel_chain;
+
+/// Providers by chain, connected on first use.
+static PROVIDERS: OnceLock<Mutex<HashMap<ChainId, Provider>>> = OnceLock::new();
+
+/// The Fuel node queried for `chain`: `FUEL_MAINNET_RPC_URL` or `FUEL_TESTNET_RPC_URL`,
+/// or the public node of the chain.
+fn rpc_url(chain: ChainId) -> Result<String, Error> {
+    let (key, default) = match chain {
+        ChainId::FUEL => ("FUEL_MAINNET_RPC_URL", "mainnet.fuel.network"),
+        ChainId::FUELTESTNET => ("FUEL_TESTNET_RPC_URL", "testnet.fuel.network"),
+        _ => return Err(Error::UnknownChainIdError),
+    };
+    Ok(ev(key).unwrap_or_else(|_| default.to_string()))
+}
+
+/// The provider of the chain named by `CHAIN`, shared by every chain query. It connects
+/// on first use and again after `reset`.
+pub async fn fuel_provider() -> Result<Provider, Error> {
+    let chain = fuel_chain()?;
+    let mut providers = PROVIDERS.get_or_init(Default::default).lock().await;
+    if let Some(provider) = providers.get(&chain) {
+        return Ok(provider.clone());
+    }
+    let url = rpc_url(chain)?;
+    let provider = Provider::connect(&url).await?;
+    info!("Connected to Fuel node {}", url);
+    providers.insert(chain, provider.clone());
+    Ok(provider)
+}
+
+/// Drops the shared provider of the chain named by `CHAIN` after a query through it
+/// failed, so the next query connects again.
+pub async fn reset() {
+    if let (Ok(chain), Some(providers)) = (fuel_chain(), PROVIDERS.get()) {
+        if providers.lock().await.remove(&chain).is_some() {
+            warn!("Dropped the Fuel provider after a failed query; reconnecting on next use");
+        }
+    }
+}
+
+pub async fn latest_block_height() -> Result<i64, Error> {
+    let provider = fuel_provider().await?;
+    match provider.latest_block_height().await {
+        Ok(height) => Ok(height as i64),
+        Err(e) => {
+            reset().await;
+            Err(e.into())
+        }
+    }
+}
+
+/// The id of the block at `height`, or `None` before the chain reaches it.
+pub async fn block_hash(height: i64) -> Result<Option<String>, Error> {
+    let provider = fuel_provider().await?;
+    match provider.block_by_height((height as u32).into()).await {
+        Ok(block) => Ok(block.map(|block| block.id.to_string())),
+        Err(e) => {
+            reset().await;
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+use crate::error::Error;
+use crate::indexer::source::SourceEvent;
+
+/// Posts GraphQL queries to one endpoint, turning reported errors into `Error`s.
+pub struct GraphQlClient {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl GraphQlClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, Error> {
+        let response: GraphQlResponse<T> = self
+            .http
+            .post(&self.url)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match (response.data, response.errors) {
+            (Some(data), None) => Ok(data),
+            (_, Some(errors)) => Err(Error::GraphQlError(
+                errors
+                    .into_iter()
+                    .map(|e| e.message)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            )),
+            (None, None) => Err(Error::GraphQlError("empty response".to_string())),
+        }
+    }
+
+    /// Sends the events of a block range page by page, starting at `cursor`: `page`
+    /// fetches the events at a cursor and the cursor of the next page, `None` after the
+    /// last. Returns false once the receiver is gone.
+    pub async fn send_pages<C, P>(
+        mut cursor: Option<C>,
+        mut page: impl FnMut(C) -> P,
+        events: &mpsc::Sender<SourceEvent>,
+    ) -> Result<bool, Error>
+    where
+        P: Future<Output = Result<(Vec<SourceEvent>, Option<C>), Error>>,
+    {
+        while let Some(at) = cursor {
+            let (fetched, next) = page(at).await?;
+            for event in fetched {
+                if events.send(event).await.is_err() {
+                    return Ok(false);
+                }
+            }
+            cursor = next;
+        }
+        Ok(true)
+    }
+
+    /// Follows the chain from `from_block`, polling `latest_block` every `interval` and
+    /// passing each newly reached range of blocks to `send_range` until the receiver is
+    /// gone. A failed poll ends the subscription so the pipeline reconnects with its
+    /// usual backoff.
+    pub async fn follow<H, S>(
+        from_block: i64,
+        interval: Duration,
+        mut latest_block: impl FnMut() -> H,
+        mut send_range: impl FnMut(i64, i64) -> S,
+        events: &mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error>
+    where
+        H: Future<Output = Result<i64, Error>>,
+        S: Future<Output = Result<bool, Error>>,
+    {
+        let mut next = from_block;
+        loop {
+            let head = latest_block().await?;
+            if head >= next {
+                if !send_range(next, head).await? {
+                    return Ok(());
+                }
+                next = head + 1;
+            }
+            tokio::select! {
+                _ = sleep(interval) => {}
+                _ = events.closed() => return Ok(()),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    errors: Option<Vec<GraphQlErrorMessage>>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlErrorMessage {
+    message: String,
+}
@@ -0,0 +1,259 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::graphql::GraphQlClient;
+use crate::indexer::order_event_handler::PangeaOrderEvent;
+use crate::indexer::source::{EventSource, SourceEvent};
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// Trades requested per GraphQL page.
+const PAGE_SIZE: usize = 1000;
+/// How often a subscription asks the indexer for newly processed blocks.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Fields every trade entity must expose.
+const TRADE_FIELDS: &str =
+    "id blockHeight timestamp txId market tradeSize tradePrice seller buyer sellOrderId";
+
+/// Hosted indexers whose GraphQL APIs carry Spark trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexerApi {
+    /// Envio HyperIndex, served through Hasura.
+    Envio,
+    /// Subsquid, served through its OpenCRUD-style GraphQL server.
+    Subsquid,
+}
+
+impl IndexerApi {
+    fn default_entity(self) -> &'static str {
+        match self {
+            IndexerApi::Envio => "TradeOrderEvent",
+            IndexerApi::Subsquid => "tradeOrderEvents",
+        }
+    }
+
+    fn latest_block_query(self) -> &'static str {
+        match self {
+            IndexerApi::Envio => "{ chain_metadata { latest_processed_block } }",
+            IndexerApi::Subsquid => "{ squidStatus { height } }",
+        }
+    }
+
+    fn trades_query(self, entity: &str) -> String {
+        match self {
+            IndexerApi::Envio => format!(
+                "query($market: String!, $from: Int!, $to: Int!, $limit: Int!, $offset: Int!) {{ \
+                 trades: {entity}(where: {{market: {{_eq: $market}}, \
+                 blockHeight: {{_gte: $from, _lte: $to}}}}, \
+                 order_by: [{{blockHeight: asc}}, {{id: asc}}], \
+                 limit: $limit, offset: $offset) {{ {TRADE_FIELDS} }} }}"
+            ),
+            IndexerApi::Subsquid => format!(
+                "query($market: String!, $from: Int!, $to: Int!, $limit: Int!, $offset: Int!) {{ \
+                 trades: {entity}(where: {{market_eq: $market, \
+                 blockHeight_gte: $from, blockHeight_lte: $to}}, \
+                 orderBy: [blockHeight_ASC, id_ASC], \
+                 limit: $limit, offset: $offset) {{ {TRADE_FIELDS} }} }}"
+            ),
+        }
+    }
+}
+
+/// Spark trades from an Envio or Subsquid deployment, for operators who run their own
+/// indexing instead of Pangea. These APIs carry no block hashes, so finality holds trades
+/// back for the chain's `finality_depth` but cannot notice a reorganized block.
+pub struct IndexerApiSource {
+    api: IndexerApi,
+    client: GraphQlClient,
+    entity: String,
+}
+
+impl IndexerApiSource {
+    /// Reads `ENVIO_URL` or `SUBSQUID_URL`, and optionally `ENVIO_TRADE_ENTITY` or
+    /// `SUBSQUID_TRADE_ENTITY` when the schema names its trade entity differently.
+    pub fn from_env(api: IndexerApi) -> Result<Self, Error> {
+        let prefix = match api {
+            IndexerApi::Envio => "ENVIO",
+            IndexerApi::Subsquid => "SUBSQUID",
+        };
+        Ok(Self {
+            api,
+            client: GraphQlClient::new(ev(&format!("{}_URL", prefix))?),
+            entity: ev(&format!("{}_TRADE_ENTITY", prefix))
+                .unwrap_or_else(|_| api.default_entity().to_string()),
+        })
+    }
+
+    /// Sends the trades of `market` in `from_block..=to_block`. Returns false once the
+    /// receiver is gone.
+    async fn send_range(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: &mpsc::Sender<SourceEvent>,
+    ) -> Result<bool, Error> {
+        let query = &self.api.trades_query(&self.entity);
+        // Pages are read by offset. The position within the current block, which stands
+        // in for the log index, carries over from one page to the next.
+        let page = |(offset, mut position): (usize, (i64, u64))| async move {
+            let page: TradesData = self
+                .client
+                .query(
+                    query,
+                    json!({
+                        "market": market.contract_id,
+                        "from": from_block,
+                        "to": to_block,
+                        "limit": PAGE_SIZE,
+                        "offset": offset,
+                    }),
+                )
+                .await?;
+            let count = page.trades.len();
+            let fetched = page
+                .trades
+                .into_iter()
+                .map(|trade| match trade.into_event(&mut position) {
+                    Ok(event) => SourceEvent::Order(Box::new(event)),
+                    Err((payload, error)) => SourceEvent::Malformed { payload, error },
+                })
+                .collect();
+            Ok((
+                fetched,
+                (count == PAGE_SIZE).then_some((offset + count, position)),
+            ))
+        };
+        GraphQlClient::send_pages(Some((0, (i64::MIN, 0))), page, events).await
+    }
+}
+
+#[async_trait]
+impl EventSource for IndexerApiSource {
+    /// The last block the indexer has processed, which trails the chain head.
+    async fn latest_block(&self) -> Result<i64, Error> {
+        let status: serde_json::Value = self
+            .client
+            .query(self.api.latest_block_query(), json!({}))
+            .await?;
+        let height = match self.api {
+            IndexerApi::Envio => &status["chain_metadata"][0]["latest_processed_block"],
+            IndexerApi::Subsquid => &status["squidStatus"]["height"],
+        };
+        height
+            .as_i64()
+            .ok_or_else(|| Error::GraphQlError(format!("no processed block in {}", status)))
+    }
+
+    async fn fetch_historical(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        self.send_range(market, from_block, to_block, &events)
+            .await
+            .map(|_| ())
+    }
+
+    /// The indexer is polled for newly processed blocks.
+    async fn subscribe(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        GraphQlClient::follow(
+            from_block,
+            POLL_INTERVAL,
+            || self.latest_block(),
+            |from, to| self.send_range(market, from, to, &events),
+            &events,
+        )
+        .await
+    }
+}
+
+#[derive(Deserialize)]
+struct TradesData {
+    trades: Vec<TradeRow>,
+}
+
+/// Numbers come back as JSON numbers or, for big integers, as strings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Int(u64),
+    Text(String),
+}
+
+impl Number {
+    fn parse(&self) -> Option<u128> {
+        match self {
+            Number::Int(value) => Some(*value as u128),
+            Number::Text(value) => value.parse().ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeRow {
+    id: String,
+    block_height: i64,
+    timestamp: Number,
+    tx_id: String,
+    market: String,
+    trade_size: Number,
+    trade_price: Number,
+    seller: Option<String>,
+    buyer: Option<String>,
+    sell_order_id: Option<String>,
+}
+
+impl TradeRow {
+    /// Converts the row, numbering it within its block through `position`. Rows with
+    /// unreadable numbers come back as their JSON and the reason.
+    fn into_event(self, position: &mut (i64, u64)) -> Result<PangeaOrderEvent, (Vec<u8>, String)> {
+        let (Some(timestamp), Some(amount), Some(price)) = (
+            self.timestamp.parse(),
+            self.trade_size.parse(),
+            self.trade_price.parse(),
+        ) else {
+            let payload = format!("{:?}", self).into_bytes();
+            return Err((payload, "unreadable number in trade row".to_string()));
+        };
+        *position = match *position {
+            (block, index) if block == self.block_height => (block, index + 1),
+            _ => (self.block_height, 0),
+        };
+
+        Ok(PangeaOrderEvent {
+            chain: 0,
+            block_number: self.block_height,
+            block_hash: String::new(),
+            block_timestamp: timestamp as i64,
+            transaction_hash: self.tx_id,
+            transaction_index: 0,
+            log_index: position.1,
+            market_id: self.market,
+            order_id: self.sell_order_id.unwrap_or(self.id),
+            event_type: Some("Trade".to_string()),
+            asset: None,
+            amount: Some(amount),
+            asset_type: None,
+            order_type: None,
+            price: Some(price),
+            user: self.buyer,
+            order_matcher: None,
+            owner: self.seller,
+            limit_type: None,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use fuels::accounts::provider::Provider;
+use fuels::accounts::wallet::WalletUnlocked;
+use fuels::types::ContractId;
+use log::{info, warn};
+use spark_market_sdk::SparkMarketContract;
+use std::str::FromStr;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::fuel_provider::fuel_provider;
+use crate::indexer::source::{normalize_hex, SourceKind};
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// A market's assets and scaling as its contract reports them.
+#[derive(Debug, Clone)]
+pub struct MarketMetadata {
+    pub base_asset: String,
+    pub quote_asset: String,
+    /// Decimals of raw base-asset amounts.
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+    /// Decimals of raw prices.
+    pub price_decimals: u32,
+}
+
+impl MarketMetadata {
+    /// Reads the `config` of the market contract `contract_id`.
+    pub async fn fetch(provider: &Provider, contract_id: &str) -> Result<Self, Error> {
+        let contract_id = ContractId::from_str(contract_id)
+            .map_err(|e| Error::ApiError(format!("invalid contract id {}: {}", contract_id, e)))?;
+        // Reads need no funds, so any wallet will do.
+        let wallet = WalletUnlocked::new_random(Some(provider.clone()));
+        let market = SparkMarketContract::new(contract_id, wallet).await;
+        let (base_asset, base_decimals, quote_asset, quote_decimals, _owner, price_decimals, _) =
+            market.config().await?.value;
+        Ok(Self {
+            base_asset: format!("0x{}", normalize_hex(&base_asset.to_string())),
+            quote_asset: format!("0x{}", normalize_hex(&quote_asset.to_string())),
+            base_decimals,
+            quote_decimals,
+            price_decimals,
+        })
+    }
+
+    /// Takes the pair's assets and decimals from the contract, warning where config.json
+    /// said otherwise.
+    pub fn apply(&self, config: &mut TradingPairConfig) {
+        if config.price_decimals() != self.price_decimals {
+            warn!(
+                "{}: contract has {} price decimals, config {}; using the contract's",
+                config.symbol,
+                self.price_decimals,
+                config.price_decimals()
+            );
+        }
+        if config.size_decimals() != self.base_decimals {
+            warn!(
+                "{}: contract has {} size decimals, config {}; using the contract's",
+                config.symbol,
+                self.base_decimals,
+                config.size_decimals()
+            );
+        }
+        config.price_decimals = Some(self.price_decimals as i32);
+        config.size_decimals = Some(self.base_decimals as i32);
+        config.base_asset = Some(self.base_asset.clone());
+        config.quote_asset = Some(self.quote_asset.clone());
+    }
+}
+
+/// Reads the live contract of each pair, falling back to config.json for pairs whose
+/// contract cannot be read. Pairs with generated or replayed events have no contract to
+/// read, and `MARKET_METADATA=config` turns the lookup off altogether.
+pub async fn apply_contract_metadata(configs: &mut [TradingPairConfig]) {
+    if ev("MARKET_METADATA").is_ok_and(|value| value == "config") {
+        return;
+    }
+    let readable: Vec<_> = configs
+        .iter_mut()
+        .filter(|config| !matches!(config.source(), SourceKind::Synthetic | SourceKind::File))
+        .collect();
+    if readable.is_empty() {
+        return;
+    }
+    let provider = match fuel_provider().await {
+        Ok(provider) => provider,
+        Err(e) => {
+            warn!("Market metadata unavailable, using config.json: {}", e);
+            return;
+        }
+    };
+    for config in readable {
+        let contract_id = config.live_contract().contract_id;
+        match MarketMetadata::fetch(&provider, &contract_id).await {
+            Ok(metadata) => {
+                metadata.apply(config);
+                info!(
+                    "Read market metadata of {} from {}",
+                    config.symbol, contract_id
+                );
+            }
+            Err(e) => warn!(
+                "Failed to read market metadata of {}, using config.json: {}",
+                config.symbol, e
+            ),
+        }
+    }
+}
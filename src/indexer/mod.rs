@@ -1,2 +1,15 @@
+pub mod control;
+pub mod discovery;
+pub mod file;
+pub mod fuel_node;
+pub mod fuel_provider;
+pub mod graphql;
+pub mod indexer_api;
+pub mod metadata;
 pub mod order_event_handler;
 pub mod pangea;
+pub mod pangea_mux;
+pub mod pipeline;
+pub mod source;
+pub mod status;
+pub mod synthetic;
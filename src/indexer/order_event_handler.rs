@@ -1,5 +1,10 @@
+use crate::indexer::source::normalize_hex;
 use crate::storage::candles::CandleStore;
-use log::error;
+use crate::storage::interval::to_millis;
+use crate::storage::open_interest::Fill;
+use crate::storage::order_book::{OrderBook, Side, Top};
+use crate::storage::trades::Trade;
+use log::{error, warn};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -26,30 +31,142 @@ pub struct PangeaOrderEvent {
     pub limit_type: Option<String>,
 }
 
-pub async fn handle_order_event(
+impl PangeaOrderEvent {
+    pub fn event_time_ms(&self) -> i64 {
+        to_millis(self.block_timestamp)
+    }
+
+    /// Whether a trade's order owner is also the counterparty that filled it. Events
+    /// missing either address are not counted as self-trades.
+    pub fn is_self_trade(&self) -> bool {
+        match (&self.owner, &self.user) {
+            (Some(owner), Some(user)) => owner.eq_ignore_ascii_case(user),
+            _ => false,
+        }
+    }
+
+    /// Whether a trade is the leg of its fill that moves `base_asset`. Spark can emit a
+    /// Trade for each asset of a fill; legs that name neither asset nor asset type count.
+    pub fn is_base_leg(&self, base_asset: &str) -> bool {
+        match (&self.asset, &self.asset_type) {
+            (Some(asset), _) => normalize_hex(asset) == normalize_hex(base_asset),
+            (None, Some(asset_type)) => asset_type.eq_ignore_ascii_case("base"),
+            (None, None) => true,
+        }
+    }
+}
+
+/// Applies the Open, Cancel and Trade events of one block to the pair's order book,
+/// returning the new best bid and ask if they changed. A Trade fills the resting order it
+/// names; with a `base_asset`, only its base-asset legs do.
+pub fn handle_book_events(
+    book: &OrderBook,
+    events: &[PangeaOrderEvent],
+    base_asset: Option<&str>,
+) -> Option<Top> {
+    let before = book.top();
+    for event in events {
+        match event.event_type.as_deref() {
+            Some("Open") => {
+                let side = event.order_type.as_deref().and_then(Side::parse);
+                if let (Some(side), Some(price), Some(amount)) = (side, event.price, event.amount) {
+                    book.open(&event.order_id, side, price, amount);
+                }
+            }
+            Some("Cancel") => book.cancel(&event.order_id),
+            Some("Trade") => {
+                if let Some(amount) = event.amount {
+                    if base_asset.is_none_or(|base_asset| event.is_base_leg(base_asset)) {
+                        book.fill(&event.order_id, amount);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let after = book.top();
+    (after != before).then_some(after)
+}
+
+/// How the Trade events of one block move positions, for open interest. A Trade's
+/// `order_type` is the side of the order `owner` placed; `user` took the other side.
+pub fn position_fills(events: &[PangeaOrderEvent], base_asset: Option<&str>) -> Vec<Fill> {
+    events
+        .iter()
+        .filter(|event| event.event_type.as_deref() == Some("Trade"))
+        .filter(|event| base_asset.is_none_or(|base_asset| event.is_base_leg(base_asset)))
+        .filter_map(|event| {
+            let side = event.order_type.as_deref().and_then(Side::parse)?;
+            let (owner, user) = (event.owner.clone()?, event.user.clone()?);
+            let (buyer, seller) = match side {
+                Side::Buy => (owner, user),
+                Side::Sell => (user, owner),
+            };
+            Some(Fill {
+                buyer: normalize_hex(&buyer),
+                seller: normalize_hex(&seller),
+                size: event.amount?,
+                event_time: event.event_time_ms(),
+            })
+        })
+        .collect()
+}
+
+/// Applies the events of one block, all of which share its number, in one go: readers
+/// see either none or all of the block's trades. Trades the pair's price filter rejects
+/// are returned with the reason instead, for quarantine.
+pub async fn handle_block_events(
     candle_store: Arc<CandleStore>,
-    event: PangeaOrderEvent,
+    events: Vec<PangeaOrderEvent>,
     symbol: String,
-) {
-    if let Some(event_type) = event.event_type.as_deref() {
-        if event_type == "Trade" {
-            if let (Some(price), Some(amount)) = (event.price, event.amount) {
-                let block_timestamp = event.block_timestamp;
-                let intervals = vec![60, 180, 300, 900, 1800, 3600, 86400, 604800, 2592000];
-                for &interval in &intervals {
-                    candle_store.add_price(
-                        &symbol.clone(),
-                        interval,
-                        price as f64,
-                        amount as f64,
-                        block_timestamp,
-                    );
+) -> Vec<(PangeaOrderEvent, String)> {
+    let mut quarantined = Vec::new();
+    let Some(first) = events.first() else {
+        return quarantined;
+    };
+    let (block, block_hash) = (first.block_number, first.block_hash.clone());
+    candle_store.mark_block(block);
+
+    let mut trades = Vec::new();
+    for event in events {
+        if let Some(event_type) = event.event_type.as_deref() {
+            if event_type == "Trade" {
+                if candle_store
+                    .base_asset()
+                    .is_some_and(|base_asset| !event.is_base_leg(base_asset))
+                {
+                    continue;
+                }
+                if let (Some(price), Some(amount)) = (event.price, event.amount) {
+                    if let Some(reason) =
+                        candle_store.check_price(&symbol, price, event.event_time_ms())
+                    {
+                        warn!(
+                            "Quarantined {} trade at block {}: {}",
+                            symbol, block, reason
+                        );
+                        quarantined.push((event, reason));
+                        continue;
+                    }
+                    candle_store.observe_first_trade(block, event.event_time_ms());
+                    let trade = Trade {
+                        price,
+                        volume: amount,
+                        event_time: event.event_time_ms(),
+                        wash: candle_store.wash_trades().is_some() && event.is_self_trade(),
+                    };
+                    let event_key = format!("{}:{}", event.transaction_hash, event.log_index);
+                    trades.push((event_key, trade));
+                } else {
+                    error!("Incomplete Trade event data: {:?}", event);
                 }
-            } else {
-                error!("Incomplete Trade event data: {:?}", event);
             }
+        } else {
+            error!("Event type is missing in event: {:?}", event);
         }
-    } else {
-        error!("Event type is missing in event: {:?}", event);
     }
+    if !trades.is_empty() {
+        candle_store.add_block_trades(&symbol, block, &block_hash, trades);
+    }
+    quarantined
 }
@@ -1,201 +1,212 @@
+use async_trait::async_trait;
 use ethers_core::types::H256;
-use fuels::accounts::provider::Provider;
-use log::{error, info};
+use log::{error, info, warn};
 use pangea_client::{
     futures::StreamExt, provider::FuelProvider, query::Bound, requests::fuel::GetSparkOrderRequest,
     ClientBuilder, Format, WsProvider,
 };
 use pangea_client::{ChainId, Client};
+use rayon::prelude::*;
 use std::collections::HashSet;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::broadcast;
-use tokio::time::{sleep, timeout};
+use tokio::sync::{mpsc, oneshot};
 
 use crate::config::env::ev;
 use crate::error::Error;
-use crate::indexer::order_event_handler::handle_order_event;
+use crate::indexer::fuel_provider::{block_hash, latest_block_height};
 use crate::indexer::order_event_handler::PangeaOrderEvent;
-use crate::storage::candles::CandleStore;
-use crate::storage::trading_engine::{TradingEngine, TradingPairConfig};
-
-pub async fn initialize_pangea_indexer(
-    configs: Vec<TradingPairConfig>,
-    trading_engine: Arc<TradingEngine>,
-    shutdown: &mut broadcast::Receiver<()>,
-) -> Result<(), Error> {
-    let mut tasks = Vec::new();
-
-    for config in configs {
-        let store = match trading_engine.get_store(&config.symbol) {
-            Some(s) => s,
-            None => {
-                error!("No CandleStore found for symbol {}", config.symbol);
-                continue;
-            }
-        };
+use crate::indexer::pangea_mux::PangeaMux;
+use crate::indexer::pipeline::stale_after;
+use crate::indexer::source::{EventSource, SourceEvent};
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// Historical messages decoded together on the rayon pool.
+const DECODE_BATCH_SIZE: usize = 512;
+
+/// Pangea endpoints, connected to in turn when one fails.
+pub(crate) struct PangeaConnector {
+    endpoints: Vec<String>,
+    /// Index of the endpoint that last connected, where the next attempt starts.
+    current: AtomicUsize,
+}
 
-        tasks.push(tokio::spawn(process_events_for_pair(config, store)));
+impl PangeaConnector {
+    fn from_env() -> Result<Self, Error> {
+        let endpoints: Vec<_> = ev("PANGEA_URL")?
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect();
+        if endpoints.is_empty() {
+            return Err(Error::EnvVarError(
+                "PANGEA_URL".to_string(),
+                "no endpoints listed".to_string(),
+            ));
+        }
+        Ok(Self {
+            endpoints,
+            current: AtomicUsize::new(0),
+        })
     }
 
-    tokio::select! {
-        _ = shutdown.recv() => {
-            info!("Shutdown signal received in indexer.");
-        }
-        _ = futures::future::join_all(tasks) => {
-            info!("All indexer tasks completed.");
+    /// Connects to the first endpoint that accepts, starting from the one that worked
+    /// last, so a dead endpoint costs one failed attempt rather than a backoff cycle.
+    pub async fn connect(&self) -> Result<Client<WsProvider>, Error> {
+        let username = ev("PANGEA_USERNAME")?;
+        let password = ev("PANGEA_PASSWORD")?;
+        let start = self.current.load(Ordering::Relaxed);
+
+        let mut last_error = None;
+        for offset in 0..self.endpoints.len() {
+            let index = (start + offset) % self.endpoints.len();
+            let url = &self.endpoints[index];
+            match ClientBuilder::default()
+                .endpoint(url)
+                .credential(username.clone(), password.clone())
+                .build::<WsProvider>()
+                .await
+            {
+                Ok(client) => {
+                    if index != start {
+                        warn!("Failed over to Pangea endpoint {}", url);
+                    }
+                    self.current.store(index, Ordering::Relaxed);
+                    info!("Pangea WebSocket client connected.");
+                    return Ok(client);
+                }
+                Err(e) => {
+                    warn!("Pangea endpoint {} failed to connect: {}", url, e);
+                    last_error = Some(e);
+                }
+            }
         }
+        Err(last_error.expect("at least one endpoint").into())
     }
-
-    Ok(())
 }
 
-async fn process_events_for_pair(
-    config: TradingPairConfig,
-    store: Arc<CandleStore>,
-) -> Result<(), Error> {
-    let client = create_pangea_client().await?;
-    let contract_h256 = H256::from_str(&config.contract_id)?;
-
-    let last_processed_block = fetch_historical_data(
-        &client,
-        &store,
-        config.start_block,
-        contract_h256,
-        config.symbol.clone(),
-    )
-    .await?;
-
-    info!(
-        "Completed historical data fetch for {}. Last processed block: {}",
-        config.symbol, last_processed_block
-    );
-
-    listen_for_new_deltas(&store, last_processed_block, contract_h256, config.symbol).await
+/// Spark order events from Pangea over WebSocket. History is requested per pair, while
+/// every pair follows the chain through one shared subscription.
+pub struct PangeaSource {
+    connector: Arc<PangeaConnector>,
+    mux: Arc<PangeaMux>,
 }
 
-async fn create_pangea_client() -> Result<Client<WsProvider>, Error> {
-    let username = ev("PANGEA_USERNAME")?;
-    let password = ev("PANGEA_PASSWORD")?;
-    let url = ev("PANGEA_URL")?;
-
-    let client = ClientBuilder::default()
-        .endpoint(&url)
-        .credential(username, password)
-        .build::<WsProvider>()
-        .await?;
-
-    info!("Pangea WebSocket client connected.");
-    Ok(client)
+impl PangeaSource {
+    /// Reads `PANGEA_URL`, which may list several comma-separated endpoints.
+    pub fn from_env() -> Result<Self, Error> {
+        let connector = Arc::new(PangeaConnector::from_env()?);
+        Ok(Self {
+            mux: Arc::new(PangeaMux::new(Arc::clone(&connector), stale_after())),
+            connector,
+        })
+    }
 }
 
-async fn fetch_historical_data(
-    client: &Client<WsProvider>,
-    candle_store: &Arc<CandleStore>,
-    contract_start_block: i64,
-    contract_h256: H256,
-    symbol: String,
-) -> Result<i64, Error> {
-    let fuel_chain = match ev("CHAIN")?.as_str() {
-        "FUEL" => ChainId::FUEL,
-        _ => ChainId::FUELTESTNET,
-    };
-
-    let target_latest_block = get_latest_block(fuel_chain).await?;
-    info!(
-        "Fetching historical data from block {} to {}",
-        contract_start_block, target_latest_block
-    );
-
-    let request = GetSparkOrderRequest {
-        from_block: Bound::Exact(contract_start_block),
-        to_block: Bound::Exact(target_latest_block),
-        market_id__in: HashSet::from([contract_h256]),
-        chains: HashSet::from([fuel_chain]),
-        ..Default::default()
-    };
-
-    let stream = client.get_fuel_spark_orders_by_format(request, Format::JsonStream, false).await?;
-    pangea_client::futures::pin_mut!(stream);
-
-    while let Some(data) = stream.next().await {
-        if let Ok(data) = data {
-            if let Ok(order) = serde_json::from_slice::<PangeaOrderEvent>(&data) {
-                handle_order_event(candle_store.clone(), order, symbol.clone()).await;
-            } else {
-                error!("Failed to deserialize order event");
-            }
-        } else {
-            error!("Stream error while processing historical data");
-        }
+#[async_trait]
+impl EventSource for PangeaSource {
+    async fn latest_block(&self) -> Result<i64, Error> {
+        latest_block_height().await
     }
 
-    Ok(target_latest_block)
-}
+    fn restarts_stale_streams(&self) -> bool {
+        true
+    }
 
-async fn listen_for_new_deltas(
-    candle_store: &Arc<CandleStore>,
-    mut last_processed_block: i64,
-    contract_h256: H256,
-    symbol: String,
-) -> Result<(), Error> {
-    let mut retry_delay = Duration::from_secs(1);
-    let max_backoff = Duration::from_secs(60);
-
-    loop {
-        let client = match create_pangea_client().await {
-            Ok(c) => c,
-            Err(e) => {
-                error!("Failed to create Pangea client: {}", e);
-                sleep(retry_delay).await;
-                retry_delay = (retry_delay * 2).min(max_backoff);
-                continue;
-            }
-        };
+    async fn block_hash(&self, height: i64) -> Result<Option<String>, Error> {
+        block_hash(height).await
+    }
 
-        let fuel_chain = match ev("CHAIN")?.as_str() {
-            "FUEL" => ChainId::FUEL,
-            _ => ChainId::FUELTESTNET,
-        };
+    async fn fetch_historical(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        let client = self.connector.connect().await?;
+        let fuel_chain = fuel_chain()?;
 
         let request = GetSparkOrderRequest {
-            from_block: Bound::Exact(last_processed_block + 1),
-            to_block: Bound::Subscribe,
-            market_id__in: HashSet::from([contract_h256]),
+            from_block: Bound::Exact(from_block),
+            to_block: Bound::Exact(to_block),
+            market_id__in: HashSet::from([H256::from_str(&market.contract_id)?]),
             chains: HashSet::from([fuel_chain]),
             ..Default::default()
         };
 
-        match timeout(Duration::from_secs(10), client.get_fuel_spark_orders_by_format(request, Format::JsonStream, true)).await {
-            Ok(Ok(stream)) => {
-                pangea_client::futures::pin_mut!(stream);
-                retry_delay = Duration::from_secs(1);
-                while let Some(data) = stream.next().await {
-                    if let Ok(data) = data {
-                        if let Ok(order_event) = serde_json::from_slice::<PangeaOrderEvent>(&data) {
-                            last_processed_block = order_event.block_number;
-                            handle_order_event(candle_store.clone(), order_event, symbol.clone()).await;
-                        } else {
-                            error!("Failed to deserialize order event");
-                        }
-                    }
+        let stream = client
+            .get_fuel_spark_orders_by_format(request, Format::JsonStream, false)
+            .await?;
+
+        // Messages are decoded a batch at a time on the rayon pool while the next batch
+        // is read, then sent on in their original order.
+        let batches = stream
+            .ready_chunks(DECODE_BATCH_SIZE)
+            .map(|batch| {
+                let payloads = batch
+                    .into_iter()
+                    .filter_map(|data| {
+                        data.map_err(|_| error!("Stream error while processing historical data"))
+                            .ok()
+                    })
+                    .collect();
+                decode_batch(payloads)
+            })
+            .buffered(2);
+        pangea_client::futures::pin_mut!(batches);
+
+        'batches: while let Some(decoded) = batches.next().await {
+            for event in decoded? {
+                if events.send(event).await.is_err() {
+                    break 'batches;
                 }
             }
-            _ => error!("Failed to subscribe to new deltas, retrying..."),
         }
-        sleep(retry_delay).await;
-        retry_delay = (retry_delay * 2).min(max_backoff);
+
+        Ok(())
     }
+
+    /// Joins the shared subscription, which starts again from the earliest block any
+    /// pair needs when the pair's market is new to it or needs blocks it has passed.
+    async fn subscribe(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        let _route = self
+            .mux
+            .route(&market.contract_id, from_block, events.clone())?;
+        events.closed().await;
+        Ok(())
+    }
+}
+
+/// Decodes payloads in parallel, keeping their order.
+async fn decode_batch(payloads: Vec<Vec<u8>>) -> Result<Vec<SourceEvent>, Error> {
+    let (tx, rx) = oneshot::channel();
+    rayon::spawn(move || {
+        let _ = tx.send(payloads.into_par_iter().map(decode).collect());
+    });
+    rx.await.map_err(|e| anyhow::Error::from(e).into())
 }
 
+pub(crate) fn decode(data: Vec<u8>) -> SourceEvent {
+    match serde_json::from_slice::<PangeaOrderEvent>(&data) {
+        Ok(order) => SourceEvent::Order(Box::new(order)),
+        Err(e) => SourceEvent::Malformed {
+            payload: data,
+            error: e.to_string(),
+        },
+    }
+}
 
-async fn get_latest_block(chain_id: ChainId) -> Result<i64, Error> {
-    let provider_url = match chain_id {
-        ChainId::FUEL => "mainnet.fuel.network",
-        ChainId::FUELTESTNET => "testnet.fuel.network",
-        _ => return Err(Error::UnknownChainIdError),
-    };
-    let provider = Provider::connect(provider_url).await?;
-    Ok(provider.latest_block_height().await? as i64)
+pub(crate) fn fuel_chain() -> Result<ChainId, Error> {
+    Ok(match ev("CHAIN")?.as_str() {
+        "FUEL" => ChainId::FUEL,
+        _ => ChainId::FUELTESTNET,
+    })
 }
@@ -0,0 +1,310 @@
+use ethers_core::types::H256;
+use log::{error, info, warn};
+use pangea_client::{
+    futures::StreamExt, provider::FuelProvider, query::Bound, requests::fuel::GetSparkOrderRequest,
+    Format,
+};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{sleep, timeout};
+
+use crate::error::Error;
+use crate::indexer::fuel_provider::latest_block_height;
+use crate::indexer::pangea::{decode, fuel_chain, PangeaConnector};
+use crate::indexer::pipeline::jittered;
+use crate::indexer::source::{normalize_hex, SourceEvent};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Where a block, transaction and log put an event in the chain.
+type Position = (i64, u64, u64);
+
+/// Where one market's live events go.
+struct Route {
+    id: u64,
+    from_block: i64,
+    /// The last event sent, so one sent before the subscription restarted is skipped.
+    sent: Option<Position>,
+    events: mpsc::Sender<SourceEvent>,
+}
+
+impl Route {
+    /// The block a subscription has to start from to serve this route.
+    fn resume_block(&self) -> i64 {
+        self.sent.map_or(self.from_block, |(block, _, _)| block)
+    }
+
+    /// Whether an event at `position` is new to the route, recording it as sent if so.
+    fn take(&mut self, position: Position) -> bool {
+        let new = position.0 >= self.from_block && self.sent.is_none_or(|sent| position > sent);
+        if new {
+            self.sent = Some(position);
+        }
+        new
+    }
+}
+
+/// The subscription currently followed.
+struct Stream {
+    markets: HashSet<String>,
+    /// The block the subscription is at, whose events may have gone out in part.
+    position: i64,
+}
+
+/// What the subscription is doing, so a joining pair only restarts it when it has to.
+#[derive(Default)]
+enum Feed {
+    /// Waiting for a route.
+    #[default]
+    Idle,
+    /// Between subscriptions; the next one covers every route there is by then.
+    Retrying,
+    Live(Stream),
+}
+
+#[derive(Default)]
+struct Routes {
+    /// Routes by normalized market id.
+    by_market: HashMap<String, Route>,
+    feed: Feed,
+}
+
+/// Keeps a market routed to its pair until dropped.
+pub(crate) struct RouteGuard {
+    mux: Arc<PangeaMux>,
+    market: String,
+    id: u64,
+}
+
+impl Drop for RouteGuard {
+    fn drop(&mut self) {
+        let mut routes = self.mux.routes.lock().unwrap();
+        if routes
+            .by_market
+            .get(&self.market)
+            .is_some_and(|route| route.id == self.id)
+        {
+            routes.by_market.remove(&self.market);
+        }
+    }
+}
+
+/// One Pangea subscription for the live events of every market, handed to each market's
+/// pair. A pair joining with a market the subscription does not cover, or from a block
+/// it has already passed, starts it again from the earliest block any pair still needs;
+/// other pairs just take their events from the subscription as it goes, and events a
+/// pair already got are not sent to it twice. Failed subscriptions are retried with
+/// backoff, and one that delivers nothing at all for `stale_after` while the chain head
+/// moves on is restarted, however quiet any one market is.
+pub(crate) struct PangeaMux {
+    connector: Arc<PangeaConnector>,
+    routes: Mutex<Routes>,
+    /// Fired when a route needs the subscription restarted to cover it.
+    joined: Notify,
+    next_id: AtomicU64,
+    started: AtomicBool,
+    /// Events the subscriptions received, for any market.
+    received: AtomicU64,
+    stale_after: Option<Duration>,
+}
+
+impl PangeaMux {
+    pub fn new(connector: Arc<PangeaConnector>, stale_after: Option<Duration>) -> Self {
+        Self {
+            connector,
+            routes: Mutex::new(Routes::default()),
+            joined: Notify::new(),
+            next_id: AtomicU64::new(0),
+            started: AtomicBool::new(false),
+            received: AtomicU64::new(0),
+            stale_after,
+        }
+    }
+
+    /// Sends the events of `market` from `from_block` on to `events`, replacing any route
+    /// the market had.
+    pub fn route(
+        self: &Arc<Self>,
+        market: &str,
+        from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<RouteGuard, Error> {
+        H256::from_str(market)?;
+        let market = normalize_hex(market);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let restart = {
+            let mut routes = self.routes.lock().unwrap();
+            routes.by_market.insert(
+                market.clone(),
+                Route {
+                    id,
+                    from_block,
+                    sent: None,
+                    events,
+                },
+            );
+            match &routes.feed {
+                Feed::Idle => true,
+                Feed::Retrying => false,
+                Feed::Live(stream) => {
+                    !stream.markets.contains(&market) || from_block <= stream.position
+                }
+            }
+        };
+        if self.started.swap(true, Ordering::Relaxed) {
+            if restart {
+                self.joined.notify_one();
+            }
+        } else {
+            tokio::spawn(Arc::clone(self).run());
+        }
+        Ok(RouteGuard {
+            mux: Arc::clone(self),
+            market,
+            id,
+        })
+    }
+
+    async fn run(self: Arc<Self>) {
+        let mut retry_delay = Duration::from_secs(1);
+        loop {
+            let Some((markets, from_block)) = self.coverage() else {
+                self.joined.notified().await;
+                continue;
+            };
+            tokio::select! {
+                result = self.follow(&markets, from_block) => {
+                    self.routes.lock().unwrap().feed = Feed::Retrying;
+                    match result {
+                        Ok(delivered) => {
+                            info!("Shared Pangea subscription ended, reconnecting");
+                            if delivered {
+                                retry_delay = Duration::from_secs(1);
+                            }
+                        }
+                        Err(e) => error!("Shared Pangea subscription failed, retrying: {}", e),
+                    }
+                    sleep(jittered(retry_delay)).await;
+                    retry_delay = (retry_delay * 2).min(MAX_BACKOFF);
+                }
+                _ = self.joined.notified() => {
+                    info!("Market joined the shared Pangea subscription, re-subscribing");
+                }
+                _ = self.watch_for_stale_stream() => {
+                    warn!("No Pangea events for any market while the chain advanced, re-subscribing");
+                }
+            }
+        }
+    }
+
+    /// The routed markets and the earliest block they need, recorded as the stream
+    /// followed next; `None` without routes.
+    fn coverage(&self) -> Option<(Vec<String>, i64)> {
+        let mut routes = self.routes.lock().unwrap();
+        let Some(from_block) = routes.by_market.values().map(Route::resume_block).min() else {
+            routes.feed = Feed::Idle;
+            return None;
+        };
+        let markets: Vec<String> = routes.by_market.keys().cloned().collect();
+        routes.feed = Feed::Live(Stream {
+            markets: markets.iter().cloned().collect(),
+            position: from_block,
+        });
+        Some((markets, from_block))
+    }
+
+    /// Resolves once a whole `stale_after` period passes with no event received for any
+    /// market while the chain head moved on. Never resolves without a period.
+    async fn watch_for_stale_stream(&self) {
+        let Some(stale_after) = self.stale_after else {
+            return futures::future::pending().await;
+        };
+        loop {
+            let seen = self.received.load(Ordering::Relaxed);
+            let head = latest_block_height().await;
+            sleep(stale_after).await;
+            if self.received.load(Ordering::Relaxed) != seen {
+                continue;
+            }
+            match (head, latest_block_height().await) {
+                (Ok(before), Ok(after)) if after > before => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Follows `markets` from `from_block` until the subscription ends. Returns whether
+    /// any event was delivered.
+    async fn follow(&self, markets: &[String], from_block: i64) -> Result<bool, Error> {
+        let client = self.connector.connect().await?;
+        let request = GetSparkOrderRequest {
+            from_block: Bound::Exact(from_block),
+            to_block: Bound::Subscribe,
+            market_id__in: markets
+                .iter()
+                .map(|market| H256::from_str(market))
+                .collect::<Result<_, _>>()?,
+            chains: [fuel_chain()?].into(),
+            ..Default::default()
+        };
+
+        let subscription = timeout(
+            Duration::from_secs(10),
+            client.get_fuel_spark_orders_by_format(request, Format::JsonStream, true),
+        )
+        .await
+        .map_err(|_| Error::SubscriptionTimeout)??;
+        pangea_client::futures::pin_mut!(subscription);
+
+        let mut delivered = false;
+        while let Some(data) = subscription.next().await {
+            if let Ok(data) = data {
+                self.received.fetch_add(1, Ordering::Relaxed);
+                delivered |= self.dispatch(data).await;
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// Sends an event to the pair of its market. Returns whether it was sent.
+    async fn dispatch(&self, data: Vec<u8>) -> bool {
+        let event = decode(data);
+        let (market, position) = match &event {
+            SourceEvent::Order(order) => (
+                normalize_hex(&order.market_id),
+                Some((order.block_number, order.transaction_index, order.log_index)),
+            ),
+            SourceEvent::Malformed { payload, .. } => {
+                let market = serde_json::from_slice::<serde_json::Value>(payload)
+                    .ok()
+                    .and_then(|value| value["market_id"].as_str().map(normalize_hex));
+                let Some(market) = market else {
+                    warn!("Dropping a malformed Pangea event without a market id");
+                    return false;
+                };
+                (market, None)
+            }
+        };
+
+        let events = {
+            let mut routes = self.routes.lock().unwrap();
+            if let (Feed::Live(stream), Some((block, _, _))) = (&mut routes.feed, position) {
+                stream.position = stream.position.max(block);
+            }
+            let Some(route) = routes.by_market.get_mut(&market) else {
+                return false;
+            };
+            if let Some(position) = position {
+                if !route.take(position) {
+                    return false;
+                }
+            }
+            route.events.clone()
+        };
+        events.send(event).await.is_ok()
+    }
+}
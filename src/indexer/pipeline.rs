@@ -0,0 +1,830 @@
+use futures::{stream, StreamExt};
+use log::{error, info, warn};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::sleep;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::discovery::MarketDiscovery;
+use crate::indexer::file::FileSource;
+use crate::indexer::fuel_node::FuelNodeSource;
+use crate::indexer::indexer_api::{IndexerApi, IndexerApiSource};
+use crate::indexer::order_event_handler::{
+    handle_block_events, handle_book_events, position_fills, PangeaOrderEvent,
+};
+use crate::indexer::pangea::PangeaSource;
+use crate::indexer::source::{normalize_hex, EventSource, SourceEvent, SourceKind};
+use crate::indexer::status::SyncPhase;
+use crate::indexer::synthetic::SyntheticSource;
+use crate::monitor::alert::Alerter;
+use crate::storage::candles::CandleStore;
+use crate::storage::timeline::OperationalKind;
+use crate::storage::trading_engine::{TradingEngine, TradingPairConfig};
+
+const EVENT_BUFFER: usize = 1024;
+const CHUNK_ATTEMPTS: u32 = 3;
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+/// How long a live block stays open for more of its events once the stream goes quiet.
+const BLOCK_SETTLE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy)]
+struct IndexerSettings {
+    /// Blocks of history per request.
+    chunk_blocks: i64,
+    /// History requests in flight per pair.
+    concurrency: usize,
+    /// How long a live subscription may go without events while the chain advances
+    /// before it is torn down and re-established; `STREAM_STALE_SECS=0` turns this off.
+    stale_after: Option<Duration>,
+    /// Failed live reconnects in a row after which an alert goes out.
+    alert_after: u32,
+}
+
+impl IndexerSettings {
+    fn from_env() -> Self {
+        Self {
+            chunk_blocks: setting("BACKFILL_CHUNK_BLOCKS", 100000) as i64,
+            concurrency: setting("BACKFILL_CONCURRENCY", 4) as usize,
+            stale_after: stale_after(),
+            alert_after: setting("RECONNECT_ALERT_AFTER", 10) as u32,
+        }
+    }
+}
+
+/// How long a live subscription may go without events while the chain advances, from
+/// `STREAM_STALE_SECS` (300 by default, 0 for never).
+pub(crate) fn stale_after() -> Option<Duration> {
+    match ev("STREAM_STALE_SECS").ok().and_then(|v| v.parse().ok()) {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => Some(Duration::from_secs(300)),
+    }
+}
+
+fn setting(key: &str, default: u64) -> u64 {
+    ev(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&v| v > 0)
+        .unwrap_or(default)
+}
+
+/// Connects the sources the pairs in `configs` read from and indexes them, along with
+/// any markets `discovery` finds. Only the sources in use need their settings.
+/// `EVENT_SOURCE` names one source for every pair, e.g. `file` to replay recordings
+/// locally.
+pub async fn initialize_indexer(
+    mut configs: Vec<TradingPairConfig>,
+    trading_engine: Arc<TradingEngine>,
+    mut discovery: Option<MarketDiscovery>,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<(), Error> {
+    if let Ok(kind) = ev("EVENT_SOURCE") {
+        let kind: SourceKind = serde_json::from_value(serde_json::Value::String(kind))?;
+        for config in &mut configs {
+            config.source = Some(kind);
+        }
+        discovery = discovery.map(|discovery| discovery.with_source(kind));
+    }
+
+    let mut sources: HashMap<SourceKind, Arc<dyn EventSource>> = HashMap::new();
+    let kinds = configs
+        .iter()
+        .map(TradingPairConfig::source)
+        .chain(discovery.as_ref().map(MarketDiscovery::source));
+    for kind in kinds {
+        if sources.contains_key(&kind) {
+            continue;
+        }
+        let source: Arc<dyn EventSource> = match kind {
+            SourceKind::Pangea => Arc::new(PangeaSource::from_env()?),
+            SourceKind::FuelNode => Arc::new(FuelNodeSource::from_env()?),
+            SourceKind::Envio => Arc::new(IndexerApiSource::from_env(IndexerApi::Envio)?),
+            SourceKind::Subsquid => Arc::new(IndexerApiSource::from_env(IndexerApi::Subsquid)?),
+            SourceKind::File => Arc::new(FileSource::from_env()?),
+            SourceKind::Synthetic => Arc::new(SyntheticSource::from_env()?),
+        };
+        sources.insert(kind, source);
+    }
+
+    let discovered = discovery.map(|discovery| {
+        let (found, discovered) = mpsc::channel(EVENT_BUFFER);
+        tokio::spawn(discovery.run(Arc::clone(&trading_engine), found, shutdown.resubscribe()));
+        discovered
+    });
+    run_indexer(configs, trading_engine, sources, discovered, shutdown).await
+}
+
+/// Backfills and then follows every pair in `configs` from its source, and each pair
+/// received on `discovered` from when it arrives, until `shutdown` fires, then waits for
+/// every pair to stop.
+pub async fn run_indexer(
+    configs: Vec<TradingPairConfig>,
+    trading_engine: Arc<TradingEngine>,
+    sources: HashMap<SourceKind, Arc<dyn EventSource>>,
+    mut discovered: Option<mpsc::Receiver<TradingPairConfig>>,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<(), Error> {
+    let settings = IndexerSettings::from_env();
+    let mut tasks = Vec::new();
+    let spawn = |config: TradingPairConfig, shutdown: broadcast::Receiver<()>| {
+        let store = match trading_engine.get_store(&config.symbol) {
+            Some(s) => s,
+            None => {
+                error!("No CandleStore found for symbol {}", config.symbol);
+                return None;
+            }
+        };
+        let Some(source) = sources.get(&config.source()) else {
+            error!("No event source connected for symbol {}", config.symbol);
+            return None;
+        };
+
+        Some(tokio::spawn(supervise_pair(
+            config,
+            store,
+            Arc::clone(&trading_engine),
+            Arc::clone(source),
+            settings,
+            shutdown,
+        )))
+    };
+    for config in configs {
+        tasks.extend(spawn(config, shutdown.resubscribe()));
+    }
+
+    while let Some(found) = discovered.as_mut() {
+        tokio::select! {
+            config = found.recv() => match config {
+                Some(config) => tasks.extend(spawn(config, shutdown.resubscribe())),
+                None => discovered = None,
+            },
+            _ = shutdown.recv() => break,
+        }
+    }
+
+    futures::future::join_all(tasks).await;
+    info!("All indexer tasks completed.");
+    Ok(())
+}
+
+/// How far a pair's indexing got, carried across restarts so a restarted pair fetches
+/// only the history it is missing and re-applies nothing.
+#[derive(Debug, Clone, Copy)]
+struct PairProgress {
+    /// Last block of history applied.
+    backfilled_to: i64,
+    /// Block that history is fetched up to and live events follow, once chosen.
+    live_from: Option<i64>,
+}
+
+/// What stopped a pair's indexing from outside.
+enum Interruption {
+    Shutdown,
+    Pause,
+}
+
+/// Runs a pair's indexing and restarts it with backoff whenever it fails, e.g. on a
+/// source outage outlasting the chunk retries, recording each failure in the engine's
+/// indexer status. While the pair is paused from the admin API its indexing is stopped,
+/// and resuming picks up where it left off.
+async fn supervise_pair(
+    config: TradingPairConfig,
+    store: Arc<CandleStore>,
+    trading_engine: Arc<TradingEngine>,
+    source: Arc<dyn EventSource>,
+    settings: IndexerSettings,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let status = &trading_engine.indexer_status;
+    let timeline = &trading_engine.timeline;
+    let mut paused = trading_engine.pair_controls.watch(&config.symbol);
+    // A standby promoted while its primary backfilled finishes that history, up to
+    // where the primary's live events began.
+    let mut progress = PairProgress {
+        backfilled_to: config.start_block - 1,
+        live_from: store.backfill_target(),
+    };
+    let mut retry_delay = Duration::from_secs(1);
+
+    loop {
+        if *paused.borrow_and_update() {
+            status.paused(&config.symbol);
+            timeline.record(&config.symbol, OperationalKind::Paused, "Indexing paused");
+            info!("Indexing of {} paused", config.symbol);
+            tokio::select! {
+                _ = until_paused(&mut paused, false) => {}
+                _ = shutdown.recv() => {
+                    status.stopped(&config.symbol);
+                    return;
+                }
+            }
+            timeline.record(&config.symbol, OperationalKind::Resumed, "Indexing resumed");
+            info!("Indexing of {} resumed", config.symbol);
+        }
+
+        status.running(&config.symbol);
+        let started = tokio::time::Instant::now();
+        // The pair's own stop signal, sent on shutdown or when it is paused.
+        let (stop, mut stopped) = broadcast::channel(1);
+        let run = process_events_for_pair(
+            &config,
+            &store,
+            &trading_engine,
+            &source,
+            settings,
+            &mut progress,
+            &mut stopped,
+        );
+        tokio::pin!(run);
+        let (result, interruption) = tokio::select! {
+            // The run goes first, so it is listening before a stop can be sent.
+            biased;
+            result = &mut run => (result, None),
+            _ = shutdown.recv() => {
+                let _ = stop.send(());
+                (run.await, Some(Interruption::Shutdown))
+            }
+            _ = until_paused(&mut paused, true) => {
+                let _ = stop.send(());
+                (run.await, Some(Interruption::Pause))
+            }
+        };
+        if let Err(e) = trading_engine.save_pair_state() {
+            error!("Failed to save pair state: {}", e);
+        }
+
+        let e = match (result, interruption) {
+            (_, Some(Interruption::Pause)) => continue,
+            (Err(e), None) => e,
+            (_, _) => {
+                status.stopped(&config.symbol);
+                return;
+            }
+        };
+
+        if started.elapsed() > MAX_RESTART_BACKOFF {
+            retry_delay = Duration::from_secs(1);
+        }
+        error!(
+            "Indexer for {} failed, restarting in {:?}: {}",
+            config.symbol, retry_delay, e
+        );
+        let next_attempt_at = chrono::Utc::now().timestamp() + retry_delay.as_secs() as i64;
+        status.failed(&config.symbol, e.to_string(), next_attempt_at);
+        timeline.record(
+            &config.symbol,
+            OperationalKind::IndexerRestart,
+            format!("Indexer failed: {}", e),
+        );
+        tokio::select! {
+            _ = sleep(retry_delay) => {}
+            _ = shutdown.recv() => {
+                status.stopped(&config.symbol);
+                return;
+            }
+        }
+        retry_delay = (retry_delay * 2).min(MAX_RESTART_BACKOFF);
+    }
+}
+
+/// Resolves once the pause switch reads `state`.
+async fn until_paused(paused: &mut watch::Receiver<bool>, state: bool) {
+    if paused.wait_for(|&paused| paused == state).await.is_err() {
+        futures::future::pending().await
+    }
+}
+
+async fn process_events_for_pair(
+    config: &TradingPairConfig,
+    store: &Arc<CandleStore>,
+    trading_engine: &Arc<TradingEngine>,
+    source: &Arc<dyn EventSource>,
+    settings: IndexerSettings,
+    progress: &mut PairProgress,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<(), Error> {
+    // Taken before the first await, so a stop sent while the head is fetched reaches the
+    // live task too.
+    let live_shutdown = shutdown.resubscribe();
+    let head = source.latest_block().await?;
+    observe_chain(&config.symbol, source, store, head).await?;
+    let latest_block = *progress.live_from.get_or_insert(head);
+
+    // Held-back trades are only applied here, once their blocks are checked against the
+    // chain. Without trades no events would move the head either, and the lag reported
+    // in the status would not grow.
+    let head_tracker = tokio::spawn(track_chain_head(
+        config.symbol.clone(),
+        Arc::clone(source),
+        Arc::clone(store),
+    ));
+
+    // Follow the chain from the head right away, so the forming candle is current while
+    // history loads behind it. After a restart, live events resume where they stopped.
+    let live = tokio::spawn({
+        let (config, store) = (config.live_contract(), Arc::clone(store));
+        let (trading_engine, source) = (Arc::clone(trading_engine), Arc::clone(source));
+        let resume_from = latest_block.max(store.last_block());
+        let shutdown = live_shutdown;
+        async move {
+            listen_for_new_deltas(
+                &config,
+                &store,
+                &trading_engine,
+                &source,
+                resume_from,
+                settings,
+                shutdown,
+            )
+            .await
+        }
+    });
+
+    info!(
+        "Fetching historical data for {} from block {} to {}",
+        config.symbol,
+        progress.backfilled_to + 1,
+        latest_block
+    );
+    store.set_backfill_checkpoint(Some(progress.backfilled_to));
+    store.set_backfill_target(Some(latest_block));
+    let status = &trading_engine.indexer_status;
+    status.phase(&config.symbol, SyncPhase::Backfill);
+    let backfilled = tokio::select! {
+        result = backfill_history(
+            config,
+            store,
+            trading_engine,
+            source,
+            settings,
+            progress,
+            latest_block,
+        ) => Some(result),
+        // The live task sees the signal too and stops by itself.
+        _ = shutdown.recv() => None,
+    };
+    match backfilled {
+        Some(Ok(())) => {
+            store.set_backfill_checkpoint(None);
+            store.set_backfill_target(None);
+            if let Some(pair) = trading_engine.pair(&config.symbol) {
+                pair.book.mark_complete();
+                if let Some(open_interest) = &pair.open_interest {
+                    open_interest.mark_complete();
+                }
+            }
+            status.phase(&config.symbol, SyncPhase::Live);
+            info!(
+                "Completed historical data fetch for {} up to block {}",
+                config.symbol, latest_block
+            );
+        }
+        Some(Err(e)) => {
+            live.abort();
+            head_tracker.abort();
+            return Err(e);
+        }
+        None => info!("Backfill of {} interrupted by shutdown", config.symbol),
+    }
+
+    let result = match live.await {
+        Ok(result) => result,
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    };
+    head_tracker.abort();
+    result
+}
+
+/// Fetches the blocks after `progress.backfilled_to` up to `latest_block` from each of
+/// the pair's contracts in chunks, several at a time, and applies them in block order.
+/// After each chunk the pair is checkpointed, so a restart resumes from the last applied
+/// chunk, and its progress is reported to the indexer status. Applying yields to the
+/// scheduler after every block, so live events queued meanwhile go ahead of the rest of
+/// the history.
+async fn backfill_history(
+    config: &TradingPairConfig,
+    store: &Arc<CandleStore>,
+    trading_engine: &TradingEngine,
+    source: &Arc<dyn EventSource>,
+    settings: IndexerSettings,
+    progress: &mut PairProgress,
+    latest_block: i64,
+) -> Result<(), Error> {
+    // Each contract of the pair serves its own block range; chunks never span two.
+    let chunks: Vec<_> = config
+        .contracts()
+        .into_iter()
+        .flat_map(|contract| {
+            let from = (progress.backfilled_to + 1).max(contract.from_block);
+            let to = contract.to_block.unwrap_or(latest_block).min(latest_block);
+            let market = config.with_contract(&contract);
+            (from..=to)
+                .step_by(settings.chunk_blocks as usize)
+                .map(move |from| {
+                    let chunk_to = (from + settings.chunk_blocks - 1).min(to);
+                    (market.clone(), from, chunk_to)
+                })
+        })
+        .collect();
+    let status = &trading_engine.indexer_status;
+    let report = |backfilled_to| {
+        status.backfilled(
+            &config.symbol,
+            config.start_block,
+            backfilled_to,
+            latest_block,
+        );
+    };
+    report(progress.backfilled_to);
+
+    // Fetching runs on a task of its own, so source streams keep being read while chunks
+    // are written; the bounded channel holds it back when writes fall behind.
+    let (sender, mut fetched) = mpsc::channel(settings.concurrency);
+    tokio::spawn({
+        let source = Arc::clone(source);
+        async move {
+            let mut chunks = stream::iter(chunks)
+                .map(|(market, from, to)| {
+                    let source = Arc::clone(&source);
+                    async move { fetch_chunk(&market, &source, from, to).await }
+                })
+                .buffered(settings.concurrency);
+            while let Some(chunk) = chunks.next().await {
+                let failed = chunk.is_err();
+                if sender.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        }
+    });
+
+    while let Some(chunk) = fetched.recv().await {
+        let (to, events) = chunk?;
+        let mut block: Vec<PangeaOrderEvent> = Vec::new();
+        for event in events {
+            let Some(event) = order_event(event, trading_engine, &config.symbol) else {
+                continue;
+            };
+            if block
+                .first()
+                .is_some_and(|first| first.block_number != event.block_number)
+            {
+                let events = std::mem::take(&mut block);
+                apply_block(events, store, trading_engine, &config.symbol, false).await;
+                tokio::task::yield_now().await;
+            }
+            block.push(event);
+        }
+        apply_block(block, store, trading_engine, &config.symbol, false).await;
+        store.mark_block(to);
+        store.set_backfill_checkpoint(Some(to));
+        progress.backfilled_to = to;
+        report(to);
+        info!("Backfilled {} up to block {}", config.symbol, to);
+    }
+    Ok(())
+}
+
+async fn track_chain_head(symbol: String, source: Arc<dyn EventSource>, store: Arc<CandleStore>) {
+    loop {
+        let observed = match source.latest_block().await {
+            Ok(head) => observe_chain(&symbol, &source, &store, head).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = observed {
+            warn!("Failed to follow the chain head for {}: {}", symbol, e);
+        }
+        sleep(HEAD_POLL_INTERVAL).await;
+    }
+}
+
+/// Drops the held-back trades of blocks the chain no longer has, with those of every
+/// later block, then moves `store` to `head`, applying the trades that became final.
+/// Sources without block hashes are trusted.
+async fn observe_chain(
+    symbol: &str,
+    source: &Arc<dyn EventSource>,
+    store: &CandleStore,
+    head: i64,
+) -> Result<(), Error> {
+    for (block, hash) in store.pending_blocks() {
+        if block > head {
+            break;
+        }
+        let Some(canonical) = source.block_hash(block).await? else {
+            break;
+        };
+        if normalize_hex(&canonical) != normalize_hex(&hash) {
+            let dropped = store.drop_reorged(block);
+            warn!(
+                "Block {} of {} was reorganized; dropped {} held-back trades from it on",
+                block, symbol, dropped
+            );
+            break;
+        }
+    }
+    store.observe_head(head);
+    Ok(())
+}
+
+/// Collects one chunk of history, retrying it from scratch when the source fails.
+async fn fetch_chunk(
+    config: &TradingPairConfig,
+    source: &Arc<dyn EventSource>,
+    from: i64,
+    to: i64,
+) -> Result<(i64, Vec<SourceEvent>), Error> {
+    let mut attempt = 1;
+    loop {
+        let (sender, mut receiver) = mpsc::channel(EVENT_BUFFER);
+        let collect = async {
+            let mut events = Vec::new();
+            while let Some(event) = receiver.recv().await {
+                events.push(event);
+            }
+            events
+        };
+        let (result, events) =
+            tokio::join!(source.fetch_historical(config, from, to, sender), collect);
+        match result {
+            Ok(()) => return Ok((to, events)),
+            Err(e) if attempt < CHUNK_ATTEMPTS => {
+                warn!(
+                    "Fetching blocks {}..={} for {} failed, retrying: {}",
+                    from, to, config.symbol, e
+                );
+                sleep(Duration::from_secs(1 << attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Why a live subscription was left.
+enum SubscriptionEnd {
+    Closed,
+    Stale,
+    Shutdown,
+}
+
+async fn listen_for_new_deltas(
+    config: &TradingPairConfig,
+    candle_store: &Arc<CandleStore>,
+    trading_engine: &Arc<TradingEngine>,
+    source: &Arc<dyn EventSource>,
+    last_processed_block: i64,
+    settings: IndexerSettings,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<(), Error> {
+    let mut retry_delay = Duration::from_secs(1);
+    let max_backoff = Duration::from_secs(60);
+    let last_block = Arc::new(AtomicI64::new(last_processed_block));
+    let alerter = Alerter::from_env();
+    // Subscriptions in a row that ended before applying a block.
+    let mut failures = 0;
+    // A source that restarts stale streams itself is not second-guessed per pair.
+    let stale_after = settings
+        .stale_after
+        .filter(|_| !source.restarts_stale_streams());
+
+    loop {
+        let resume_from = last_block.load(Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+        // Writes happen on a task of their own, so a slow store holds the subscription
+        // back through the bounded channel instead of stalling its socket reads.
+        let writer = tokio::spawn(apply_events(
+            receiver,
+            Arc::clone(candle_store),
+            Arc::clone(trading_engine),
+            config.symbol.clone(),
+            Arc::clone(&last_block),
+        ));
+
+        let end = tokio::select! {
+            result = source.subscribe(config, resume_from + 1, sender) => {
+                match result {
+                    Ok(()) => info!("Subscription for {} ended, reconnecting", config.symbol),
+                    Err(e) => error!("Failed to subscribe to new deltas, retrying: {}", e),
+                }
+                SubscriptionEnd::Closed
+            }
+            _ = watch_for_stale_stream(source, &last_block, stale_after) => SubscriptionEnd::Stale,
+            _ = shutdown.recv() => SubscriptionEnd::Shutdown,
+        };
+        // The subscription is gone with its sender, so the writer stops once it applied
+        // everything already delivered and the next subscription resumes after that.
+        if let Err(e) = writer.await {
+            error!("Event writer for {} failed: {}", config.symbol, e);
+        }
+
+        if let SubscriptionEnd::Shutdown = end {
+            info!(
+                "Stopped following {} at block {}",
+                config.symbol,
+                last_block.load(Ordering::Relaxed)
+            );
+            return Ok(());
+        }
+
+        if last_block.load(Ordering::Relaxed) > resume_from {
+            if failures >= settings.alert_after {
+                trading_engine.timeline.record(
+                    &config.symbol,
+                    OperationalKind::LiveRecovered,
+                    "Live events are flowing again",
+                );
+                alerter
+                    .send(&format!(
+                        "Live events of {} are flowing again",
+                        config.symbol
+                    ))
+                    .await;
+            }
+            failures = 0;
+            retry_delay = Duration::from_secs(1);
+        } else {
+            failures += 1;
+            if failures == settings.alert_after {
+                trading_engine.timeline.record(
+                    &config.symbol,
+                    OperationalKind::LiveInterrupted,
+                    format!("Live subscription failed {} times in a row", failures),
+                );
+                alerter
+                    .send(&format!(
+                        "Live subscription of {} failed {} times in a row",
+                        config.symbol, failures
+                    ))
+                    .await;
+            }
+        }
+        trading_engine
+            .indexer_status
+            .reconnected(&config.symbol, failures);
+
+        if let SubscriptionEnd::Stale = end {
+            warn!(
+                "No events for {} since block {} while the chain advanced, re-subscribing",
+                config.symbol,
+                last_block.load(Ordering::Relaxed)
+            );
+            continue;
+        }
+        tokio::select! {
+            _ = sleep(jittered(retry_delay)) => {}
+            _ = shutdown.recv() => return Ok(()),
+        }
+        retry_delay = (retry_delay * 2).min(max_backoff);
+    }
+}
+
+/// Somewhere between half of `delay` and all of it, so pairs that lost their streams
+/// together do not all reconnect at the same moment.
+pub(crate) fn jittered(delay: Duration) -> Duration {
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+/// Resolves once a whole `stale_after` period passes with no new block applied from the
+/// subscription while the chain head moved on. Never resolves without a period.
+async fn watch_for_stale_stream(
+    source: &Arc<dyn EventSource>,
+    last_block: &AtomicI64,
+    stale_after: Option<Duration>,
+) {
+    let Some(stale_after) = stale_after else {
+        return futures::future::pending().await;
+    };
+    loop {
+        let seen = last_block.load(Ordering::Relaxed);
+        let head = source.latest_block().await;
+        sleep(stale_after).await;
+        if last_block.load(Ordering::Relaxed) != seen {
+            continue;
+        }
+        match (head, source.latest_block().await) {
+            (Ok(before), Ok(after)) if after > before => return,
+            _ => {}
+        }
+    }
+}
+
+/// Applies live events block by block until the source drops its sender, recording the
+/// last block applied in `last_block`. A block is applied once an event of a later block
+/// arrives or the stream stays quiet for `BLOCK_SETTLE`.
+async fn apply_events(
+    mut receiver: mpsc::Receiver<SourceEvent>,
+    candle_store: Arc<CandleStore>,
+    trading_engine: Arc<TradingEngine>,
+    symbol: String,
+    last_block: Arc<AtomicI64>,
+) {
+    let mut block: Vec<PangeaOrderEvent> = Vec::new();
+    loop {
+        let next = if block.is_empty() {
+            receiver.recv().await
+        } else {
+            match tokio::time::timeout(BLOCK_SETTLE, receiver.recv()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    let events = std::mem::take(&mut block);
+                    if let Some(applied) =
+                        apply_block(events, &candle_store, &trading_engine, &symbol, true).await
+                    {
+                        last_block.fetch_max(applied, Ordering::Relaxed);
+                    }
+                    continue;
+                }
+            }
+        };
+        let Some(event) = next else {
+            break;
+        };
+        let Some(event) = order_event(event, &trading_engine, &symbol) else {
+            continue;
+        };
+        if block
+            .first()
+            .is_some_and(|first| first.block_number != event.block_number)
+        {
+            let events = std::mem::take(&mut block);
+            if let Some(applied) =
+                apply_block(events, &candle_store, &trading_engine, &symbol, true).await
+            {
+                last_block.fetch_max(applied, Ordering::Relaxed);
+            }
+        }
+        block.push(event);
+    }
+    if let Some(applied) = apply_block(block, &candle_store, &trading_engine, &symbol, true).await {
+        last_block.fetch_max(applied, Ordering::Relaxed);
+    }
+}
+
+/// Unwraps an order event, recording it when the engine records events, and
+/// dead-letters payloads the source could not decode.
+fn order_event(
+    event: SourceEvent,
+    trading_engine: &TradingEngine,
+    symbol: &str,
+) -> Option<PangeaOrderEvent> {
+    match event {
+        SourceEvent::Order(order_event) => {
+            if let Some(recorder) = &trading_engine.recorder {
+                recorder.record(symbol, &order_event);
+            }
+            Some(*order_event)
+        }
+        SourceEvent::Malformed { payload, error } => {
+            error!("Failed to deserialize order event: {}", error);
+            trading_engine.dead_letters.push(symbol, &payload, error);
+            None
+        }
+    }
+}
+
+/// Applies the events of one block, returning its number unless there were none.
+async fn apply_block(
+    events: Vec<PangeaOrderEvent>,
+    candle_store: &Arc<CandleStore>,
+    trading_engine: &TradingEngine,
+    symbol: &str,
+    live: bool,
+) -> Option<i64> {
+    let first = events.first()?;
+    let block = first.block_number;
+    let event_time_ms = first.event_time_ms();
+    let count = events.len();
+    if let Some(pair) = trading_engine.pair(symbol) {
+        let book = &pair.book;
+        let top = handle_book_events(book, &events, candle_store.base_asset());
+        let mid = top.filter(|_| book.is_complete()).and_then(|top| top.mid());
+        if let (Some(mid), Some(mid_store)) = (mid, &pair.mid_store) {
+            mid_store.add_trade(symbol, mid, 0, event_time_ms);
+        }
+        if let Some(open_interest) = &pair.open_interest {
+            open_interest.apply(position_fills(&events, candle_store.base_asset()), live);
+        }
+    }
+    let quarantined = handle_block_events(candle_store.clone(), events, symbol.to_string()).await;
+    for (event, reason) in quarantined {
+        let payload = serde_json::to_vec(&event).unwrap_or_default();
+        trading_engine.dead_letters.push(symbol, &payload, reason);
+    }
+    trading_engine.indexer_status.applied(symbol, event_time_ms);
+    if live {
+        // Events of a block share its timestamp, so they all waited as long.
+        let latency_ms = chrono::Utc::now().timestamp_millis() - event_time_ms;
+        for _ in 0..count {
+            candle_store.latency.record_ms(latency_ms);
+        }
+    }
+    Some(block)
+}
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::error::Error;
+use crate::indexer::order_event_handler::PangeaOrderEvent;
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// Backend a pair's events are read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    #[default]
+    Pangea,
+    /// A Fuel node's GraphQL API, configured by `FUEL_NODE_URL`.
+    FuelNode,
+    /// An Envio HyperIndex deployment, configured by `ENVIO_URL`.
+    Envio,
+    /// A Subsquid deployment, configured by `SUBSQUID_URL`.
+    Subsquid,
+    /// NDJSON files of recorded events under `EVENT_FILE_PATH`, replayed once.
+    File,
+    /// Random-walk trades generated in process, for development.
+    Synthetic,
+}
+
+/// What a source delivers for a market: a decoded order event, or a payload it could
+/// not decode, which ends up in the dead-letter queue.
+#[derive(Debug)]
+pub enum SourceEvent {
+    Order(Box<PangeaOrderEvent>),
+    Malformed { payload: Vec<u8>, error: String },
+}
+
+/// Lowercase hex without the `0x` prefix, as ids and addresses are compared.
+pub fn normalize_hex(value: &str) -> String {
+    value.trim_start_matches("0x").to_ascii_lowercase()
+}
+
+/// A provider of order events. Sources only fetch and decode; applying events to
+/// candles, dead-lettering and reconnect policy live in the indexer pipeline.
+#[async_trait]
+pub trait EventSource: Send + Sync {
+    /// The newest block the source can serve history up to.
+    async fn latest_block(&self) -> Result<i64, Error>;
+
+    /// The hash of the canonical block at `height`, which held-back trades are checked
+    /// against before they become final; `None` when the source cannot tell.
+    async fn block_hash(&self, _height: i64) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+
+    /// Whether the source restarts its live subscriptions once they go stale, leaving
+    /// pairs that are merely quiet alone.
+    fn restarts_stale_streams(&self) -> bool {
+        false
+    }
+
+    /// Sends every event of `market` in the inclusive block range `from_block..=to_block`.
+    async fn fetch_historical(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error>;
+
+    /// Sends live events of `market` from `from_block` on, returning when the
+    /// subscription ends.
+    async fn subscribe(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error>;
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairTaskState {
+    Running,
+    /// Failed and waiting out its backoff before the next attempt.
+    Restarting,
+    /// Finished without error; it is not restarted.
+    Stopped,
+    /// Stopped from the admin API until it is resumed.
+    Paused,
+}
+
+/// Whether a pair is still loading history or only following the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Backfill,
+    Live,
+}
+
+/// How much of a pair's history has been applied, counted in blocks from its start
+/// block to the chain head seen when the backfill began.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BackfillProgress {
+    pub from_block: i64,
+    pub target_block: i64,
+    pub backfilled_to: i64,
+    pub percent: f64,
+}
+
+/// Health of one pair's indexer task as seen by its supervisor.
+#[derive(Debug, Clone, Serialize)]
+pub struct PairTaskStatus {
+    pub state: PairTaskState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    /// Seconds since the epoch.
+    pub last_error_at: Option<i64>,
+    /// Seconds since the epoch.
+    pub next_attempt_at: Option<i64>,
+    pub phase: SyncPhase,
+    /// Live subscriptions re-established after closing or going stale.
+    pub reconnects: u32,
+    /// Live subscriptions in a row that ended before applying a block.
+    pub consecutive_failures: u32,
+    /// Event time of the last block applied, in milliseconds since the epoch.
+    pub last_event_at: Option<i64>,
+    pub backfill: Option<BackfillProgress>,
+}
+
+/// Indexer task status per pair of one engine.
+#[derive(Debug, Default)]
+pub struct IndexerStatus {
+    pairs: RwLock<BTreeMap<String, PairTaskStatus>>,
+}
+
+impl IndexerStatus {
+    pub fn running(&self, symbol: &str) {
+        self.update(symbol, |status| {
+            status.state = PairTaskState::Running;
+            status.next_attempt_at = None;
+        });
+    }
+
+    pub fn failed(&self, symbol: &str, error: String, next_attempt_at: i64) {
+        self.update(symbol, |status| {
+            status.state = PairTaskState::Restarting;
+            status.restarts += 1;
+            status.last_error = Some(error);
+            status.last_error_at = Some(chrono::Utc::now().timestamp());
+            status.next_attempt_at = Some(next_attempt_at);
+        });
+    }
+
+    pub fn stopped(&self, symbol: &str) {
+        self.update(symbol, |status| status.state = PairTaskState::Stopped);
+    }
+
+    pub fn paused(&self, symbol: &str) {
+        self.update(symbol, |status| {
+            status.state = PairTaskState::Paused;
+            status.next_attempt_at = None;
+        });
+    }
+
+    pub fn phase(&self, symbol: &str, phase: SyncPhase) {
+        self.update(symbol, |status| status.phase = phase);
+    }
+
+    pub fn reconnected(&self, symbol: &str, consecutive_failures: u32) {
+        self.update(symbol, |status| {
+            status.reconnects += 1;
+            status.consecutive_failures = consecutive_failures;
+        });
+    }
+
+    pub fn applied(&self, symbol: &str, event_time_ms: i64) {
+        self.update(symbol, |status| {
+            status.last_event_at = status.last_event_at.max(Some(event_time_ms));
+        });
+    }
+
+    pub fn backfilled(&self, symbol: &str, from_block: i64, backfilled_to: i64, target_block: i64) {
+        let total = target_block - from_block + 1;
+        let percent = if total > 0 {
+            (backfilled_to - from_block + 1).clamp(0, total) as f64 * 100.0 / total as f64
+        } else {
+            100.0
+        };
+        self.update(symbol, |status| {
+            status.backfill = Some(BackfillProgress {
+                from_block,
+                target_block,
+                backfilled_to,
+                percent,
+            });
+        });
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, PairTaskStatus> {
+        self.pairs.read().unwrap().clone()
+    }
+
+    fn update(&self, symbol: &str, change: impl FnOnce(&mut PairTaskStatus)) {
+        let mut pairs = self.pairs.write().unwrap();
+        let status = pairs
+            .entry(symbol.to_string())
+            .or_insert_with(|| PairTaskStatus {
+                state: PairTaskState::Running,
+                restarts: 0,
+                last_error: None,
+                last_error_at: None,
+                next_attempt_at: None,
+                phase: SyncPhase::Backfill,
+                reconnects: 0,
+                consecutive_failures: 0,
+                last_event_at: None,
+                backfill: None,
+            });
+        change(status);
+    }
+}
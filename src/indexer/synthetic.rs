@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::order_event_handler::PangeaOrderEvent;
+use crate::indexer::source::{EventSource, SourceEvent};
+use crate::storage::trading_engine::TradingPairConfig;
+
+const DEFAULT_HISTORY_HOURS: i64 = 24;
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// How a pair's synthetic market moves, in display units.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SyntheticSettings {
+    /// Price the walk starts from.
+    pub price: f64,
+    /// Standard deviation of the daily log return, e.g. 0.05 for 5%.
+    pub volatility: f64,
+    /// Average number of trades a minute; arrivals are Poisson distributed.
+    pub trades_per_minute: f64,
+    /// Mean trade size; sizes are exponentially distributed around it.
+    pub mean_size: f64,
+}
+
+impl Default for SyntheticSettings {
+    fn default() -> Self {
+        Self {
+            price: 100.0,
+            volatility: 0.05,
+            trades_per_minute: 30.0,
+            mean_size: 1.0,
+        }
+    }
+}
+
+/// One generated trade.
+struct SyntheticTrade {
+    block: i64,
+    price: u128,
+    size: u128,
+}
+
+/// A pair's random walk, generated up to `next_block` and kept, so history and live
+/// trades continue one another whatever order they are asked for in.
+struct Walk {
+    rng: StdRng,
+    price: f64,
+    next_block: i64,
+    trades: Vec<SyntheticTrade>,
+}
+
+/// Random-walk trades for running the full server without Pangea or Fuel access. Blocks
+/// are seconds: the chain starts `SYNTHETIC_HISTORY_HOURS` (24 by default) before the
+/// process did, so pairs with a `start_block` of 0 get that much history. Each pair
+/// moves by its `synthetic` settings and the same symbol always yields the same walk.
+pub struct SyntheticSource {
+    started: Instant,
+    started_at: i64,
+    history_secs: i64,
+    walks: Mutex<HashMap<String, Walk>>,
+}
+
+impl SyntheticSource {
+    pub fn from_env() -> Result<Self, Error> {
+        let history_hours = match ev("SYNTHETIC_HISTORY_HOURS") {
+            Ok(hours) => hours.parse()?,
+            Err(_) => DEFAULT_HISTORY_HOURS,
+        };
+        Ok(Self {
+            started: Instant::now(),
+            started_at: chrono::Utc::now().timestamp(),
+            history_secs: history_hours * 3600,
+            walks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn head(&self) -> i64 {
+        self.history_secs + self.started.elapsed().as_secs() as i64
+    }
+
+    /// The events of `market` in `from_block..=to_block`, generating the walk that far.
+    fn events(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+    ) -> Vec<PangeaOrderEvent> {
+        let to_block = to_block.min(self.head());
+        let mut walks = self.walks.lock().unwrap();
+        let walk = walks
+            .entry(market.symbol.clone())
+            .or_insert_with(|| Walk::new(market));
+        walk.advance(market, to_block);
+
+        let lo = walk
+            .trades
+            .partition_point(|trade| trade.block < from_block);
+        let hi = walk.trades.partition_point(|trade| trade.block <= to_block);
+        walk.trades[lo..hi]
+            .iter()
+            .enumerate()
+            .map(|(index, trade)| PangeaOrderEvent {
+                chain: 0,
+                block_number: trade.block,
+                block_hash: format!("0x{:064x}", trade.block),
+                block_timestamp: self.started_at - self.history_secs + trade.block,
+                transaction_hash: format!("0x{:064x}", lo + index),
+                transaction_index: 0,
+                log_index: 0,
+                market_id: market.contract_id.clone(),
+                order_id: format!("0x{:064x}", lo + index),
+                event_type: Some("Trade".to_string()),
+                asset: None,
+                amount: Some(trade.size),
+                asset_type: None,
+                order_type: None,
+                price: Some(trade.price),
+                user: None,
+                order_matcher: None,
+                owner: None,
+                limit_type: None,
+            })
+            .collect()
+    }
+}
+
+impl Walk {
+    fn new(market: &TradingPairConfig) -> Self {
+        let mut hasher = DefaultHasher::new();
+        market.symbol.hash(&mut hasher);
+        Self {
+            rng: StdRng::seed_from_u64(hasher.finish()),
+            price: market.synthetic.clone().unwrap_or_default().price,
+            next_block: 0,
+            trades: Vec::new(),
+        }
+    }
+
+    fn advance(&mut self, market: &TradingPairConfig, to_block: i64) {
+        let settings = market.synthetic.clone().unwrap_or_default();
+        let per_second = (settings.trades_per_minute / 60.0).max(0.0);
+        // Spread the daily variance over the trades of a day.
+        let step = settings.volatility / (per_second * SECONDS_PER_DAY).max(1.0).sqrt();
+        let price_scale = 10f64.powi(market.price_decimals() as i32);
+        let size_scale = 10f64.powi(market.size_decimals() as i32);
+
+        while self.next_block <= to_block {
+            for _ in 0..self.poisson(per_second) {
+                self.price *= (step * self.standard_normal()).exp();
+                let size = -settings.mean_size * (1.0 - self.rng.gen::<f64>()).ln();
+                self.trades.push(SyntheticTrade {
+                    block: self.next_block,
+                    price: (self.price * price_scale).max(1.0) as u128,
+                    size: (size * size_scale).max(1.0) as u128,
+                });
+            }
+            self.next_block += 1;
+        }
+    }
+
+    fn poisson(&mut self, mean: f64) -> u32 {
+        let threshold = (-mean).exp();
+        let (mut count, mut product) = (0, self.rng.gen::<f64>());
+        while product > threshold {
+            count += 1;
+            product *= self.rng.gen::<f64>();
+        }
+        count
+    }
+
+    /// Box-Muller transform.
+    fn standard_normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.rng.gen::<f64>();
+        let u2 = self.rng.gen::<f64>();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+}
+
+#[async_trait]
+impl EventSource for SyntheticSource {
+    async fn latest_block(&self) -> Result<i64, Error> {
+        Ok(self.head())
+    }
+
+    async fn fetch_historical(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        to_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        for event in self.events(market, from_block, to_block) {
+            if events
+                .send(SourceEvent::Order(Box::new(event)))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends each second's trades as the second passes.
+    async fn subscribe(
+        &self,
+        market: &TradingPairConfig,
+        from_block: i64,
+        events: mpsc::Sender<SourceEvent>,
+    ) -> Result<(), Error> {
+        let mut next = from_block;
+        loop {
+            let head = self.head();
+            for event in self.events(market, next, head) {
+                if events
+                    .send(SourceEvent::Order(Box::new(event)))
+                    .await
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+            next = next.max(head + 1);
+            tokio::select! {
+                _ = sleep(Duration::from_secs(1)) => {}
+                _ = events.closed() => return Ok(()),
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Technical indicators over a series of values, oldest first. Each output lines up
+//! with its input, with `None` where the indicator does not have enough history yet.
+
+/// Simple moving average: the mean of the last `period` values.
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut averages = Vec::with_capacity(values.len());
+    let mut sum = 0.0;
+    for (i, value) in values.iter().enumerate() {
+        sum += value;
+        if i >= period {
+            sum -= values[i - period];
+        }
+        averages.push((period > 0 && i + 1 >= period).then(|| sum / period as f64));
+    }
+    averages
+}
+
+/// Exponential moving average with smoothing `2 / (period + 1)`, seeded with the simple
+/// average of the first `period` values.
+pub fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut average = None;
+    sma(values, period)
+        .into_iter()
+        .zip(values)
+        .map(|(seed, value)| {
+            average = match average {
+                Some(previous) => Some(previous + alpha * (value - previous)),
+                None => seed,
+            };
+            average
+        })
+        .collect()
+}
+
+/// Exponential moving average of a series that starts undefined, e.g. another
+/// indicator's output, seeded once `period` values are defined.
+fn ema_of_defined(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let start = values
+        .iter()
+        .position(Option::is_some)
+        .unwrap_or(values.len());
+    let defined: Vec<f64> = values[start..].iter().map(|v| v.unwrap_or(0.0)).collect();
+    let mut averages = vec![None; start];
+    averages.extend(ema(&defined, period));
+    averages
+}
+
+/// Wilder's relative strength index from 0 to 100: average gains against average losses
+/// over `period` changes, seeded with their simple averages and smoothed by
+/// `1 / period` after that.
+pub fn rsi(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut strengths = vec![None; values.len()];
+    if period == 0 || values.len() <= period {
+        return strengths;
+    }
+    let (mut gain, mut loss) = (0.0, 0.0);
+    for (i, pair) in values.windows(2).enumerate() {
+        let change = pair[1] - pair[0];
+        let (up, down) = (change.max(0.0), (-change).max(0.0));
+        if i < period {
+            gain += up / period as f64;
+            loss += down / period as f64;
+        } else {
+            gain = (gain * (period - 1) as f64 + up) / period as f64;
+            loss = (loss * (period - 1) as f64 + down) / period as f64;
+        }
+        if i + 1 >= period {
+            strengths[i + 1] = Some(if loss == 0.0 {
+                100.0
+            } else {
+                100.0 - 100.0 / (1.0 + gain / loss)
+            });
+        }
+    }
+    strengths
+}
+
+/// Moving average convergence/divergence lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macd {
+    /// Fast EMA minus slow EMA.
+    pub macd: Vec<Option<f64>>,
+    /// EMA of `macd`.
+    pub signal: Vec<Option<f64>>,
+    /// `macd` minus `signal`.
+    pub histogram: Vec<Option<f64>>,
+}
+
+pub fn macd(values: &[f64], fast: usize, slow: usize, signal: usize) -> Macd {
+    let macd: Vec<_> = ema(values, fast)
+        .into_iter()
+        .zip(ema(values, slow))
+        .map(|(fast, slow)| Some(fast? - slow?))
+        .collect();
+    let signal = ema_of_defined(&macd, signal);
+    let histogram = macd
+        .iter()
+        .zip(&signal)
+        .map(|(macd, signal)| Some((*macd)? - (*signal)?))
+        .collect();
+    Macd {
+        macd,
+        signal,
+        histogram,
+    }
+}
+
+/// Bollinger Bands: the simple moving average and bands `width` standard deviations of
+/// the same window (taken over the whole window, not as a sample) above and below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bands {
+    pub middle: Vec<Option<f64>>,
+    pub upper: Vec<Option<f64>>,
+    pub lower: Vec<Option<f64>>,
+}
+
+pub fn bollinger(values: &[f64], period: usize, width: f64) -> Bands {
+    let middle = sma(values, period);
+    let deviations: Vec<_> = middle
+        .iter()
+        .enumerate()
+        .map(|(i, mean)| {
+            let mean = (*mean)?;
+            let window = &values[i + 1 - period..=i];
+            let variance = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / period as f64;
+            Some(variance.sqrt())
+        })
+        .collect();
+    let band = |sign: f64| -> Vec<_> {
+        middle
+            .iter()
+            .zip(&deviations)
+            .map(|(mean, deviation)| Some((*mean)? + sign * width * (*deviation)?))
+            .collect()
+    };
+    Bands {
+        upper: band(1.0),
+        lower: band(-1.0),
+        middle,
+    }
+}
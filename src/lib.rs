@@ -0,0 +1,23 @@
+/// Renko bricks and range bars, which chart price movement rather than time.
+pub mod bricks;
+pub mod cli;
+pub mod config;
+pub mod error;
+/// gRPC access to candles for backend consumers, served next to the HTTP API.
+pub mod grpc;
+pub mod indexer;
+/// Technical indicators computed from stored candles for lightweight clients.
+pub mod indicators;
+pub mod metrics;
+pub mod monitor;
+pub mod replication;
+pub mod storage;
+pub mod web;
+
+/// Typed async client for this server's HTTP API, for Rust bots and services.
+#[cfg(feature = "client")]
+pub mod client;
+
+/// Deterministic trade fixtures and golden candles that stores are checked against.
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
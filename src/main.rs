@@ -1,62 +1,175 @@
-use config::env::ev;
-use error::Error;
-use indexer::pangea::initialize_pangea_indexer;
+use spark_candles::cli;
+use spark_candles::config::env::ev;
+use spark_candles::config::server::ServerConfig;
+use spark_candles::error::Error;
+use spark_candles::grpc;
+use spark_candles::indexer::discovery::MarketDiscovery;
+use spark_candles::indexer::metadata::apply_contract_metadata;
+use spark_candles::indexer::pipeline::initialize_indexer;
+use spark_candles::monitor::activity::run_activity_monitor;
+use spark_candles::monitor::memory::run_memory_budget;
+use spark_candles::replication::standby::{resume_configs, run_standby};
+use spark_candles::replication::Promotion;
+use spark_candles::storage::dead_letter::DeadLetterStore;
+use spark_candles::storage::pair_state::PairStateFile;
+use spark_candles::storage::recorder::EventRecorder;
+use spark_candles::storage::tenants::TenantRegistry;
+use spark_candles::storage::trading_engine::{TradingEngine, TradingPairConfig};
+use spark_candles::web::format::NumberFormat;
+use spark_candles::web::handover;
+use spark_candles::web::server::rocket;
 use std::sync::Arc;
-use storage::trading_engine::{TradingEngine, TradingPairConfig};
 use tokio::signal;
 use tokio::sync::broadcast;
-use web::server::rocket;
-
-pub mod config;
-pub mod error;
-pub mod indexer;
-pub mod storage;
-pub mod web;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     dotenv::dotenv().ok();
     env_logger::init();
 
-    let configs = TradingEngine::load_config("config.json")?;
-    let trading_engine = Arc::new(TradingEngine::new(configs.clone()));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("simulate") {
+        return cli::simulate::run(&args[1..]);
+    }
+
+    let (mut configs, chain) = TradingEngine::load_config("config.json")?;
+    apply_contract_metadata(&mut configs).await;
+    let trading_engine = Arc::new(
+        TradingEngine::new(configs, chain, DeadLetterStore::from_env()?)
+            .with_pair_state(PairStateFile::from_env())?
+            .with_recorder(EventRecorder::from_env()?),
+    );
+
+    let tenants = TenantRegistry::load_from_env()?;
+    let server_config = ServerConfig::load_from_env()?;
 
     let (shutdown_tx, _) = broadcast::channel(1);
 
-    let port = ev("SERVER_PORT")?.parse()?;
-    let rocket_task =
-        spawn_rocket_server(port, Arc::clone(&trading_engine), shutdown_tx.subscribe());
+    let primary_url = ev("STANDBY_OF").ok();
+    let promotion = Arc::new(Promotion::new(primary_url.is_some()));
 
-    let indexer_task = spawn_indexer(
-        configs,
+    // Candles live in memory, so skipping ahead to the persisted block only pays off
+    // once they outlive a restart; until then history is re-fetched from `start_block`.
+    let resume = ev("RESUME_FROM_LAST_BLOCK").is_ok_and(|value| value == "true");
+    let indexer_configs = |engine: &TradingEngine| match resume {
+        true => resume_configs(engine),
+        false => engine
+            .pairs()
+            .iter()
+            .map(|pair| pair.config.clone())
+            .collect(),
+    };
+
+    // Discovered markets join the default engine; tenants index only their own config.
+    let discovery = MarketDiscovery::from_env()?;
+    let mut indexer_tasks = vec![match primary_url {
+        Some(primary_url) => spawn_standby(
+            primary_url,
+            Arc::clone(&trading_engine),
+            discovery,
+            Arc::clone(&promotion),
+            shutdown_tx.subscribe(),
+        ),
+        None => spawn_indexer(
+            indexer_configs(&trading_engine),
+            Arc::clone(&trading_engine),
+            discovery,
+            shutdown_tx.subscribe(),
+        ),
+    }];
+    for (name, tenant) in tenants.iter() {
+        println!("Starting indexer for tenant {}", name);
+        indexer_tasks.push(spawn_indexer(
+            indexer_configs(&tenant.engine),
+            Arc::clone(&tenant.engine),
+            None,
+            shutdown_tx.subscribe(),
+        ));
+    }
+
+    let engines: Vec<_> = std::iter::once(Arc::clone(&trading_engine))
+        .chain(tenants.iter().map(|(_, tenant)| Arc::clone(&tenant.engine)))
+        .collect();
+    indexer_tasks.push(spawn_pair_state_writer(
+        engines.clone(),
+        shutdown_tx.subscribe(),
+    ));
+    indexer_tasks.push(tokio::spawn(run_memory_budget(
+        engines.clone(),
+        shutdown_tx.subscribe(),
+    )));
+    indexer_tasks.push(tokio::spawn(run_activity_monitor(
+        engines,
+        shutdown_tx.subscribe(),
+    )));
+
+    let grpc_task = match ev("GRPC_PORT") {
+        Ok(grpc_port) => Some(spawn_grpc_server(
+            grpc_port.parse()?,
+            Arc::clone(&trading_engine),
+            server_config.number_format,
+            shutdown_tx.subscribe(),
+        )),
+        Err(_) => None,
+    };
+
+    let port = ev("SERVER_PORT")?.parse()?;
+    let rocket_task = spawn_rocket_server(
+        port,
         Arc::clone(&trading_engine),
+        tenants,
+        server_config,
+        promotion,
         shutdown_tx.subscribe(),
     );
 
-    signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
-    println!("Ctrl+C received! Initiating shutdown...");
+    wait_for_stop_signal().await;
+    println!("Stop signal received! Initiating shutdown...");
 
     drop(shutdown_tx);
 
     if let Err(e) = rocket_task.await {
         eprintln!("Rocket server error: {:?}", e);
     }
-    if let Err(e) = indexer_task.await {
-        eprintln!("Indexer error: {:?}", e);
+    if let Some(grpc_task) = grpc_task {
+        if let Err(e) = grpc_task.await {
+            eprintln!("gRPC server error: {:?}", e);
+        }
+    }
+    for indexer_task in indexer_tasks {
+        if let Err(e) = indexer_task.await {
+            eprintln!("Indexer error: {:?}", e);
+        }
     }
 
     println!("Application has shut down gracefully.");
     Ok(())
 }
 
+/// Ctrl+C, or SIGTERM as sent by systemd and Kubernetes when a new version takes over.
+async fn wait_for_stop_signal() {
+    let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+        .expect("failed to listen for SIGTERM");
+    tokio::select! {
+        result = signal::ctrl_c() => result.expect("failed to listen for Ctrl+C"),
+        _ = terminate.recv() => {}
+    }
+}
+
 fn spawn_rocket_server(
     port: u16,
     trading_engine: Arc<TradingEngine>,
+    tenants: TenantRegistry,
+    server_config: ServerConfig,
+    promotion: Arc<Promotion>,
     mut shutdown: broadcast::Receiver<()>,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         println!("Starting Rocket server on port {}", port);
-        let rocket = rocket(port, trading_engine);
+        let rocket = rocket(port, trading_engine, tenants, server_config, promotion);
+        if handover::enabled() {
+            return handover::launch(rocket, port, shutdown).await;
+        }
 
         tokio::select! {
             result = rocket.launch() => {
@@ -71,13 +184,80 @@ fn spawn_rocket_server(
     })
 }
 
+fn spawn_grpc_server(
+    port: u16,
+    trading_engine: Arc<TradingEngine>,
+    number_format: NumberFormat,
+    shutdown: broadcast::Receiver<()>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        println!("Starting gRPC server on port {}", port);
+        if let Err(e) = grpc::serve(port, trading_engine, number_format, shutdown).await {
+            eprintln!("Error running gRPC server: {:?}", e);
+        }
+    })
+}
+
+/// Flushes changed pair state every few seconds and once more on shutdown.
+fn spawn_pair_state_writer(
+    engines: Vec<Arc<TradingEngine>>,
+    mut shutdown: broadcast::Receiver<()>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let save_all = || {
+            for engine in &engines {
+                if let Err(e) = engine.save_pair_state() {
+                    eprintln!("Failed to save pair state: {:?}", e);
+                }
+            }
+        };
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(10)) => save_all(),
+                _ = shutdown.recv() => {
+                    save_all();
+                    break;
+                }
+            }
+        }
+    })
+}
+
 fn spawn_indexer(
     configs: Vec<TradingPairConfig>,
     trading_engine: Arc<TradingEngine>,
+    discovery: Option<MarketDiscovery>,
+    mut shutdown: broadcast::Receiver<()>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = initialize_indexer(configs, trading_engine, discovery, &mut shutdown).await
+        {
+            eprintln!("Indexer error: {:?}", e);
+        }
+    })
+}
+
+fn spawn_standby(
+    primary_url: String,
+    trading_engine: Arc<TradingEngine>,
+    discovery: Option<MarketDiscovery>,
+    promotion: Arc<Promotion>,
     mut shutdown: broadcast::Receiver<()>,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
-        if let Err(e) = initialize_pangea_indexer(configs, trading_engine, &mut shutdown).await {
+        tokio::select! {
+            result = run_standby(Arc::clone(&trading_engine), primary_url, promotion) => {
+                if let Err(e) = result {
+                    eprintln!("Standby error: {:?}", e);
+                    return;
+                }
+            }
+            _ = shutdown.recv() => return,
+        }
+
+        let configs = resume_configs(&trading_engine);
+        if let Err(e) = initialize_indexer(configs, trading_engine, discovery, &mut shutdown).await
+        {
             eprintln!("Indexer error: {:?}", e);
         }
     })
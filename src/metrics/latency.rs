@@ -0,0 +1,110 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Upper bounds (seconds) of the latency histogram buckets; a final bucket catches the rest.
+pub const LATENCY_BUCKETS: [f64; 10] = [0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// Distribution of the delay between a trade's `block_timestamp` and the moment
+/// it was applied to the candles.
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    count: AtomicU64,
+    sum_ms: AtomicU64,
+    max_ms: AtomicU64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LatencySnapshot {
+    pub count: u64,
+    pub mean_seconds: f64,
+    pub max_seconds: f64,
+    pub p50_seconds: Option<f64>,
+    pub p90_seconds: Option<f64>,
+    pub p99_seconds: Option<f64>,
+    /// `(upper bound in seconds, cumulative count)`; `None` bound is +Inf.
+    pub buckets: Vec<(Option<f64>, u64)>,
+}
+
+impl LatencyHistogram {
+    pub fn record_ms(&self, latency_ms: i64) {
+        let latency_ms = latency_ms.max(0) as u64;
+        let seconds = latency_ms as f64 / 1000.0;
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ms.fetch_add(latency_ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(latency_ms, Ordering::Relaxed);
+    }
+
+    /// Cumulative bucket counts, Prometheus-style.
+    pub fn cumulative(&self) -> Vec<(Option<f64>, u64)> {
+        let mut total = 0;
+        self.buckets
+            .iter()
+            .enumerate()
+            .map(|(i, bucket)| {
+                total += bucket.load(Ordering::Relaxed);
+                (LATENCY_BUCKETS.get(i).copied(), total)
+            })
+            .collect()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn sum_seconds(&self) -> f64 {
+        self.sum_ms.load(Ordering::Relaxed) as f64 / 1000.0
+    }
+
+    /// Share of events applied within `seconds`, at bucket resolution.
+    pub fn share_within(&self, seconds: f64) -> Option<f64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let within = self
+            .cumulative()
+            .into_iter()
+            .take_while(|(bound, _)| bound.is_some_and(|b| b <= seconds))
+            .last()
+            .map_or(0, |(_, total)| total);
+        Some(within as f64 / count as f64)
+    }
+
+    /// Upper bound of the bucket containing quantile `q`; `None` when empty or in the overflow bucket.
+    fn quantile(&self, q: f64) -> Option<f64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = (q * count as f64).ceil() as u64;
+        self.cumulative()
+            .into_iter()
+            .find(|(_, total)| *total >= rank)
+            .and_then(|(bound, _)| bound)
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let count = self.count();
+        LatencySnapshot {
+            count,
+            mean_seconds: if count == 0 {
+                0.0
+            } else {
+                self.sum_seconds() / count as f64
+            },
+            max_seconds: self.max_ms.load(Ordering::Relaxed) as f64 / 1000.0,
+            p50_seconds: self.quantile(0.5),
+            p90_seconds: self.quantile(0.9),
+            p99_seconds: self.quantile(0.99),
+            buckets: self.cumulative(),
+        }
+    }
+}
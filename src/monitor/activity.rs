@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::monitor::alert::Alerter;
+use crate::storage::trading_engine::TradingEngine;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Alerts when a market goes without trades for its `inactive_after_secs`, and again
+/// when trading resumes.
+pub async fn run_activity_monitor(
+    engines: Vec<Arc<TradingEngine>>,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let alerter = Alerter::from_env();
+    let mut inactive: HashMap<(usize, String), bool> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+            _ = shutdown.recv() => break,
+        }
+
+        for (index, engine) in engines.iter().enumerate() {
+            for symbol in engine.symbols() {
+                let Some(activity) = engine.activity(&symbol) else {
+                    continue;
+                };
+                let previous = inactive.insert((index, symbol.clone()), activity.inactive);
+                match (previous, activity.inactive) {
+                    (Some(false), true) => {
+                        let since = activity
+                            .last_trade_at
+                            .map_or("startup".to_string(), |at| at.to_string());
+                        alerter
+                            .send(&format!(
+                                "Market {} has had no trades since {}",
+                                symbol, since
+                            ))
+                            .await;
+                    }
+                    (Some(true), false) => {
+                        alerter
+                            .send(&format!("Market {} is trading again", symbol))
+                            .await;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
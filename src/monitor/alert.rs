@@ -0,0 +1,37 @@
+use log::{error, warn};
+use serde_json::json;
+
+use crate::config::env::ev;
+
+/// Operational alerts: always logged, and posted as `{"text": ...}` to `ALERT_WEBHOOK_URL`
+/// (Slack-compatible) when it is set.
+pub struct Alerter {
+    webhook: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Alerter {
+    pub fn from_env() -> Self {
+        Self {
+            webhook: ev("ALERT_WEBHOOK_URL").ok(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn send(&self, message: &str) {
+        warn!("{}", message);
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        let result = self
+            .client
+            .post(webhook)
+            .json(&json!({ "text": message }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            error!("Failed to deliver alert: {}", e);
+        }
+    }
+}
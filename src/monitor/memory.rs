@@ -0,0 +1,77 @@
+use log::warn;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::config::env::ev;
+use crate::storage::trading_engine::TradingEngine;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keeps the stores of all `engines` within `MEMORY_BUDGET_BYTES`, split evenly across
+/// pairs. While the total is over budget, every pair above its share, heaviest first,
+/// drops its oldest archived trades, which only sends trade-built responses back to the
+/// candles, and then the oldest candles of its finest intervals. Does nothing without a
+/// budget.
+pub async fn run_memory_budget(
+    engines: Vec<Arc<TradingEngine>>,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let Some(budget) = ev("MEMORY_BUDGET_BYTES")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+    else {
+        return;
+    };
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+            _ = shutdown.recv() => break,
+        }
+
+        let mut stores: Vec<_> = engines
+            .iter()
+            .flat_map(|engine| engine.pairs())
+            .map(|pair| {
+                let bytes = pair.store.footprint().bytes;
+                (pair.config.symbol.clone(), pair.store.clone(), bytes)
+            })
+            .collect();
+        let total: usize = stores.iter().map(|(_, _, bytes)| bytes).sum();
+        if stores.is_empty() || total <= budget {
+            continue;
+        }
+
+        let share = budget / stores.len();
+        stores.sort_by_key(|&(_, _, bytes)| std::cmp::Reverse(bytes));
+        let mut excess = total - budget;
+        for (symbol, store, bytes) in stores {
+            if excess == 0 || bytes <= share {
+                break;
+            }
+            let over = (bytes - share).min(excess);
+            let trades = store.trades.evict(over);
+            let freed = bytes.saturating_sub(store.footprint().bytes);
+            let candles = match over.saturating_sub(freed) {
+                0 => 0,
+                rest => store.evict_finest(rest),
+            };
+            if trades == 0 && candles == 0 {
+                continue;
+            }
+            let freed = bytes.saturating_sub(store.footprint().bytes);
+            excess = excess.saturating_sub(freed);
+            warn!(
+                "Memory budget exceeded: dropped {} archived trades and {} old candles of {} ({} bytes)",
+                trades, candles, symbol, freed
+            );
+        }
+        if excess > 0 {
+            warn!(
+                "Still {} bytes over the memory budget after evicting trades and candles",
+                excess
+            );
+        }
+    }
+}
@@ -0,0 +1,18 @@
+pub mod standby;
+
+use tokio::sync::Notify;
+
+/// Lets `POST /admin/promote` hand a standby over to live indexing.
+pub struct Promotion {
+    pub standby: bool,
+    pub notify: Notify,
+}
+
+impl Promotion {
+    pub fn new(standby: bool) -> Self {
+        Self {
+            standby,
+            notify: Notify::new(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use log::{error, info};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::replication::Promotion;
+use crate::storage::candles::StoreDelta;
+use crate::storage::trading_engine::{TradingEngine, TradingPairConfig};
+
+/// Pulls candle deltas from the primary at `primary_url` until promoted,
+/// keeping this instance's stores warm for failover.
+pub async fn run_standby(
+    trading_engine: Arc<TradingEngine>,
+    primary_url: String,
+    promotion: Arc<Promotion>,
+) -> Result<(), Error> {
+    let client = reqwest::Client::new();
+    let admin_key = ev("ADMIN_API_KEY").unwrap_or_default();
+    let poll_interval = Duration::from_secs(
+        ev("STANDBY_POLL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2),
+    );
+    let mut cursors: HashMap<String, u64> = HashMap::new();
+
+    info!("Running as warm standby of {}", primary_url);
+    loop {
+        for pair in trading_engine.pairs() {
+            let (symbol, store) = (&pair.config.symbol, &pair.store);
+            let since = cursors.get(symbol).copied().unwrap_or(0);
+            let request = client
+                .get(format!(
+                    "{}/admin/replication/delta",
+                    primary_url.trim_end_matches('/')
+                ))
+                .query(&[("symbol", symbol.clone()), ("since", since.to_string())])
+                .header("X-Admin-Key", &admin_key);
+
+            match fetch_delta(request).await {
+                Ok(delta) => {
+                    cursors.insert(symbol.clone(), delta.revision);
+                    store.apply_delta(delta);
+                }
+                Err(e) => error!("Failed to pull delta for {} from primary: {}", symbol, e),
+            }
+        }
+
+        tokio::select! {
+            _ = promotion.notify.notified() => {
+                info!("Standby promoted to primary.");
+                return Ok(());
+            }
+            _ = sleep(poll_interval) => {}
+        }
+    }
+}
+
+async fn fetch_delta(request: reqwest::RequestBuilder) -> Result<StoreDelta, Error> {
+    Ok(request.send().await?.error_for_status()?.json().await?)
+}
+
+/// Pair configs adjusted to continue after each store's checkpoint block, whether
+/// replicated from a primary or restored from the pair state file, so history not yet
+/// backfilled and trades still held back are fetched again.
+pub fn resume_configs(trading_engine: &TradingEngine) -> Vec<TradingPairConfig> {
+    trading_engine
+        .pairs()
+        .iter()
+        .map(|pair| {
+            let mut config = pair.config.clone();
+            let checkpoint = pair.store.checkpoint_block();
+            if checkpoint >= config.start_block {
+                config.start_block = checkpoint + 1;
+            }
+            config
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+use crate::storage::candles::Candle;
+
+/// Events buffered per subscriber before the slowest one starts missing them.
+const EVENT_CAPACITY: usize = 4096;
+
+/// A change to the newest bar of a series.
+#[derive(Debug, Clone)]
+pub enum CandleEvent {
+    /// The newest bar of `interval` changed or opened.
+    Updated {
+        symbol: String,
+        interval: u64,
+        candle: Candle,
+    },
+    /// A newer bar opened, so `candle` has reached its final state.
+    Closed {
+        symbol: String,
+        interval: u64,
+        candle: Candle,
+    },
+}
+
+impl CandleEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            CandleEvent::Updated { symbol, .. } | CandleEvent::Closed { symbol, .. } => symbol,
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        match self {
+            CandleEvent::Updated { interval, .. } | CandleEvent::Closed { interval, .. } => {
+                *interval
+            }
+        }
+    }
+
+    pub fn candle(&self) -> &Candle {
+        match self {
+            CandleEvent::Updated { candle, .. } | CandleEvent::Closed { candle, .. } => candle,
+        }
+    }
+
+    /// Whether the event belongs to the series of `symbol` at `interval`.
+    pub fn is_for(&self, symbol: &str, interval: u64) -> bool {
+        self.symbol() == symbol && self.interval() == interval
+    }
+}
+
+/// Publishes bar updates and closes of every pair of an engine as trades are recorded, so
+/// streaming endpoints, sinks and alerts follow candles without polling the stores.
+/// Nothing is tracked while nobody listens; a subscriber that falls more than
+/// `EVENT_CAPACITY` events behind is told how many it missed and should re-read the
+/// store.
+#[derive(Debug)]
+pub struct CandleEvents {
+    sender: broadcast::Sender<CandleEvent>,
+    /// Newest bar published per symbol and interval.
+    newest: Mutex<HashMap<(String, u64), Candle>>,
+}
+
+impl Default for CandleEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_CAPACITY).0,
+            newest: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl CandleEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<CandleEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Whether anyone listens, forgetting what was published once nobody does.
+    pub(crate) fn is_listened(&self) -> bool {
+        let listened = self.sender.receiver_count() > 0;
+        if !listened {
+            self.newest.lock().unwrap().clear();
+        }
+        listened
+    }
+
+    /// Publishes what changed in a series given its newest bar and the bar before it.
+    pub(crate) fn observe(
+        &self,
+        symbol: &str,
+        interval: u64,
+        newest: Candle,
+        previous: Option<&Candle>,
+    ) {
+        let mut published = self.newest.lock().unwrap();
+        let key = (symbol.to_string(), interval);
+        if let Some(last) = published.get(&key) {
+            if last.same_contents(&newest) {
+                return;
+            }
+            if last.timestamp < newest.timestamp {
+                // The bar before the newest is the last one published unless several
+                // opened at once; then the last published state is the best known.
+                let closed = previous
+                    .filter(|previous| previous.timestamp == last.timestamp)
+                    .unwrap_or(last);
+                let _ = self.sender.send(CandleEvent::Closed {
+                    symbol: symbol.to_string(),
+                    interval,
+                    candle: closed.clone(),
+                });
+            }
+        }
+        let _ = self.sender.send(CandleEvent::Updated {
+            symbol: symbol.to_string(),
+            interval,
+            candle: newest.clone(),
+        });
+        published.insert(key, newest);
+    }
+}
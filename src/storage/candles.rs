@@ -1,127 +1,1502 @@
-use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
-use std::collections::HashMap;
-use std::sync::RwLock;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::watch;
 
-#[derive(Debug, Clone)]
+use crate::metrics::latency::LatencyHistogram;
+use crate::storage::candle_events::CandleEvents;
+use crate::storage::finality::PendingTrades;
+use crate::storage::import::{
+    ConflictPolicy, ImportAction, ImportChange, ImportReport, ImportedCandle, RejectedCandle,
+};
+use crate::storage::interval::{period_end, period_start, IntervalPyramid, SUB_MINUTE_INTERVALS};
+use crate::storage::pair_state::FirstTrade;
+use crate::storage::trades::{PriceFilter, Trade, TradeArchive, WashTradePolicy};
+use crate::storage::trim_front;
+
+/// Prices and volumes are raw on-chain integers; conversion to decimals happens at the API boundary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Candle {
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64,
+    pub open: u128,
+    pub high: u128,
+    pub low: u128,
+    pub close: u128,
+    pub volume: u128,
+    /// Sum of `price * amount` over the bucket's trades in raw units (price scale times size scale).
+    /// This is the quote-denominated volume and the VWAP numerator.
+    pub quote_volume: u128,
+    pub trade_count: u64,
+    /// Base and quote volume of detected self-trades, included in `volume` and
+    /// `quote_volume`; zero unless the pair tracks wash trades.
+    #[serde(default)]
+    pub wash_volume: u128,
+    #[serde(default)]
+    pub wash_quote_volume: u128,
     pub timestamp: DateTime<Utc>,
+    /// Event times in milliseconds of the earliest and latest trades in the bucket;
+    /// `None` for gap-filled candles.
+    pub first_trade_at: Option<i64>,
+    pub last_trade_at: Option<i64>,
+    /// Store revision of the last change to this candle, used for replication deltas.
+    #[serde(default)]
+    pub revision: u64,
+}
+
+impl Candle {
+    fn from_trade(timestamp: DateTime<Utc>, trade: &Trade) -> Self {
+        let quote_volume = trade.price.saturating_mul(trade.volume);
+        Self {
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.volume,
+            quote_volume,
+            trade_count: 1,
+            wash_volume: if trade.wash { trade.volume } else { 0 },
+            wash_quote_volume: if trade.wash { quote_volume } else { 0 },
+            timestamp,
+            first_trade_at: Some(trade.event_time),
+            last_trade_at: Some(trade.event_time),
+            revision: 0,
+        }
+    }
+
+    fn flat(timestamp: DateTime<Utc>, price: u128) -> Self {
+        Self {
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0,
+            quote_volume: 0,
+            trade_count: 0,
+            wash_volume: 0,
+            wash_quote_volume: 0,
+            timestamp,
+            first_trade_at: None,
+            last_trade_at: None,
+            revision: 0,
+        }
+    }
+
+    pub fn is_gap(&self) -> bool {
+        self.last_trade_at.is_none()
+    }
+
+    /// Whether the bucket's period has ended by `now`; late trades can still amend it.
+    pub fn is_closed(&self, interval: u64, now: DateTime<Utc>) -> bool {
+        period_end(self.timestamp, interval) <= now
+    }
+
+    /// Volume and quote volume without detected self-trades.
+    pub fn clean_volume(&self) -> (u128, u128) {
+        (
+            self.volume.saturating_sub(self.wash_volume),
+            self.quote_volume.saturating_sub(self.wash_quote_volume),
+        )
+    }
+
+    /// Volume-weighted average price in raw price units.
+    pub fn vwap(&self) -> Option<u128> {
+        (self.volume > 0).then(|| self.quote_volume / self.volume)
+    }
+
+    /// Combines time-ordered candles into one covering `timestamp`. Gap candles only
+    /// contribute when the whole bucket is a gap.
+    fn aggregate<'a>(
+        timestamp: DateTime<Utc>,
+        children: impl IntoIterator<Item = &'a Candle>,
+    ) -> Option<Candle> {
+        let mut children = children.into_iter();
+        let mut candle = children.next()?.clone();
+        candle.timestamp = timestamp;
+        for child in children {
+            candle.revision = candle.revision.max(child.revision);
+            if child.is_gap() {
+                continue;
+            }
+            if candle.is_gap() {
+                candle = Candle {
+                    timestamp,
+                    revision: candle.revision,
+                    ..child.clone()
+                };
+                continue;
+            }
+            candle.high = candle.high.max(child.high);
+            candle.low = candle.low.min(child.low);
+            candle.volume = candle.volume.saturating_add(child.volume);
+            candle.quote_volume = candle.quote_volume.saturating_add(child.quote_volume);
+            candle.trade_count += child.trade_count;
+            candle.wash_volume = candle.wash_volume.saturating_add(child.wash_volume);
+            candle.wash_quote_volume = candle
+                .wash_quote_volume
+                .saturating_add(child.wash_quote_volume);
+            candle.close = child.close;
+            candle.last_trade_at = child.last_trade_at;
+        }
+        Some(candle)
+    }
+
+    /// Same contents, ignoring the replication revision.
+    pub(crate) fn same_contents(&self, other: &Candle) -> bool {
+        self.timestamp == other.timestamp
+            && self.open == other.open
+            && self.high == other.high
+            && self.low == other.low
+            && self.close == other.close
+            && self.volume == other.volume
+            && self.quote_volume == other.quote_volume
+            && self.trade_count == other.trade_count
+            && self.wash_volume == other.wash_volume
+            && self.wash_quote_volume == other.wash_quote_volume
+            && self.first_trade_at == other.first_trade_at
+            && self.last_trade_at == other.last_trade_at
+    }
+
+    /// Merges a trade into the candle regardless of arrival order: open and close
+    /// follow the earliest and latest event times, ties resolved by arrival.
+    fn merge_trade(&mut self, trade: &Trade) {
+        let (Some(first), Some(last)) = (self.first_trade_at, self.last_trade_at) else {
+            *self = Self::from_trade(self.timestamp, trade);
+            return;
+        };
+        let &Trade {
+            price,
+            volume,
+            event_time,
+            wash,
+        } = trade;
+
+        let quote_volume = price.saturating_mul(volume);
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.volume = self.volume.saturating_add(volume);
+        self.quote_volume = self.quote_volume.saturating_add(quote_volume);
+        self.trade_count += 1;
+        if wash {
+            self.wash_volume = self.wash_volume.saturating_add(volume);
+            self.wash_quote_volume = self.wash_quote_volume.saturating_add(quote_volume);
+        }
+        if event_time < first {
+            self.open = price;
+            self.first_trade_at = Some(event_time);
+        }
+        if event_time >= last {
+            self.close = price;
+            self.last_trade_at = Some(event_time);
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct CandleStore {
     pub candles: RwLock<HashMap<String, HashMap<u64, Vec<Candle>>>>,
+    /// Block-to-candle latency of live events.
+    pub latency: LatencyHistogram,
+    /// Raw trades behind the candles, kept for reconciliation.
+    pub trades: TradeArchive,
+    pyramid: IntervalPyramid,
+    /// Sub-minute intervals, written per trade without gap filling and kept briefly.
+    sub_minute: Vec<u64>,
+    /// Whether missing periods are stored as flat candles; otherwise levels stay sparse.
+    fill_gaps: bool,
+    wash_trades: Option<WashTradePolicy>,
+    /// Asset whose Trade legs are counted when a fill emits one per asset.
+    base_asset: Option<String>,
+    price_filter: Option<PriceFilter>,
+    /// Raw price units per display unit.
+    price_scale: f64,
+    /// Trades kept out of the candles by the price filter.
+    quarantined: AtomicU64,
+    /// Oldest retained candle per symbol and interval once retention has dropped history.
+    horizons: Mutex<HashMap<(String, u64), DateTime<Utc>>>,
+    revision: AtomicU64,
+    last_block: AtomicI64,
+    /// Newest block known to exist on chain, which decides what is final.
+    chain_head: AtomicI64,
+    /// Trades from blocks not yet final, applied once they are.
+    pending: Mutex<PendingTrades>,
+    /// Last block backfilled while history loads behind live events, `i64::MAX` otherwise.
+    backfill_checkpoint: AtomicI64,
+    /// Block history is fetched up to while it loads behind live events, `i64::MIN`
+    /// otherwise.
+    backfill_target: AtomicI64,
+    /// Carries the store revision after every change, for streaming subscribers.
+    changes: watch::Sender<u64>,
+    /// Where bar updates and closes of recorded trades are published.
+    events: Option<Arc<CandleEvents>>,
+    /// Latest trade event time in milliseconds, `i64::MIN` before any trade.
+    last_trade_at: AtomicI64,
+    first_trade: RwLock<Option<FirstTrade>>,
+    /// Candles dropped to stay within the memory budget.
+    evicted: AtomicU64,
+    /// Revision of the last change that removed candles other than by trimming old ones,
+    /// which a standby behind it cannot follow by upserts.
+    removed: AtomicU64,
+}
+
+/// Candles each sub-minute interval keeps, about 28 hours of 1s bars.
+const MAX_SUB_MINUTE_CANDLES: usize = 100000;
+/// Candles every interval keeps however tight the memory budget gets.
+const MIN_RETAINED_CANDLES: usize = 1440;
+
+enum LevelUpdate {
+    Set(DateTime<Utc>, Option<Candle>),
+    Merge(DateTime<Utc>),
+}
+
+/// Candles changed since a revision, shipped from a primary to warm standbys.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoreDelta {
+    pub revision: u64,
+    /// Whether this is everything the primary holds rather than the changes, replacing
+    /// the standby's candles and trades: on the first pull, and whenever candles were
+    /// removed after the standby's cursor.
+    pub full: bool,
+    /// Last block whose trades are all in the candles, before any still held back.
+    pub last_block: i64,
+    /// The primary's `checkpoint_block`, which a promoted standby resumes after.
+    pub checkpoint_block: i64,
+    /// Block the primary's history is fetched up to while a backfill runs behind live
+    /// events, which a standby promoted meanwhile backfills up to.
+    pub backfill_target: Option<i64>,
+    pub first_trade: Option<FirstTrade>,
+    pub candles: HashMap<String, HashMap<u64, Vec<Candle>>>,
+    /// Oldest retained candle per symbol and interval once history was dropped.
+    pub horizons: HashMap<String, HashMap<u64, DateTime<Utc>>>,
+    /// Archived trades in the periods of the changed base candles, as `(from, to,
+    /// trades)` in milliseconds, which replace the standby's trades of those periods.
+    pub trades: HashMap<String, Vec<(i64, i64, Vec<Trade>)>>,
+    /// Event time of the oldest archived trade per symbol once older ones were dropped.
+    pub trade_horizons: HashMap<String, i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RebuildReport {
+    pub trades: usize,
+    pub candles: usize,
+    /// False when the archive had already dropped older trades, so older candles are gone.
+    pub complete: bool,
+}
+
+/// Approximate memory held by a store, excluding map and allocator overhead.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StoreFootprint {
+    pub candles: usize,
+    pub trades: usize,
+    pub bytes: usize,
+}
+
+/// A stored bucket that disagrees with the aggregate of its source level.
+#[derive(Debug, Serialize)]
+pub struct Mismatch {
+    pub symbol: String,
+    pub interval: u64,
+    pub source: u64,
+    pub timestamp: DateTime<Utc>,
+    pub expected: Option<Candle>,
+    pub actual: Option<Candle>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub checked: usize,
+    pub mismatches: Vec<Mismatch>,
 }
 
 impl CandleStore {
     pub fn new() -> Self {
         Self {
             candles: RwLock::new(HashMap::new()),
+            latency: LatencyHistogram::default(),
+            trades: TradeArchive::default(),
+            pyramid: IntervalPyramid::default(),
+            sub_minute: SUB_MINUTE_INTERVALS.to_vec(),
+            fill_gaps: true,
+            wash_trades: None,
+            base_asset: None,
+            price_filter: None,
+            price_scale: 1.0,
+            quarantined: AtomicU64::new(0),
+            horizons: Mutex::new(HashMap::new()),
+            revision: AtomicU64::new(0),
+            last_block: AtomicI64::new(0),
+            chain_head: AtomicI64::new(0),
+            pending: Mutex::new(PendingTrades::default()),
+            backfill_checkpoint: AtomicI64::new(i64::MAX),
+            backfill_target: AtomicI64::new(i64::MIN),
+            changes: watch::channel(0).0,
+            events: None,
+            last_trade_at: AtomicI64::new(i64::MIN),
+            first_trade: RwLock::new(None),
+            evicted: AtomicU64::new(0),
+            removed: AtomicU64::new(0),
         }
     }
 
-    pub fn add_price(&self, symbol: &str, interval: u64, price: f64, volume: f64, event_time: i64) {
+    /// Publishes bar updates and closes to `events` as trades are recorded.
+    pub fn with_events(mut self, events: Arc<CandleEvents>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn with_fill_gaps(mut self, fill_gaps: bool) -> Self {
+        self.fill_gaps = fill_gaps;
+        self
+    }
+
+    /// Holds trades back until their blocks are `depth` confirmations deep.
+    pub fn with_finality_depth(self, depth: u64) -> Self {
+        *self.pending.lock().unwrap() = PendingTrades::new(depth);
+        self
+    }
+
+    pub fn finality_depth(&self) -> u64 {
+        self.pending.lock().unwrap().depth()
+    }
+
+    /// Flags self-trades for `policy`; without one they are not looked for.
+    pub fn with_wash_trades(mut self, policy: Option<WashTradePolicy>) -> Self {
+        self.wash_trades = policy;
+        self
+    }
+
+    pub fn wash_trades(&self) -> Option<WashTradePolicy> {
+        self.wash_trades
+    }
+
+    /// Counts only the Trade legs of `asset`, so fills emitted for both assets of the
+    /// pair are not counted twice.
+    pub fn with_base_asset(mut self, asset: Option<String>) -> Self {
+        self.base_asset = asset;
+        self
+    }
+
+    pub fn base_asset(&self) -> Option<&str> {
+        self.base_asset.as_deref()
+    }
+
+    /// Quarantines trades outside `filter`, whose bounds are in units of
+    /// `10^price_decimals` raw units.
+    pub fn with_price_filter(mut self, filter: Option<PriceFilter>, price_decimals: u32) -> Self {
+        self.price_filter = filter;
+        self.price_scale = 10f64.powi(price_decimals as i32);
+        self
+    }
+
+    /// Why a trade of `symbol` at `price` should be quarantined, if it should. Moves are
+    /// measured from the close of the latest base candle at or before the trade, so
+    /// history loaded behind live events is compared with its own neighbourhood.
+    pub fn check_price(&self, symbol: &str, price: u128, event_time: i64) -> Option<String> {
+        let filter = self.price_filter?;
+        let previous = self.close_at(symbol, event_time);
+        let reason = filter.check(
+            price as f64 / self.price_scale,
+            previous.map(|close| close as f64 / self.price_scale),
+        )?;
+        self.quarantined.fetch_add(1, Ordering::Relaxed);
+        Some(reason)
+    }
+
+    pub fn quarantined_trades(&self) -> u64 {
+        self.quarantined.load(Ordering::Relaxed)
+    }
+
+    /// Close of the latest base candle starting at or before `event_time` milliseconds.
+    fn close_at(&self, symbol: &str, event_time: i64) -> Option<u128> {
+        let at = DateTime::from_timestamp_millis(event_time)?;
+        let candles = self.candles.read().unwrap();
+        let level = candles.get(symbol)?.get(&self.pyramid.base())?;
+        let end = level.partition_point(|candle| candle.timestamp <= at);
+        level[..end].last().map(|candle| candle.close)
+    }
+
+    pub fn pending_trades(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Every stored interval, finest first.
+    pub fn intervals(&self) -> Vec<u64> {
+        self.sub_minute
+            .iter()
+            .chain(self.pyramid.intervals())
+            .copied()
+            .collect()
+    }
+
+    /// The finest pyramid level, which keeps the store's full retention while the
+    /// sub-minute intervals are trimmed to about a day.
+    pub fn base_interval(&self) -> u64 {
+        self.pyramid.base()
+    }
+
+    pub fn stores_interval(&self, interval: u64) -> bool {
+        self.sub_minute.contains(&interval) || self.pyramid.contains(interval)
+    }
+
+    /// Stores `sub_minute` intervals, written per trade, and the levels of `pyramid`.
+    pub fn with_intervals(mut self, sub_minute: Vec<u64>, pyramid: IntervalPyramid) -> Self {
+        self.sub_minute = sub_minute;
+        self.pyramid = pyramid;
+        self
+    }
+
+    /// Notes that `block_number` was processed. Without a finality depth this applies
+    /// the trades held back up to it; otherwise they wait for `observe_head`, which the
+    /// indexer calls once their blocks are checked against the chain.
+    pub fn mark_block(&self, block_number: i64) {
+        self.last_block.fetch_max(block_number, Ordering::Relaxed);
+        if self.finality_depth() == 0 {
+            self.observe_head(block_number);
+        } else {
+            self.chain_head.fetch_max(block_number, Ordering::Relaxed);
+        }
+    }
+
+    pub fn last_block(&self) -> i64 {
+        self.last_block.load(Ordering::Relaxed)
+    }
+
+    pub fn chain_head(&self) -> i64 {
+        self.chain_head.load(Ordering::Relaxed)
+    }
+
+    /// Notes that the chain has reached `head` and applies the trades that became final.
+    pub fn observe_head(&self, head: i64) {
+        self.chain_head.fetch_max(head, Ordering::Relaxed);
+        let sealed = {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.is_empty() {
+                return;
+            }
+            pending.seal(self.chain_head.load(Ordering::Relaxed))
+        };
+        self.record_trades(sealed);
+    }
+
+    /// Heights and hashes of the blocks whose trades are held back, oldest first.
+    pub fn pending_blocks(&self) -> Vec<(i64, String)> {
+        self.pending.lock().unwrap().blocks()
+    }
+
+    /// Discards the held-back trades of `block` and later blocks once the chain has
+    /// replaced `block`. Returns the trades dropped.
+    pub fn drop_reorged(&self, block: i64) -> usize {
+        self.pending.lock().unwrap().drop_from(block)
+    }
+
+    /// Caps `checkpoint_block` while a backfill runs behind live events; `None` once
+    /// history is complete.
+    pub fn set_backfill_checkpoint(&self, block: Option<i64>) {
+        self.backfill_checkpoint
+            .store(block.unwrap_or(i64::MAX), Ordering::Relaxed);
+    }
+
+    /// Records where history is fetched up to while a backfill runs behind live events;
+    /// `None` once history is complete.
+    pub fn set_backfill_target(&self, block: Option<i64>) {
+        self.backfill_target
+            .store(block.unwrap_or(i64::MIN), Ordering::Relaxed);
+    }
+
+    pub fn backfill_target(&self) -> Option<i64> {
+        Some(self.backfill_target.load(Ordering::Relaxed)).filter(|&block| block != i64::MIN)
+    }
+
+    /// The last processed block, or the one before the first block whose trades are
+    /// still held back.
+    fn applied_block(&self) -> i64 {
+        match self.pending.lock().unwrap().first_block() {
+            Some(first_pending) => self.last_block().min(first_pending - 1),
+            None => self.last_block(),
+        }
+    }
+
+    /// The block a restart can safely continue after: the last processed block, but
+    /// never past trades that are still held back or history not yet backfilled.
+    pub fn checkpoint_block(&self) -> i64 {
+        self.applied_block()
+            .min(self.backfill_checkpoint.load(Ordering::Relaxed))
+    }
+
+    pub fn last_trade_at(&self) -> Option<i64> {
+        Some(self.last_trade_at.load(Ordering::Relaxed)).filter(|&at| at != i64::MIN)
+    }
+
+    /// Keeps the earliest trade seen, including ones backfilled after later trades.
+    pub fn observe_first_trade(&self, block: i64, event_time: i64) {
+        if self
+            .first_trade
+            .read()
+            .unwrap()
+            .is_some_and(|first| first.event_time <= event_time)
+        {
+            return;
+        }
+        let mut first_trade = self.first_trade.write().unwrap();
+        if first_trade.is_none_or(|first| event_time < first.event_time) {
+            *first_trade = Some(FirstTrade { block, event_time });
+        }
+    }
+
+    pub fn first_trade(&self) -> Option<FirstTrade> {
+        *self.first_trade.read().unwrap()
+    }
+
+    /// Seeds the first trade from persisted pair state.
+    pub fn restore_first_trade(&self, first_trade: FirstTrade) {
+        *self.first_trade.write().unwrap() = Some(first_trade);
+    }
+
+    /// Records the trades of `block` together, holding them back while the block is not
+    /// final. Each trade comes with a key identifying its event within the block, so
+    /// re-deliveries are ignored.
+    pub fn add_block_trades(
+        &self,
+        symbol: &str,
+        block: i64,
+        block_hash: &str,
+        trades: Vec<(String, Trade)>,
+    ) {
+        let exclude_wash = self.wash_trades == Some(WashTradePolicy::Exclude);
+        let trades = trades
+            .into_iter()
+            .filter(|(_, trade)| !(exclude_wash && trade.wash));
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_final(block, self.chain_head.load(Ordering::Relaxed)) {
+            drop(pending);
+            self.record_trades(trades.map(|(_, trade)| (symbol.to_string(), trade)));
+        } else {
+            for (event_key, trade) in trades {
+                pending.push(block, block_hash, event_key, symbol, trade);
+            }
+        }
+    }
+
+    /// Records a trade with its event time in milliseconds.
+    pub fn add_trade(&self, symbol: &str, price: u128, volume: u128, event_time: i64) {
+        let trade = Trade {
+            price,
+            volume,
+            event_time,
+            wash: false,
+        };
+        self.record_trades([(symbol.to_string(), trade)]);
+    }
+
+    /// Records `(symbol, trade)` pairs under a single write lock, so readers see all of
+    /// them or none. Only the base interval and the sub-minute intervals are written
+    /// directly; candles of coarser intervals are re-derived from their source level
+    /// whenever a finer candle closes.
+    fn record_trades(&self, trades: impl IntoIterator<Item = (String, Trade)>) {
         let mut candles = self.candles.write().unwrap();
+        let mut horizons = self.horizons.lock().unwrap();
+        let mut recorded = HashSet::new();
+        for (symbol, trade) in trades {
+            self.trades.record(&symbol, trade.clone());
+            let symbol_candles = candles.entry(symbol.clone()).or_default();
+            self.apply_trade(symbol_candles, &mut horizons, &symbol, &trade);
+            recorded.insert(symbol);
+        }
+        if recorded.is_empty() {
+            return;
+        }
+        if let Some(events) = self.events.as_ref().filter(|events| events.is_listened()) {
+            for symbol in &recorded {
+                self.publish_events(events, symbol, &candles[symbol]);
+            }
+        }
+        self.publish_change();
+    }
+
+    /// Hands the newest bar of every interval of `symbol`, and the bar before it, to
+    /// `events`, which publishes whatever changed.
+    fn publish_events(
+        &self,
+        events: &CandleEvents,
+        symbol: &str,
+        symbol_candles: &HashMap<u64, Vec<Candle>>,
+    ) {
+        for interval in self.intervals() {
+            let (stored, forming) = self.read_level(symbol_candles, interval);
+            let (newest, previous) = match forming {
+                Some(forming) => (forming, stored.last()),
+                None => match stored {
+                    [.., previous, newest] => (newest.clone(), Some(previous)),
+                    [newest] => (newest.clone(), None),
+                    [] => continue,
+                },
+            };
+            events.observe(symbol, interval, newest, previous);
+        }
+    }
+
+    /// Wakes streaming subscribers, which re-read whatever they follow.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.changes.subscribe()
+    }
 
+    /// The revision last published to subscribers; it moves on with every change.
+    pub fn revision(&self) -> u64 {
+        *self.changes.borrow()
+    }
+
+    fn publish_change(&self) {
+        self.changes
+            .send_replace(self.revision.load(Ordering::Relaxed));
+    }
+
+    /// Wipes every interval of `symbol` and recomputes it from the trade archive, e.g.
+    /// after decimals or aggregation changes. Trades keep arriving only once it is done.
+    pub fn rebuild(&self, symbol: &str) -> RebuildReport {
+        let mut candles = self.candles.write().unwrap();
+        let mut horizons = self.horizons.lock().unwrap();
+        let trades = self.trades.range(symbol, i64::MIN, i64::MAX);
+
+        let revision = self.revision.fetch_add(1, Ordering::Relaxed) + 1;
+        self.removed.fetch_max(revision, Ordering::Relaxed);
+        candles.remove(symbol);
+        horizons.retain(|(horizon_symbol, _), _| horizon_symbol != symbol);
         let symbol_candles = candles.entry(symbol.to_string()).or_default();
-        let candle_list = symbol_candles.entry(interval).or_default();
+        for trade in &trades {
+            self.apply_trade(symbol_candles, &mut horizons, symbol, trade);
+        }
+
+        self.publish_change();
+        RebuildReport {
+            trades: trades.len(),
+            candles: symbol_candles.values().map(Vec::len).sum(),
+            complete: self.trades.covers(symbol, i64::MIN),
+        }
+    }
+
+    /// Writes externally sourced candles into one interval, resolving periods that are
+    /// already stored by `policy`, and re-derives the coarser levels built from it.
+    /// Imported candles have no trades behind them, so a rebuild from the archive drops
+    /// them again. With `dry_run` the report is computed but nothing is stored.
+    pub fn import(
+        &self,
+        symbol: &str,
+        interval: u64,
+        imported: &[ImportedCandle],
+        policy: ConflictPolicy,
+        dry_run: bool,
+    ) -> ImportReport {
+        let mut candles = self.candles.write().unwrap();
+        let mut horizons = self.horizons.lock().unwrap();
+        let revision = if dry_run {
+            self.revision.load(Ordering::Relaxed)
+        } else {
+            self.revision.fetch_add(1, Ordering::Relaxed) + 1
+        };
+        let now = Utc::now();
+        let horizon = horizons.get(&(symbol.to_string(), interval)).copied();
+        let filled = self.fill_gaps && !self.sub_minute.contains(&interval);
+
+        let symbol_candles = candles.entry(symbol.to_string()).or_default();
+        let mut list = symbol_candles.get(&interval).cloned().unwrap_or_default();
+        let mut report = ImportReport {
+            dry_run,
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+
+        for item in imported {
+            let timestamp = match item.datetime() {
+                None => Err("invalid timestamp".to_string()),
+                Some(ts) if period_start(ts, interval) != ts => {
+                    Err("timestamp is not aligned to the interval".to_string())
+                }
+                Some(ts) if period_end(ts, interval) > now => {
+                    Err("period has not closed yet".to_string())
+                }
+                Some(ts) if horizon.is_some_and(|horizon| ts < horizon) => {
+                    Err("older than the retained history".to_string())
+                }
+                Some(ts) if !seen.insert(ts) => Err("duplicate timestamp".to_string()),
+                Some(ts) => item.check().map(|()| ts),
+            };
+            let timestamp = match timestamp {
+                Ok(timestamp) => timestamp,
+                Err(reason) => {
+                    report.rejected.push(RejectedCandle {
+                        timestamp: item.timestamp,
+                        reason,
+                    });
+                    continue;
+                }
+            };
+
+            let mut candle = item.to_candle(timestamp, period_end(timestamp, interval));
+            candle.revision = revision;
+            let (index, action, before) =
+                match list.binary_search_by_key(&timestamp, |c| c.timestamp) {
+                    Err(index) => {
+                        list.insert(index, candle.clone());
+                        (index, ImportAction::Insert, None)
+                    }
+                    Ok(index) if list[index].is_gap() => {
+                        let before = std::mem::replace(&mut list[index], candle.clone());
+                        (index, ImportAction::Insert, Some(before))
+                    }
+                    Ok(index) => {
+                        let (action, after) = match policy {
+                            ConflictPolicy::Skip => {
+                                report.skipped += 1;
+                                continue;
+                            }
+                            ConflictPolicy::Overwrite => (ImportAction::Overwrite, candle),
+                            ConflictPolicy::MergeExtremes => {
+                                let mut merged = list[index].clone();
+                                merged.high = merged.high.max(item.high);
+                                merged.low = merged.low.min(item.low);
+                                merged.revision = revision;
+                                (ImportAction::MergeExtremes, merged)
+                            }
+                        };
+                        if after.same_contents(&list[index]) {
+                            report.skipped += 1;
+                            continue;
+                        }
+                        let before = std::mem::replace(&mut list[index], after);
+                        (index, action, Some(before))
+                    }
+                };
+
+            match action {
+                ImportAction::Insert => report.inserted += 1,
+                ImportAction::Overwrite => report.overwritten += 1,
+                ImportAction::MergeExtremes => report.merged += 1,
+            }
+            report.changes.push(ImportChange {
+                action,
+                before,
+                after: list[index].clone(),
+            });
+
+            if filled {
+                // Fill forward first so the preceding fill does not shift `index`.
+                Self::fill_gaps(&mut list, index, interval, revision);
+                Self::reflatten_gaps(&mut list, index, revision);
+                if index > 0 {
+                    Self::fill_gaps(&mut list, index - 1, interval, revision);
+                }
+            }
+        }
+
+        if dry_run || report.changes.is_empty() {
+            return report;
+        }
+
+        let first = list.iter().find(|c| c.revision == revision);
+        let last = list.iter().rfind(|c| c.revision == revision);
+        let changed = first
+            .zip(last)
+            .map(|(first, last)| (first.timestamp, last.timestamp));
+        symbol_candles.insert(interval, list);
+        if let Some((from, to)) = changed {
+            let changed = HashMap::from([(interval, (from, to))]);
+            self.cascade(
+                symbol_candles,
+                &mut horizons,
+                symbol,
+                changed,
+                revision,
+                None,
+            );
+        }
+        self.publish_change();
+        report
+    }
+
+    fn apply_trade(
+        &self,
+        symbol_candles: &mut HashMap<u64, Vec<Candle>>,
+        horizons: &mut HashMap<(String, u64), DateTime<Utc>>,
+        symbol: &str,
+        trade: &Trade,
+    ) {
+        let event_time = trade.event_time;
+        let revision = self.revision.fetch_add(1, Ordering::Relaxed) + 1;
+        self.last_trade_at.fetch_max(event_time, Ordering::Relaxed);
 
         let event_datetime = Utc
-            .timestamp_opt(event_time, 0)
+            .timestamp_millis_opt(event_time)
             .single()
             .expect("Invalid timestamp");
 
-        let period_start = Self::get_period_start(event_datetime, interval);
+        for &interval in &self.sub_minute {
+            let fine_list = symbol_candles.entry(interval).or_default();
+            Self::merge_sparse(
+                fine_list,
+                period_start(event_datetime, interval),
+                trade,
+                revision,
+            );
+        }
+
+        let base = self.pyramid.base();
+        let base_start = period_start(event_datetime, base);
+        let base_horizon = horizons.get(&(symbol.to_string(), base)).copied();
+        let base_list = symbol_candles.entry(base).or_default();
 
-        if let Some(last_candle) = candle_list.last_mut() {
-            if last_candle.timestamp == period_start {
-                last_candle.high = last_candle.high.max(price);
-                last_candle.low = last_candle.low.min(price);
-                last_candle.close = price;
-                last_candle.volume += volume;
-                return;
-            }
+        // Closed buckets whose contents changed per level, as inclusive timestamp ranges.
+        let mut changed = HashMap::new();
+        let base_changed = if base_horizon.is_some_and(|horizon| base_start < horizon) {
+            Some((base_start, base_start))
+        } else {
+            Self::insert_trade(base_list, base, base_start, trade, revision, self.fill_gaps)
+        };
+        Self::enforce_retention(base_list, horizons, symbol, base);
+        if let Some(range) = base_changed {
+            changed.insert(base, range);
         }
 
-        if let Some(last_candle) = candle_list.last() {
-            let mut missing_time = last_candle.timestamp + Duration::seconds(interval as i64);
-            let last_close = last_candle.close;
+        self.cascade(
+            symbol_candles,
+            horizons,
+            symbol,
+            changed,
+            revision,
+            Some(trade),
+        );
+    }
 
-            while missing_time < period_start {
-                let empty_candle = Candle {
-                    open: last_close,
-                    high: last_close,
-                    low: last_close,
-                    close: last_close,
-                    volume: 0.0,
-                    timestamp: missing_time,
+    /// Re-derives every level whose source has closed buckets in `changed`, given as
+    /// inclusive timestamp ranges per interval. Buckets that have partly aged out of their
+    /// source only take `trade` merged in, and are left alone without one.
+    fn cascade(
+        &self,
+        symbol_candles: &mut HashMap<u64, Vec<Candle>>,
+        horizons: &mut HashMap<(String, u64), DateTime<Utc>>,
+        symbol: &str,
+        mut changed: HashMap<u64, (DateTime<Utc>, DateTime<Utc>)>,
+        revision: u64,
+        trade: Option<&Trade>,
+    ) {
+        let base = self.pyramid.base();
+        let trade_time = trade.map(|trade| {
+            Utc.timestamp_millis_opt(trade.event_time)
+                .single()
+                .expect("Invalid timestamp")
+        });
+
+        for (level, source) in self.pyramid.levels() {
+            let Some(&(from, to)) = changed.get(&source) else {
+                continue;
+            };
+            let source_horizon = horizons.get(&(symbol.to_string(), source)).copied();
+            let trade_start = trade_time.map(|time| period_start(time, level));
+
+            let mut updates = Vec::new();
+            {
+                let source_list = symbol_candles
+                    .get(&source)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                // The forming base candle is folded in at read time, never stored upward.
+                let closed = if source == base {
+                    &source_list[..source_list.len().saturating_sub(1)]
+                } else {
+                    source_list
                 };
-                candle_list.push(empty_candle);
-                missing_time += Duration::seconds(interval as i64);
+
+                let last = period_start(to, level);
+                let mut bucket = period_start(from, level);
+                while bucket <= last {
+                    let end = period_end(bucket, level);
+                    if source_horizon.is_some_and(|horizon| bucket < horizon) {
+                        // Part of the bucket has aged out of the source level.
+                        if trade_start == Some(bucket) {
+                            updates.push(LevelUpdate::Merge(bucket));
+                        }
+                    } else {
+                        let lo = closed.partition_point(|c| c.timestamp < bucket);
+                        let hi = closed.partition_point(|c| c.timestamp < end);
+                        updates.push(LevelUpdate::Set(
+                            bucket,
+                            Candle::aggregate(bucket, &closed[lo..hi]),
+                        ));
+                    }
+                    bucket = period_start(end, level);
+                }
             }
+
+            let level_list = symbol_candles.entry(level).or_default();
+            for update in updates {
+                match update {
+                    LevelUpdate::Set(bucket, candle) => {
+                        let position = level_list.binary_search_by_key(&bucket, |c| c.timestamp);
+                        match (position, candle) {
+                            (Ok(index), Some(mut candle)) => {
+                                candle.revision = revision;
+                                level_list[index] = candle;
+                            }
+                            (Err(index), Some(mut candle)) => {
+                                candle.revision = revision;
+                                level_list.insert(index, candle);
+                            }
+                            (Ok(index), None) => {
+                                level_list.remove(index);
+                                self.removed.fetch_max(revision, Ordering::Relaxed);
+                            }
+                            (Err(_), None) => {}
+                        }
+                    }
+                    LevelUpdate::Merge(bucket) => {
+                        if let Some(trade) = trade {
+                            Self::merge_sparse(level_list, bucket, trade, revision);
+                        }
+                    }
+                }
+            }
+            Self::enforce_retention(level_list, horizons, symbol, level);
+
+            changed.insert(level, (period_start(from, level), period_start(to, level)));
         }
+    }
 
-        let new_candle = Candle {
-            open: price,
-            high: price,
-            low: price,
-            close: price,
-            volume,
-            timestamp: period_start,
+    /// Writes a trade into the base level and returns the range of closed base candles it
+    /// changed, including the previously forming candle when a newer bucket opens.
+    fn insert_trade(
+        candle_list: &mut Vec<Candle>,
+        interval: u64,
+        period_start: DateTime<Utc>,
+        trade: &Trade,
+        revision: u64,
+        fill_gaps: bool,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let previous_last = candle_list.last().map(|c| c.timestamp);
+
+        match candle_list.binary_search_by_key(&period_start, |c| c.timestamp) {
+            Ok(index) => {
+                candle_list[index].merge_trade(trade);
+                candle_list[index].revision = revision;
+                Self::reflatten_gaps(candle_list, index, revision);
+            }
+            Err(index) => {
+                let mut candle = Candle::from_trade(period_start, trade);
+                candle.revision = revision;
+                candle_list.insert(index, candle);
+                // Fill forward first so the preceding fill does not shift `index`.
+                if fill_gaps && index + 1 < candle_list.len() {
+                    Self::fill_gaps(candle_list, index, interval, revision);
+                    Self::reflatten_gaps(candle_list, index, revision);
+                }
+                if fill_gaps && index > 0 {
+                    Self::fill_gaps(candle_list, index - 1, interval, revision);
+                }
+            }
+        }
+
+        // Everything touched by this trade is stamped with `revision` and contiguous.
+        let index = candle_list
+            .binary_search_by_key(&period_start, |c| c.timestamp)
+            .ok()?;
+        let lo = candle_list[..index]
+            .iter()
+            .rev()
+            .take_while(|c| c.revision == revision)
+            .count();
+        let hi = candle_list[index + 1..]
+            .iter()
+            .take_while(|c| c.revision == revision)
+            .count();
+        let mut from = candle_list[index - lo].timestamp;
+        let mut to = candle_list[index + hi].timestamp;
+
+        let forming = candle_list.last()?.timestamp;
+        if let Some(previous_last) = previous_last.filter(|&last| last < forming) {
+            from = from.min(previous_last);
+        }
+        if to == forming {
+            if from == forming {
+                return None;
+            }
+            to = candle_list[candle_list.len() - 2].timestamp;
+        }
+        Some((from, to))
+    }
+
+    /// Merges a trade into a sparse level that is neither gap-filled nor derived.
+    fn merge_sparse(
+        candle_list: &mut Vec<Candle>,
+        period_start: DateTime<Utc>,
+        trade: &Trade,
+        revision: u64,
+    ) {
+        let index = match candle_list.binary_search_by_key(&period_start, |c| c.timestamp) {
+            Ok(index) => index,
+            Err(index) => {
+                candle_list.insert(index, Candle::flat(period_start, trade.price));
+                index
+            }
         };
-        candle_list.push(new_candle);
+        candle_list[index].merge_trade(trade);
+        candle_list[index].revision = revision;
 
+        trim_front(candle_list, MAX_SUB_MINUTE_CANDLES);
+    }
+
+    fn enforce_retention(
+        candle_list: &mut Vec<Candle>,
+        horizons: &mut HashMap<(String, u64), DateTime<Utc>>,
+        symbol: &str,
+        interval: u64,
+    ) {
         const MAX_CANDLES: usize = 1000000;
-        if candle_list.len() > MAX_CANDLES {
-            candle_list.drain(0..(candle_list.len() - MAX_CANDLES));
+        if trim_front(candle_list, MAX_CANDLES) {
+            horizons.insert((symbol.to_string(), interval), candle_list[0].timestamp);
+        }
+    }
+
+    /// Inserts flat candles for every missing period between `index` and `index + 1`.
+    fn fill_gaps(candle_list: &mut Vec<Candle>, index: usize, interval: u64, revision: u64) {
+        let step = Duration::seconds(interval as i64);
+        let last_close = candle_list[index].close;
+        let mut missing_time = candle_list[index].timestamp + step;
+
+        let next_time = match candle_list.get(index + 1) {
+            Some(next) => next.timestamp,
+            None => return,
+        };
+
+        let mut missing = Vec::new();
+        while missing_time < next_time {
+            let mut candle = Candle::flat(missing_time, last_close);
+            candle.revision = revision;
+            missing.push(candle);
+            missing_time += step;
+        }
+        candle_list.splice(index + 1..index + 1, missing);
+    }
+
+    /// Re-levels the gap candles following `index` to its close after an out-of-order update.
+    fn reflatten_gaps(candle_list: &mut [Candle], index: usize, revision: u64) {
+        let close = candle_list[index].close;
+        for candle in candle_list[index + 1..].iter_mut() {
+            if !candle.is_gap() {
+                break;
+            }
+            *candle = Candle::flat(candle.timestamp, close);
+            candle.revision = revision;
+        }
+    }
+
+    /// Every candle changed after `since`, with the archived trades of the periods that
+    /// changed, plus the cursor for the next call. Everything is shipped instead when
+    /// `since` is 0 or candles were removed after it.
+    pub fn delta_since(&self, since: u64) -> StoreDelta {
+        let candles = self.candles.read().unwrap();
+        let full = since == 0 || since < self.removed.load(Ordering::Relaxed);
+        let base = self.pyramid.base();
+        let mut trades = HashMap::new();
+        let mut trade_horizons = HashMap::new();
+        let changed = candles
+            .iter()
+            .map(|(symbol, intervals)| {
+                let intervals: HashMap<u64, Vec<Candle>> = intervals
+                    .iter()
+                    .map(|(interval, list)| {
+                        let changed: Vec<Candle> = list
+                            .iter()
+                            .filter(|c| full || c.revision > since)
+                            .cloned()
+                            .collect();
+                        (*interval, changed)
+                    })
+                    .filter(|(_, changed)| !changed.is_empty())
+                    .collect();
+
+                // Every trade recorded since lands in a base candle that changed with it.
+                let mut periods: Vec<(i64, i64)> = Vec::new();
+                if full {
+                    periods.push((i64::MIN, i64::MAX));
+                } else {
+                    let step = base as i64 * 1000;
+                    for candle in intervals.get(&base).into_iter().flatten() {
+                        let from = candle.timestamp.timestamp_millis();
+                        match periods.last_mut() {
+                            Some(last) if last.1 == from => last.1 = from + step,
+                            _ => periods.push((from, from + step)),
+                        }
+                    }
+                }
+                let spans = periods
+                    .into_iter()
+                    .map(|(from, to)| (from, to, self.trades.range(symbol, from, to)))
+                    .collect();
+                trades.insert(symbol.clone(), spans);
+                if let Some(horizon) = self.trades.horizon(symbol) {
+                    trade_horizons.insert(symbol.clone(), horizon);
+                }
+                (symbol.clone(), intervals)
+            })
+            .collect();
+
+        let mut horizons: HashMap<String, HashMap<u64, DateTime<Utc>>> = HashMap::new();
+        for ((symbol, interval), horizon) in self.horizons.lock().unwrap().iter() {
+            horizons
+                .entry(symbol.clone())
+                .or_default()
+                .insert(*interval, *horizon);
+        }
+
+        StoreDelta {
+            revision: self.revision.load(Ordering::Relaxed),
+            full,
+            last_block: self.applied_block(),
+            checkpoint_block: self.checkpoint_block(),
+            backfill_target: self.backfill_target(),
+            first_trade: self.first_trade(),
+            candles: changed,
+            horizons,
+            trades,
+            trade_horizons,
+        }
+    }
+
+    /// Takes over a delta from the primary: upserts its candles, or replaces everything
+    /// with a full one, and trims them as trades arriving here would. Its trades, first
+    /// trade and checkpoint are taken over too.
+    pub fn apply_delta(&self, delta: StoreDelta) {
+        let mut candles = self.candles.write().unwrap();
+        let mut horizons = self.horizons.lock().unwrap();
+        if delta.full {
+            candles.clear();
+            horizons.clear();
+        }
+        for (symbol, intervals) in delta.horizons {
+            for (interval, horizon) in intervals {
+                let current = horizons
+                    .entry((symbol.clone(), interval))
+                    .or_insert(horizon);
+                *current = (*current).max(horizon);
+            }
+        }
+        for (symbol, intervals) in delta.candles {
+            let symbol_candles = candles.entry(symbol.clone()).or_default();
+            for (interval, changed) in intervals {
+                let candle_list = symbol_candles.entry(interval).or_default();
+                for candle in changed {
+                    match candle_list.binary_search_by_key(&candle.timestamp, |c| c.timestamp) {
+                        Ok(index) => candle_list[index] = candle,
+                        Err(index) => candle_list.insert(index, candle),
+                    }
+                }
+                if self.sub_minute.contains(&interval) {
+                    trim_front(candle_list, MAX_SUB_MINUTE_CANDLES);
+                } else {
+                    Self::enforce_retention(candle_list, &mut horizons, &symbol, interval);
+                }
+            }
+        }
+        drop(horizons);
+        drop(candles);
+
+        for (symbol, spans) in delta.trades {
+            for (from, to, trades) in spans {
+                self.trades.replace_range(&symbol, from, to, trades);
+            }
+        }
+        for (symbol, horizon) in delta.trade_horizons {
+            self.trades.restore_horizon(&symbol, horizon);
+        }
+        if let Some(first) = delta.first_trade {
+            self.observe_first_trade(first.block, first.event_time);
+        }
+        self.revision.fetch_max(delta.revision, Ordering::Relaxed);
+        self.mark_block(delta.last_block);
+        self.set_backfill_checkpoint(Some(delta.checkpoint_block));
+        self.set_backfill_target(delta.backfill_target);
+        self.publish_change();
+    }
+
+    /// The stored interval `interval` is served from: itself when stored, otherwise the
+    /// coarsest stored interval dividing it, whose candles `resample` aggregates.
+    pub fn source_interval(&self, interval: u64) -> Option<u64> {
+        if self.stores_interval(interval) {
+            return Some(interval);
+        }
+        self.intervals()
+            .into_iter()
+            .filter(|&stored| interval.is_multiple_of(stored))
+            .max()
+    }
+
+    /// Aggregates consecutive `candles` of a finer interval into buckets of `interval`.
+    pub fn resample(candles: &[Candle], interval: u64) -> Vec<Candle> {
+        candles
+            .chunk_by(|a, b| {
+                period_start(a.timestamp, interval) == period_start(b.timestamp, interval)
+            })
+            .filter_map(|bucket| {
+                Candle::aggregate(period_start(bucket[0].timestamp, interval), bucket)
+            })
+            .collect()
+    }
+
+    /// Heikin-Ashi bars of consecutive `candles`: the close is the mean of the bar's
+    /// prices, the open the midpoint of the previous Heikin-Ashi bar (of the first bar's
+    /// open and close at the start), and high and low are widened to include both.
+    pub fn heikin_ashi(candles: &[Candle]) -> Vec<Candle> {
+        let mut previous: Option<(u128, u128)> = None;
+        candles
+            .iter()
+            .map(|candle| {
+                let close = (candle.open + candle.high + candle.low + candle.close) / 4;
+                let open = match previous {
+                    Some((open, close)) => (open + close) / 2,
+                    None => (candle.open + candle.close) / 2,
+                };
+                previous = Some((open, close));
+                Candle {
+                    open,
+                    high: candle.high.max(open).max(close),
+                    low: candle.low.min(open).min(close),
+                    close,
+                    ..candle.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Inserts flat candles at the previous close between consecutive `candles` of
+    /// `interval`, for levels stored without gap filling. At most `limit` candles are
+    /// returned, keeping the newest.
+    pub fn synthesize_gaps(candles: &[Candle], interval: u64, limit: usize) -> Vec<Candle> {
+        let step = Duration::seconds(interval as i64);
+        // Built newest first so the limit drops the oldest candles.
+        let mut filled = Vec::new();
+        for (index, candle) in candles.iter().enumerate().rev() {
+            if let Some(next) = candles.get(index + 1) {
+                let mut bucket = next.timestamp - step;
+                while bucket > candle.timestamp && filled.len() < limit {
+                    filled.push(Candle::flat(bucket, candle.close));
+                    bucket -= step;
+                }
+            }
+            if filled.len() >= limit {
+                break;
+            }
+            filled.push(candle.clone());
         }
+        filled.reverse();
+        filled
     }
 
-    fn get_period_start(event_datetime: DateTime<Utc>, interval: u64) -> DateTime<Utc> {
-        match interval {
-            60 | 180 | 300 | 900 | 3600 => {
-                let timestamp = event_datetime.timestamp();
-                let period = timestamp - (timestamp % interval as i64);
-                DateTime::from_timestamp(period, 0).expect("Invalid timestamp")
+    /// Checks that every derived bucket equals the aggregate of its closed source candles,
+    /// and that sub-minute candles add up to the base candle they fall in.
+    pub fn validate(&self, symbol: &str) -> ValidationReport {
+        let candles = self.candles.read().unwrap();
+        let horizons = self.horizons.lock().unwrap();
+        let mut report = ValidationReport::default();
+        let Some(symbol_candles) = candles.get(symbol) else {
+            return report;
+        };
+        let base = self.pyramid.base();
+        let level = |interval: u64| {
+            symbol_candles
+                .get(&interval)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+        };
+
+        for (interval, source) in self.pyramid.levels() {
+            let source_list = level(source);
+            let closed = if source == base {
+                &source_list[..source_list.len().saturating_sub(1)]
+            } else {
+                source_list
+            };
+            let horizon = horizons.get(&(symbol.to_string(), source)).copied();
+
+            // Buckets present on either side, skipping those partly aged out of the source.
+            let mut buckets: Vec<DateTime<Utc>> = level(interval)
+                .iter()
+                .map(|c| c.timestamp)
+                .chain(closed.iter().map(|c| period_start(c.timestamp, interval)))
+                .filter(|&bucket| horizon.is_none_or(|horizon| bucket >= horizon))
+                .collect();
+            buckets.sort_unstable();
+            buckets.dedup();
+
+            for bucket in buckets {
+                let lo = closed.partition_point(|c| c.timestamp < bucket);
+                let hi = closed.partition_point(|c| c.timestamp < period_end(bucket, interval));
+                let expected = Candle::aggregate(bucket, &closed[lo..hi]);
+                let actual = level(interval)
+                    .binary_search_by_key(&bucket, |c| c.timestamp)
+                    .ok()
+                    .map(|index| level(interval)[index].clone());
+                report.checked += 1;
+
+                let consistent = match (&expected, &actual) {
+                    (Some(expected), Some(actual)) => expected.same_contents(actual),
+                    (None, None) => true,
+                    _ => false,
+                };
+                if !consistent {
+                    report.mismatches.push(Mismatch {
+                        symbol: symbol.to_string(),
+                        interval,
+                        source,
+                        timestamp: bucket,
+                        expected,
+                        actual,
+                    });
+                }
             }
-            86400 => event_datetime
-                .date_naive()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(Utc)
-                .unwrap(),
-            604800 => {
-                let naive_date = event_datetime.date_naive();
-                let weekday = naive_date.weekday().num_days_from_monday();
-                let start_of_week = naive_date - Duration::days(weekday as i64);
-                start_of_week
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap()
-                    .and_local_timezone(Utc)
-                    .unwrap()
+        }
+
+        for &interval in &self.sub_minute {
+            let fine = level(interval);
+            let Some(first) = fine.first() else {
+                continue;
+            };
+            // The oldest base bucket may have been partly trimmed from the sub-minute level.
+            let retained_from = period_end(period_start(first.timestamp, base), base);
+            for candle in level(base)
+                .iter()
+                .filter(|c| c.timestamp >= retained_from && !c.is_gap())
+            {
+                let lo = fine.partition_point(|c| c.timestamp < candle.timestamp);
+                let hi = fine.partition_point(|c| c.timestamp < period_end(candle.timestamp, base));
+                let aggregate = Candle::aggregate(candle.timestamp, &fine[lo..hi]);
+                report.checked += 1;
+
+                if !aggregate
+                    .as_ref()
+                    .is_some_and(|aggregate| aggregate.same_contents(candle))
+                {
+                    report.mismatches.push(Mismatch {
+                        symbol: symbol.to_string(),
+                        interval: base,
+                        source: interval,
+                        timestamp: candle.timestamp,
+                        expected: aggregate,
+                        actual: Some(candle.clone()),
+                    });
+                }
             }
-            _ => {
-                let timestamp = event_datetime.timestamp();
-                let period = timestamp - (timestamp % interval as i64);
-                DateTime::from_timestamp(period, 0).expect("Invalid timestamp")
+        }
+
+        report
+    }
+
+    /// The candle of `interval` containing the forming base candle, combined with whatever
+    /// the level already stores for that bucket.
+    fn forming_candle(
+        &self,
+        symbol_candles: &HashMap<u64, Vec<Candle>>,
+        interval: u64,
+    ) -> Option<Candle> {
+        let base = self.pyramid.base();
+        if interval == base || !self.pyramid.contains(interval) {
+            return None;
+        }
+        let forming = symbol_candles.get(&base)?.last()?;
+        let bucket = period_start(forming.timestamp, interval);
+        match symbol_candles
+            .get(&interval)
+            .and_then(|list| list.last())
+            .filter(|c| c.timestamp == bucket)
+        {
+            Some(stored) => Candle::aggregate(bucket, [stored, forming]),
+            None => Candle::aggregate(bucket, [forming]),
+        }
+    }
+
+    /// Stored candles of `interval` with the forming bucket replaced by its read-time view.
+    fn read_level<'a>(
+        &self,
+        symbol_candles: &'a HashMap<u64, Vec<Candle>>,
+        interval: u64,
+    ) -> (&'a [Candle], Option<Candle>) {
+        let stored = symbol_candles
+            .get(&interval)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        match self.forming_candle(symbol_candles, interval) {
+            Some(forming) => {
+                let stored = match stored.split_last() {
+                    Some((last, rest)) if last.timestamp == forming.timestamp => rest,
+                    _ => stored,
+                };
+                (stored, Some(forming))
             }
+            None => (stored, None),
         }
     }
 
     pub fn get_candles(&self, symbol: &str, interval: u64, count: usize) -> Vec<Candle> {
         let candles = self.candles.read().unwrap();
         if let Some(symbol_candles) = candles.get(symbol) {
-            if let Some(interval_candles) = symbol_candles.get(&interval) {
-                return interval_candles.iter().rev().take(count).cloned().collect();
-            }
+            let (stored, forming) = self.read_level(symbol_candles, interval);
+            return forming
+                .into_iter()
+                .chain(stored.iter().rev().cloned())
+                .take(count)
+                .collect();
         }
         vec![]
     }
 
+    /// The last `count` candles of `symbol` at `interval` that start at or before `to`,
+    /// oldest first.
+    pub fn get_candles_until(
+        &self,
+        symbol: &str,
+        interval: u64,
+        to: i64,
+        count: usize,
+    ) -> Vec<Candle> {
+        let candles = self.candles.read().unwrap();
+        let Some(symbol_candles) = candles.get(symbol) else {
+            return vec![];
+        };
+        let (stored, forming) = self.read_level(symbol_candles, interval);
+        let until = stored.partition_point(|c| c.timestamp.timestamp() <= to);
+        let mut selected: Vec<Candle> = forming
+            .filter(|c| c.timestamp.timestamp() <= to)
+            .into_iter()
+            .chain(stored[..until].iter().rev().cloned())
+            .take(count)
+            .collect();
+        selected.reverse();
+        selected
+    }
+
+    /// The first `count` candles of `symbol` at `interval` that start in `[from, to]`,
+    /// oldest first, for reading long ranges a page at a time.
+    pub fn get_candles_from(
+        &self,
+        symbol: &str,
+        interval: u64,
+        from: i64,
+        to: i64,
+        count: usize,
+    ) -> Vec<Candle> {
+        let candles = self.candles.read().unwrap();
+        let Some(symbol_candles) = candles.get(symbol) else {
+            return vec![];
+        };
+        let (stored, forming) = self.read_level(symbol_candles, interval);
+        let start = stored.partition_point(|c| c.timestamp.timestamp() < from);
+        stored[start..]
+            .iter()
+            .cloned()
+            .chain(forming)
+            .filter(|c| c.timestamp.timestamp() >= from)
+            .take_while(|c| c.timestamp.timestamp() <= to)
+            .take(count)
+            .collect()
+    }
+
     pub fn get_candles_in_time_range(
         &self,
         symbol: &str,
@@ -130,23 +1505,144 @@ impl CandleStore {
         to: i64,
     ) -> Vec<Candle> {
         let candles = self.candles.read().unwrap();
-        if let Some(interval_candles) = candles
-            .get(symbol)
-            .and_then(|interval_map| interval_map.get(&interval))
-        {
-            interval_candles
+        if let Some(symbol_candles) = candles.get(symbol) {
+            let (stored, forming) = self.read_level(symbol_candles, interval);
+            stored
                 .iter()
+                .cloned()
+                .chain(forming)
                 .filter(|c| {
                     let timestamp = c.timestamp.timestamp();
                     timestamp >= from && timestamp <= to
                 })
-                .cloned()
                 .collect()
         } else {
             vec![]
         }
     }
 
+    /// The raw trades of `symbol` in `[from, to]`, in seconds, if the archive still
+    /// holds all of them. A restored store archives only the trades it has seen since,
+    /// fewer than the candles of the range count.
+    pub fn archived_trades(&self, symbol: &str, from: i64, to: i64) -> Option<Vec<Trade>> {
+        let trades = self.trades.range(
+            symbol,
+            from.saturating_mul(1000),
+            to.saturating_add(1).saturating_mul(1000),
+        );
+        // Only candles whose whole period lies in the range have all their trades in it.
+        let interval = self.base_interval();
+        let counted: u64 = self
+            .get_candles_in_time_range(
+                symbol,
+                interval,
+                from,
+                to.saturating_add(1) - interval as i64,
+            )
+            .iter()
+            .map(|c| c.trade_count)
+            .sum();
+        (self.trades.covers(symbol, from.saturating_mul(1000)) && trades.len() as u64 >= counted)
+            .then_some(trades)
+    }
+
+    /// The trades of the `window` seconds up to `now` as one candle starting at the
+    /// window's start, from base-interval candles, so the window's first bucket counts
+    /// whole. `None` without trades in the window.
+    pub fn rolling_window(&self, symbol: &str, window: u64, now: DateTime<Utc>) -> Option<Candle> {
+        let candles = self.candles.read().unwrap();
+        let base = candles.get(symbol)?.get(&self.pyramid.base())?;
+        let start = now - Duration::seconds(window as i64);
+        let from = base.partition_point(|c| c.timestamp < period_start(start, self.pyramid.base()));
+        Candle::aggregate(start, base[from..].iter().filter(|c| c.timestamp <= now))
+            .filter(|candle| !candle.is_gap())
+    }
+
+    /// Close of the newest candle of `symbol`, the last traded price.
+    pub fn last_price(&self, symbol: &str) -> Option<u128> {
+        let candles = self.candles.read().unwrap();
+        candles
+            .get(symbol)?
+            .get(&self.pyramid.base())?
+            .iter()
+            .rev()
+            .find(|c| !c.is_gap())
+            .map(|c| c.close)
+    }
+
+    /// Start of the oldest candle of `symbol` at `interval`. Each level is trimmed by its
+    /// own retention, so fine intervals may start later than coarse ones.
+    pub fn earliest_candle(&self, symbol: &str, interval: u64) -> Option<DateTime<Utc>> {
+        let candles = self.candles.read().unwrap();
+        let (stored, forming) = self.read_level(candles.get(symbol)?, interval);
+        stored
+            .first()
+            .map(|c| c.timestamp)
+            .or(forming.map(|c| c.timestamp))
+    }
+
+    /// Start of the oldest candle of `symbol` kept at `interval` once retention or eviction
+    /// has dropped older ones, or `None` while the level holds all of its history.
+    pub fn horizon(&self, symbol: &str, interval: u64) -> Option<DateTime<Utc>> {
+        let horizons = self.horizons.lock().unwrap();
+        horizons.get(&(symbol.to_string(), interval)).copied()
+    }
+
+    pub fn footprint(&self) -> StoreFootprint {
+        let candles = self.candles.read().unwrap();
+        let (trades, trade_bytes) = self.trades.footprint();
+        let mut footprint = StoreFootprint {
+            candles: 0,
+            trades,
+            bytes: trade_bytes,
+        };
+        for list in candles.values().flat_map(|intervals| intervals.values()) {
+            footprint.candles += list.len();
+            footprint.bytes += list.capacity() * std::mem::size_of::<Candle>();
+        }
+        footprint
+    }
+
+    /// Frees about `bytes` by dropping the oldest candles of the finest intervals first,
+    /// keeping at least `MIN_RETAINED_CANDLES` of each. Returns the candles dropped.
+    pub fn evict_finest(&self, bytes: usize) -> usize {
+        let mut candles = self.candles.write().unwrap();
+        let mut horizons = self.horizons.lock().unwrap();
+        let mut remaining = bytes.div_ceil(std::mem::size_of::<Candle>());
+        let mut evicted = 0;
+
+        for interval in self.intervals() {
+            for (symbol, symbol_candles) in candles.iter_mut() {
+                if remaining == 0 {
+                    break;
+                }
+                let Some(list) = symbol_candles.get_mut(&interval) else {
+                    continue;
+                };
+                let count = remaining.min(list.len().saturating_sub(MIN_RETAINED_CANDLES));
+                if count == 0 {
+                    continue;
+                }
+                list.drain(..count);
+                list.shrink_to_fit();
+                // Coarser levels must not be re-derived from what is left of this one.
+                if !self.sub_minute.contains(&interval) {
+                    horizons.insert((symbol.clone(), interval), list[0].timestamp);
+                }
+                remaining -= count;
+                evicted += count;
+            }
+        }
+
+        self.evicted.fetch_add(evicted as u64, Ordering::Relaxed);
+        evicted
+    }
+
+    /// Candles dropped so far to stay within the memory budget.
+    pub fn evicted_candles(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
     pub fn get_min_max_timestamps(&self) -> Option<(i64, i64)> {
         let candles = self.candles.read().unwrap();
         if candles.is_empty() {
@@ -0,0 +1,118 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::config::env::ev;
+use crate::error::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub symbol: String,
+    pub payload: String,
+    pub error: String,
+    pub received_at: i64,
+}
+
+/// Events that could not be decoded or applied, kept for replay after a fix.
+/// When `DEAD_LETTER_PATH` is set the queue is mirrored to an NDJSON file so it
+/// survives the redeploy that ships the fix.
+#[derive(Debug)]
+pub struct DeadLetterStore {
+    entries: RwLock<Vec<DeadLetter>>,
+    path: Option<PathBuf>,
+}
+
+impl DeadLetterStore {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            entries: RwLock::new(Vec::new()),
+            path,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        let path = ev("DEAD_LETTER_PATH").ok().map(PathBuf::from);
+        let store = Self::new(path);
+        store.load()?;
+        Ok(store)
+    }
+
+    pub fn load(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let data = fs::read_to_string(path)?;
+        let loaded = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<DeadLetter>, _>>()?;
+        self.entries.write().unwrap().extend(loaded);
+        Ok(())
+    }
+
+    pub fn push(&self, symbol: &str, payload: &[u8], error: String) {
+        let entry = DeadLetter {
+            symbol: symbol.to_string(),
+            payload: String::from_utf8_lossy(payload).into_owned(),
+            error,
+            received_at: chrono::Utc::now().timestamp(),
+        };
+
+        if let Some(path) = &self.path {
+            if let Err(e) = Self::append(path, &entry) {
+                error!("Failed to persist dead letter for {}: {}", symbol, e);
+            }
+        }
+        self.entries.write().unwrap().push(entry);
+    }
+
+    fn append(path: &PathBuf, entry: &DeadLetter) -> Result<(), Error> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    pub fn list(&self, symbol: Option<&str>) -> Vec<DeadLetter> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|e| symbol.is_none_or(|s| e.symbol == s))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes and returns the entries for `symbol` (or all of them).
+    pub fn take(&self, symbol: Option<&str>) -> Vec<DeadLetter> {
+        let mut entries = self.entries.write().unwrap();
+        let (taken, kept) = entries
+            .drain(..)
+            .partition(|e| symbol.is_none_or(|s| e.symbol == s));
+        *entries = kept;
+        taken
+    }
+
+    /// Puts back entries that still fail and rewrites the mirror file.
+    pub fn restore(&self, failed: Vec<DeadLetter>) -> Result<(), Error> {
+        let mut entries = self.entries.write().unwrap();
+        entries.extend(failed);
+
+        if let Some(path) = &self.path {
+            let mut data = String::new();
+            for entry in entries.iter() {
+                data.push_str(&serde_json::to_string(entry)?);
+                data.push('\n');
+            }
+            fs::write(path, data)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use crate::storage::trades::Trade;
+
+/// Trades of one not yet final block, with the event keys already buffered so a
+/// re-delivered event is not counted twice.
+#[derive(Debug)]
+struct PendingBlock {
+    hash: String,
+    events: Vec<(String, String, Trade)>,
+}
+
+/// Trades from blocks fewer than `depth` confirmations deep, held back from the candles
+/// until their blocks are final. A block seen again under a different hash, or found
+/// replaced on the canonical chain, is dropped with everything after it, since the
+/// chain was reorganized from there.
+#[derive(Debug, Default)]
+pub struct PendingTrades {
+    depth: u64,
+    blocks: BTreeMap<i64, PendingBlock>,
+}
+
+impl PendingTrades {
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth,
+            blocks: BTreeMap::new(),
+        }
+    }
+
+    pub fn depth(&self) -> u64 {
+        self.depth
+    }
+
+    /// Whether `block` is final with the chain at `head`.
+    pub fn is_final(&self, block: i64, head: i64) -> bool {
+        block <= head.saturating_sub(self.depth as i64)
+    }
+
+    /// Buffers a trade; `event_key` identifies the event within its block.
+    pub fn push(
+        &mut self,
+        block: i64,
+        block_hash: &str,
+        event_key: String,
+        symbol: &str,
+        trade: Trade,
+    ) {
+        if self
+            .blocks
+            .get(&block)
+            .is_some_and(|pending| pending.hash != block_hash)
+        {
+            self.drop_from(block);
+        }
+        let pending = self.blocks.entry(block).or_insert_with(|| PendingBlock {
+            hash: block_hash.to_string(),
+            events: Vec::new(),
+        });
+        if pending.events.iter().all(|(key, _, _)| *key != event_key) {
+            pending.events.push((event_key, symbol.to_string(), trade));
+        }
+    }
+
+    /// Removes and returns the trades of blocks that are final with the chain at `head`,
+    /// oldest block first, as `(symbol, trade)` pairs.
+    pub fn seal(&mut self, head: i64) -> Vec<(String, Trade)> {
+        let pending = self
+            .blocks
+            .split_off(&(head.saturating_sub(self.depth as i64) + 1));
+        std::mem::replace(&mut self.blocks, pending)
+            .into_values()
+            .flat_map(|block| {
+                block
+                    .events
+                    .into_iter()
+                    .map(|(_, symbol, trade)| (symbol, trade))
+            })
+            .collect()
+    }
+
+    /// Drops the trades of `block` and every later block, returning how many there were.
+    pub fn drop_from(&mut self, block: i64) -> usize {
+        self.blocks
+            .split_off(&block)
+            .into_values()
+            .map(|block| block.events.len())
+            .sum()
+    }
+
+    /// Heights and hashes of the blocks held back, oldest first.
+    pub fn blocks(&self) -> Vec<(i64, String)> {
+        self.blocks
+            .iter()
+            .map(|(&height, block)| (height, block.hash.clone()))
+            .collect()
+    }
+
+    /// The oldest block still held back.
+    pub fn first_block(&self) -> Option<i64> {
+        self.blocks.keys().next().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.values().map(|block| block.events.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
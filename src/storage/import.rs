@@ -0,0 +1,173 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::error::{Error, ParsingError};
+use crate::storage::candles::Candle;
+
+/// What to do with an imported candle whose period is already stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the stored candle.
+    #[default]
+    Skip,
+    /// Replace the stored candle with the imported one.
+    Overwrite,
+    /// Keep the stored candle but widen its high and low to cover the imported one.
+    MergeExtremes,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "merge_extremes" => Ok(Self::MergeExtremes),
+            _ => Err(
+                ParsingError::StringParsingError(format!("Unknown conflict policy {}", s)).into(),
+            ),
+        }
+    }
+}
+
+/// An externally sourced candle in raw price and size units; `timestamp` is in seconds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportedCandle {
+    pub timestamp: i64,
+    pub open: u128,
+    pub high: u128,
+    pub low: u128,
+    pub close: u128,
+    pub volume: u128,
+    #[serde(default)]
+    pub quote_volume: Option<u128>,
+    #[serde(default)]
+    pub trade_count: Option<u64>,
+}
+
+impl ImportedCandle {
+    /// Checks the candle's own consistency, returning the reason it cannot be imported.
+    pub fn check(&self) -> Result<(), String> {
+        if self.low > self.high {
+            return Err("low is above high".to_string());
+        }
+        if self.open.max(self.close) > self.high {
+            return Err("open or close is above high".to_string());
+        }
+        if self.open.min(self.close) < self.low {
+            return Err("open or close is below low".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.timestamp, 0).single()
+    }
+
+    /// The stored form of the candle. Trade times span the whole period, so late trades
+    /// merged into it never move its open or close.
+    pub fn to_candle(&self, timestamp: DateTime<Utc>, end: DateTime<Utc>) -> Candle {
+        Candle {
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+            quote_volume: self
+                .quote_volume
+                .unwrap_or_else(|| self.close.saturating_mul(self.volume)),
+            trade_count: self.trade_count.unwrap_or(0),
+            wash_volume: 0,
+            wash_quote_volume: 0,
+            timestamp,
+            first_trade_at: Some(timestamp.timestamp_millis()),
+            last_trade_at: Some(end.timestamp_millis() - 1),
+            revision: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Insert,
+    Overwrite,
+    MergeExtremes,
+}
+
+/// A stored candle that the import changed, or would change on a dry run.
+#[derive(Debug, Serialize)]
+pub struct ImportChange {
+    pub action: ImportAction,
+    pub before: Option<Candle>,
+    pub after: Candle,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RejectedCandle {
+    pub timestamp: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub inserted: usize,
+    pub overwritten: usize,
+    pub merged: usize,
+    /// Conflicting candles kept as stored, including merges that changed nothing.
+    pub skipped: usize,
+    pub rejected: Vec<RejectedCandle>,
+    pub changes: Vec<ImportChange>,
+}
+
+/// Parses `timestamp,open,high,low,close,volume[,quote_volume[,trade_count]]` rows. A
+/// leading header row is skipped.
+pub fn parse_csv(body: &str) -> Result<Vec<ImportedCandle>, Error> {
+    let mut candles = Vec::new();
+    for (line_number, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (line_number == 0 && line.starts_with("timestamp")) {
+            continue;
+        }
+        let fields: Vec<_> = line.split(',').map(str::trim).collect();
+        if !(6..=8).contains(&fields.len()) {
+            return Err(csv_error(line_number, "expected 6 to 8 columns"));
+        }
+
+        let number = |index: usize| {
+            fields[index]
+                .parse::<u128>()
+                .map_err(|_| csv_error(line_number, &format!("invalid number {}", fields[index])))
+        };
+        let optional = |index: usize| match fields.get(index) {
+            Some(field) if !field.is_empty() => number(index).map(Some),
+            _ => Ok(None),
+        };
+        candles.push(ImportedCandle {
+            timestamp: fields[0]
+                .parse()
+                .map_err(|_| csv_error(line_number, "invalid timestamp"))?,
+            open: number(1)?,
+            high: number(2)?,
+            low: number(3)?,
+            close: number(4)?,
+            volume: number(5)?,
+            quote_volume: optional(6)?,
+            trade_count: optional(7)?
+                .map(|count| {
+                    u64::try_from(count)
+                        .map_err(|_| csv_error(line_number, "trade count too large"))
+                })
+                .transpose()?,
+        });
+    }
+    Ok(candles)
+}
+
+fn csv_error(line_number: usize, message: &str) -> Error {
+    ParsingError::StringParsingError(format!("CSV line {}: {}", line_number + 1, message)).into()
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Datelike, Duration, Utc};
+
+use crate::error::Error;
+
+pub const DEFAULT_INTERVALS: [u64; 9] = [60, 180, 300, 900, 1800, 3600, 86400, 604800, 2592000];
+pub const SUB_MINUTE_INTERVALS: [u64; 3] = [1, 5, 15];
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 3600;
+const DAY: u64 = 86400;
+const WEEK: u64 = 604800;
+/// What the stored "month" interval is: 30 days, not calendar months.
+const MONTH: u64 = 2592000;
+
+pub fn period_start(event_datetime: DateTime<Utc>, interval: u64) -> DateTime<Utc> {
+    match interval {
+        DAY => event_datetime
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(Utc)
+            .unwrap(),
+        WEEK => {
+            let naive_date = event_datetime.date_naive();
+            let weekday = naive_date.weekday().num_days_from_monday();
+            let start_of_week = naive_date - Duration::days(weekday as i64);
+            start_of_week
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_local_timezone(Utc)
+                .unwrap()
+        }
+        _ => {
+            let timestamp = event_datetime.timestamp();
+            let period = timestamp - timestamp.rem_euclid(interval as i64);
+            DateTime::from_timestamp(period, 0).expect("Invalid timestamp")
+        }
+    }
+}
+
+/// Candle interval in seconds for a TradingView resolution code: a count followed by `S`
+/// for seconds, nothing for minutes, or `D`, `W` and `M` for days, weeks and 30-day
+/// months. A unit alone counts one.
+pub fn resolution_seconds(resolution: &str) -> Option<u64> {
+    let digits = resolution.bytes().take_while(u8::is_ascii_digit).count();
+    let (count, unit) = resolution.split_at(digits);
+    let count: u64 = match count {
+        "" if unit.is_empty() => return None,
+        "" => 1,
+        count => count.parse().ok()?,
+    };
+    let unit = match unit {
+        "S" => 1,
+        "" => MINUTE,
+        "D" => DAY,
+        "W" => WEEK,
+        "M" => MONTH,
+        _ => return None,
+    };
+    count.checked_mul(unit).filter(|&seconds| seconds > 0)
+}
+
+/// Length in seconds of a window such as `24h`: a count followed by `s`, `m`, `h`, `d`
+/// or `w`.
+pub fn window_seconds(window: &str) -> Option<u64> {
+    let (count, unit) = window.split_at(window.len().checked_sub(1)?);
+    let unit = match unit {
+        "s" => 1,
+        "m" => MINUTE,
+        "h" => HOUR,
+        "d" => DAY,
+        "w" => WEEK,
+        _ => return None,
+    };
+    count
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(unit)
+        .filter(|&seconds| seconds > 0)
+}
+
+/// The TradingView resolution code of an interval, if it has one.
+pub fn resolution_code(interval: u64) -> Option<String> {
+    Some(match interval {
+        0 => return None,
+        MONTH => "1M".to_string(),
+        interval if interval < MINUTE => format!("{}S", interval),
+        interval if interval.is_multiple_of(WEEK) => format!("{}W", interval / WEEK),
+        interval if interval.is_multiple_of(DAY) => format!("{}D", interval / DAY),
+        interval if interval.is_multiple_of(MINUTE) => (interval / MINUTE).to_string(),
+        _ => return None,
+    })
+}
+
+/// Normalizes an event timestamp to milliseconds. Sources report either seconds or
+/// milliseconds; anything below 10^11 would be before 1973 in milliseconds.
+pub fn to_millis(timestamp: i64) -> i64 {
+    if timestamp.abs() < 100_000_000_000 {
+        timestamp * 1000
+    } else {
+        timestamp
+    }
+}
+
+pub fn period_end(start: DateTime<Utc>, interval: u64) -> DateTime<Utc> {
+    start + Duration::seconds(interval as i64)
+}
+
+/// Whether every `parent` bucket is an exact union of `child` buckets.
+/// Weeks start on Monday rather than at an epoch multiple, so only day divisors tile them.
+pub fn tiles(child: u64, parent: u64) -> bool {
+    if child >= parent || child == WEEK {
+        return false;
+    }
+    if parent == WEEK {
+        return DAY.is_multiple_of(child);
+    }
+    parent.is_multiple_of(child)
+}
+
+/// The interval pyramid: intervals sorted ascending, each non-base level paired with the
+/// coarsest finer level that tiles it and from which it is aggregated.
+#[derive(Debug, Clone)]
+pub struct IntervalPyramid {
+    intervals: Vec<u64>,
+    sources: Vec<Option<u64>>,
+}
+
+impl IntervalPyramid {
+    pub fn new(intervals: &[u64]) -> Result<Self, Error> {
+        let mut intervals = intervals.to_vec();
+        intervals.sort_unstable();
+        intervals.dedup();
+        if intervals.is_empty() || intervals[0] == 0 {
+            return Err(Error::InvalidIntervals(
+                "at least one non-zero interval is required".to_string(),
+            ));
+        }
+
+        let sources = intervals
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| {
+                if i == 0 {
+                    return Ok(None);
+                }
+                intervals[..i]
+                    .iter()
+                    .rev()
+                    .find(|&&child| tiles(child, level))
+                    .map(|&child| Some(child))
+                    .ok_or_else(|| {
+                        Error::InvalidIntervals(format!(
+                            "interval {} is not tiled by any finer interval",
+                            level
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { intervals, sources })
+    }
+
+    pub fn base(&self) -> u64 {
+        self.intervals[0]
+    }
+
+    pub fn intervals(&self) -> &[u64] {
+        &self.intervals
+    }
+
+    pub fn contains(&self, interval: u64) -> bool {
+        self.intervals.binary_search(&interval).is_ok()
+    }
+
+    /// Non-base levels with their source level, finest first.
+    pub fn levels(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.intervals
+            .iter()
+            .zip(&self.sources)
+            .filter_map(|(&level, source)| source.map(|source| (level, source)))
+    }
+}
+
+impl Default for IntervalPyramid {
+    fn default() -> Self {
+        Self::new(&DEFAULT_INTERVALS).expect("default intervals form a pyramid")
+    }
+}
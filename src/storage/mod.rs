@@ -1,2 +1,24 @@
+pub mod candle_events;
 pub mod candles;
+pub mod dead_letter;
+pub mod finality;
+pub mod import;
+pub mod interval;
+pub mod open_interest;
+pub mod order_book;
+pub mod pair_state;
+pub mod recorder;
+pub mod tenants;
+pub mod timeline;
+pub mod trades;
 pub mod trading_engine;
+
+/// Drops the oldest entries once `list` exceeds `max` by a tenth, leaving `max`.
+/// Trimming in batches keeps appends at the cap from shifting the whole list each time.
+pub fn trim_front<T>(list: &mut Vec<T>, max: usize) -> bool {
+    if list.len() <= max + max / 10 {
+        return false;
+    }
+    list.drain(0..(list.len() - max));
+    true
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::storage::candles::CandleStore;
+
+/// One trade as it moves positions: `size` base units from `seller` to `buyer`.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub buyer: String,
+    pub seller: String,
+    pub size: u128,
+    /// Milliseconds since the epoch.
+    pub event_time: i64,
+}
+
+#[derive(Debug, Default)]
+struct Positions {
+    /// Net position per address, positive when long.
+    net: HashMap<String, i128>,
+    /// Sum of the long positions, which equals the sum of the short ones.
+    open_interest: u128,
+    complete: bool,
+    /// Live fills received while history loads, applied once it has.
+    deferred: Vec<Fill>,
+}
+
+impl Positions {
+    fn shift(&mut self, address: &str, delta: i128) {
+        let net = self.net.entry(address.to_string()).or_default();
+        let before = (*net).max(0) as u128;
+        *net += delta;
+        let after = (*net).max(0) as u128;
+        if *net == 0 {
+            self.net.remove(address);
+        }
+        self.open_interest = self.open_interest - before + after;
+    }
+
+    fn apply(&mut self, fill: &Fill) -> (i64, u128) {
+        let size = fill.size as i128;
+        self.shift(&fill.buyer, size);
+        self.shift(&fill.seller, -size);
+        (fill.event_time, self.open_interest)
+    }
+}
+
+/// Open interest of a perpetual market, from the net position every address holds after
+/// its trades, kept as a series of candles whose prices are open interest in base units.
+/// History has to be applied in order for the series to be right, so live fills arriving
+/// while it loads wait until it has.
+#[derive(Debug)]
+pub struct OpenInterest {
+    symbol: String,
+    positions: Mutex<Positions>,
+    pub series: CandleStore,
+}
+
+impl OpenInterest {
+    pub fn new(symbol: &str, series: CandleStore) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            positions: Mutex::new(Positions::default()),
+            series,
+        }
+    }
+
+    /// Applies `fills` and records the open interest after each. Live fills are held back
+    /// until history is complete.
+    pub fn apply(&self, fills: Vec<Fill>, live: bool) {
+        let mut positions = self.positions.lock().unwrap();
+        if live && !positions.complete {
+            positions.deferred.extend(fills);
+            return;
+        }
+        for fill in &fills {
+            self.record(positions.apply(fill));
+        }
+    }
+
+    /// Marks history complete and applies the live fills held back meanwhile.
+    pub fn mark_complete(&self) {
+        let mut positions = self.positions.lock().unwrap();
+        positions.complete = true;
+        for fill in std::mem::take(&mut positions.deferred) {
+            self.record(positions.apply(&fill));
+        }
+    }
+
+    pub fn current(&self) -> u128 {
+        self.positions.lock().unwrap().open_interest
+    }
+
+    fn record(&self, (event_time, open_interest): (i64, u128)) {
+        self.series
+            .add_trade(&self.symbol, open_interest, 0, event_time);
+    }
+}
@@ -0,0 +1,204 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// Reads an event's `order_type`, `Buy` or `Sell` in any case.
+    pub fn parse(order_type: &str) -> Option<Self> {
+        if order_type.eq_ignore_ascii_case("buy") {
+            Some(Side::Buy)
+        } else if order_type.eq_ignore_ascii_case("sell") {
+            Some(Side::Sell)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RestingOrder {
+    side: Side,
+    price: u128,
+    amount: u128,
+}
+
+/// What happened to an order before its Open event was seen.
+#[derive(Debug, Clone, Copy)]
+enum EarlyChange {
+    Cancelled,
+    Filled(u128),
+}
+
+/// Best bid and ask, raw prices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Top {
+    pub bid: Option<u128>,
+    pub ask: Option<u128>,
+}
+
+impl Top {
+    pub fn mid(&self) -> Option<u128> {
+        Some((self.bid? + self.ask?) / 2)
+    }
+}
+
+/// Aggregated resting amounts per price, best prices first.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DepthSnapshot {
+    pub bids: Vec<(u128, u128)>,
+    pub asks: Vec<(u128, u128)>,
+    pub orders: usize,
+}
+
+#[derive(Debug, Default)]
+struct Book {
+    orders: HashMap<String, RestingOrder>,
+    bids: BTreeMap<u128, u128>,
+    asks: BTreeMap<u128, u128>,
+    /// Cancels and fills of orders not opened yet. Live events are applied while history
+    /// loads, so an order's Open can arrive after what later happened to it.
+    early: HashMap<String, EarlyChange>,
+    complete: bool,
+}
+
+impl Book {
+    fn levels(&mut self, side: Side) -> &mut BTreeMap<u128, u128> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    fn add_level(&mut self, side: Side, price: u128, amount: u128) {
+        *self.levels(side).entry(price).or_default() += amount;
+    }
+
+    fn remove_level(&mut self, side: Side, price: u128, amount: u128) {
+        let levels = self.levels(side);
+        if let Some(level) = levels.get_mut(&price) {
+            *level = level.saturating_sub(amount);
+            if *level == 0 {
+                levels.remove(&price);
+            }
+        }
+    }
+
+    fn top(&self) -> Top {
+        Top {
+            bid: self.bids.last_key_value().map(|(&price, _)| price),
+            ask: self.asks.first_key_value().map(|(&price, _)| price),
+        }
+    }
+}
+
+/// Resting limit orders of one pair, rebuilt from its Open, Cancel and Trade events.
+/// Orders are tracked from the events indexed since startup, so the book is complete
+/// once history from the pair's start block has loaded.
+#[derive(Debug, Default)]
+pub struct OrderBook {
+    book: RwLock<Book>,
+}
+
+impl OrderBook {
+    pub fn open(&self, order_id: &str, side: Side, price: u128, amount: u128) {
+        let mut book = self.book.write().unwrap();
+        let amount = match book.early.remove(order_id) {
+            Some(EarlyChange::Cancelled) => return,
+            Some(EarlyChange::Filled(filled)) => amount.saturating_sub(filled),
+            None => amount,
+        };
+        if amount == 0 || book.orders.contains_key(order_id) {
+            return;
+        }
+        book.orders.insert(
+            order_id.to_string(),
+            RestingOrder {
+                side,
+                price,
+                amount,
+            },
+        );
+        book.add_level(side, price, amount);
+    }
+
+    pub fn cancel(&self, order_id: &str) {
+        let mut book = self.book.write().unwrap();
+        match book.orders.remove(order_id) {
+            Some(order) => book.remove_level(order.side, order.price, order.amount),
+            None if !book.complete => {
+                book.early
+                    .insert(order_id.to_string(), EarlyChange::Cancelled);
+            }
+            None => {}
+        }
+    }
+
+    /// Takes `amount` off a resting order, removing it once fully filled.
+    pub fn fill(&self, order_id: &str, amount: u128) {
+        let mut book = self.book.write().unwrap();
+        let Some(order) = book.orders.get_mut(order_id) else {
+            if book.complete {
+                return;
+            }
+            let early = book
+                .early
+                .entry(order_id.to_string())
+                .or_insert(EarlyChange::Filled(0));
+            if let EarlyChange::Filled(filled) = early {
+                *filled += amount;
+            }
+            return;
+        };
+        let filled = amount.min(order.amount);
+        order.amount -= filled;
+        let (side, price, remaining) = (order.side, order.price, order.amount);
+        if remaining == 0 {
+            book.orders.remove(order_id);
+        }
+        book.remove_level(side, price, filled);
+    }
+
+    /// Marks the book complete once history has loaded. Changes to orders never opened
+    /// are forgotten, as every Open still to come is newer than them: they were fills of
+    /// orders that never rested. From then on such changes are ignored.
+    pub fn mark_complete(&self) {
+        let mut book = self.book.write().unwrap();
+        book.early.clear();
+        book.complete = true;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.book.read().unwrap().complete
+    }
+
+    pub fn top(&self) -> Top {
+        self.book.read().unwrap().top()
+    }
+
+    /// The best `levels` price levels of each side.
+    pub fn depth(&self, levels: usize) -> DepthSnapshot {
+        let book = self.book.read().unwrap();
+        DepthSnapshot {
+            bids: book
+                .bids
+                .iter()
+                .rev()
+                .take(levels)
+                .map(|(&price, &amount)| (price, amount))
+                .collect(),
+            asks: book
+                .asks
+                .iter()
+                .take(levels)
+                .map(|(&price, &amount)| (price, amount))
+                .collect(),
+            orders: book.orders.len(),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::env::ev;
+use crate::error::Error;
+
+/// The earliest trade ever seen for a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirstTrade {
+    pub block: i64,
+    /// Milliseconds since the epoch.
+    pub event_time: i64,
+}
+
+impl FirstTrade {
+    /// UTC date of the first trade, e.g. `2024-01-01`.
+    pub fn listing_date(&self) -> Option<String> {
+        DateTime::from_timestamp_millis(self.event_time)
+            .map(|listed| listed.date_naive().to_string())
+    }
+}
+
+/// Per-pair facts that retention or a restart would otherwise lose.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PairState {
+    pub first_trade: Option<FirstTrade>,
+    /// Newest block whose events were applied to the pair's candles.
+    pub last_processed_block: Option<i64>,
+}
+
+/// JSON file holding `PairState` keyed by symbol. Without a path nothing is persisted.
+#[derive(Debug, Default)]
+pub struct PairStateFile {
+    path: Option<PathBuf>,
+}
+
+impl PairStateFile {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ev("PAIR_STATE_PATH").ok().map(PathBuf::from))
+    }
+
+    pub fn load(&self) -> Result<HashMap<String, PairState>, Error> {
+        match &self.path {
+            Some(path) if path.exists() => Ok(serde_json::from_str(&fs::read_to_string(path)?)?),
+            _ => Ok(HashMap::new()),
+        }
+    }
+
+    /// Replaces the file atomically so a crash never leaves it half written.
+    pub fn save(&self, state: &HashMap<String, PairState>) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(state)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
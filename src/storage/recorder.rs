@@ -0,0 +1,111 @@
+use chrono::{DateTime, NaiveDate};
+use log::error;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::env::ev;
+use crate::error::Error;
+use crate::indexer::order_event_handler::PangeaOrderEvent;
+
+/// Size a recording file rolls over at unless configured otherwise.
+pub const RECORD_MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// The file a pair's events currently go to.
+struct RecordFile {
+    day: NaiveDate,
+    index: u32,
+    bytes: u64,
+    file: File,
+}
+
+/// Copies every received order event to per-pair NDJSON files, so incidents can be
+/// replayed locally with `EVENT_SOURCE=file` and candles rebuilt offline. Files are named
+/// `<symbol>-<event day>-<index>.ndjson` and roll over to the next index once they
+/// reach the size limit. Recordings may repeat events, e.g. history fetched again after a
+/// restart; replay drops the repeats.
+pub struct EventRecorder {
+    dir: PathBuf,
+    max_bytes: u64,
+    files: Mutex<HashMap<String, RecordFile>>,
+}
+
+impl EventRecorder {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Result<Self, Error> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Records into `RECORD_EVENTS_DIR` when it is set, rolling files over at
+    /// `RECORD_MAX_FILE_MB` (256 by default).
+    pub fn from_env() -> Result<Option<Self>, Error> {
+        let Ok(dir) = ev("RECORD_EVENTS_DIR") else {
+            return Ok(None);
+        };
+        let max_bytes = ev("RECORD_MAX_FILE_MB")
+            .ok()
+            .and_then(|mb| mb.parse::<u64>().ok())
+            .map_or(RECORD_MAX_FILE_BYTES, |mb| mb * 1024 * 1024);
+        Self::new(PathBuf::from(dir), max_bytes).map(Some)
+    }
+
+    /// Appends `event` to the file of `symbol` for the event's day. Failures are logged,
+    /// since a recording must never hold up indexing.
+    pub fn record(&self, symbol: &str, event: &PangeaOrderEvent) {
+        if let Err(e) = self.write(symbol, event) {
+            error!("Failed to record event for {}: {}", symbol, e);
+        }
+    }
+
+    fn write(&self, symbol: &str, event: &PangeaOrderEvent) -> Result<(), Error> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        let day = DateTime::from_timestamp_millis(event.event_time_ms())
+            .unwrap_or_default()
+            .date_naive();
+
+        let mut files = self.files.lock().unwrap();
+        let current = files.get(symbol);
+        let reusable = current.is_some_and(|current| {
+            current.day == day && current.bytes + line.len() as u64 <= self.max_bytes
+        });
+        if !reusable {
+            let index = current
+                .filter(|current| current.day == day)
+                .map_or(0, |current| current.index + 1);
+            files.insert(symbol.to_string(), self.open(symbol, day, index)?);
+        }
+
+        let current = files.get_mut(symbol).unwrap();
+        current.file.write_all(&line)?;
+        current.bytes += line.len() as u64;
+        Ok(())
+    }
+
+    /// Opens the first file of `symbol` and `day` from `index` on that has room left, so
+    /// a restarted process appends to what it recorded before.
+    fn open(&self, symbol: &str, day: NaiveDate, mut index: u32) -> Result<RecordFile, Error> {
+        loop {
+            let path = self
+                .dir
+                .join(format!("{}-{}-{:04}.ndjson", symbol, day, index));
+            let bytes = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+            if bytes < self.max_bytes {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                return Ok(RecordFile {
+                    day,
+                    index,
+                    bytes,
+                    file,
+                });
+            }
+            index += 1;
+        }
+    }
+}
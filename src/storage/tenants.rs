@@ -0,0 +1,105 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::env::ev;
+use crate::error::{Error, ParsingError};
+use crate::storage::dead_letter::DeadLetterStore;
+use crate::storage::pair_state::PairStateFile;
+use crate::storage::recorder::{EventRecorder, RECORD_MAX_FILE_BYTES};
+use crate::storage::trading_engine::TradingEngine;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TenantConfig {
+    pub name: String,
+    pub config: String,
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    pub admin_key: Option<String>,
+    pub dead_letter_path: Option<String>,
+    pub pair_state_path: Option<String>,
+    /// Directory to record the tenant's events to, as `RECORD_EVENTS_DIR` does.
+    pub record_events_dir: Option<String>,
+}
+
+pub struct Tenant {
+    pub engine: Arc<TradingEngine>,
+    pub api_keys: Vec<String>,
+    pub admin_key: Option<String>,
+}
+
+impl Tenant {
+    /// Tenants without API keys are public.
+    pub fn authorize(&self, key: Option<&str>) -> bool {
+        self.api_keys.is_empty() || key.is_some_and(|k| self.api_keys.iter().any(|a| a == k))
+    }
+}
+
+/// Independent exchanges served from one process under `/t/<tenant>/...`,
+/// each with its own `TradingEngine`, pair config and keys.
+#[derive(Default)]
+pub struct TenantRegistry {
+    tenants: HashMap<String, Tenant>,
+}
+
+impl TenantRegistry {
+    pub fn load_from_env() -> Result<Self, Error> {
+        match ev("TENANTS_CONFIG") {
+            Ok(path) => Self::load(&path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)?;
+        let configs: Vec<TenantConfig> = serde_json::from_str(&data)?;
+
+        let mut tenants = HashMap::new();
+        for config in configs {
+            let valid_name = !config.name.is_empty()
+                && config
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_name {
+                return Err(ParsingError::StringParsingError(format!(
+                    "Invalid tenant name '{}'",
+                    config.name
+                ))
+                .into());
+            }
+
+            let (pairs, chain) = TradingEngine::load_config(&config.config)?;
+            let dead_letters = DeadLetterStore::new(config.dead_letter_path.map(PathBuf::from));
+            dead_letters.load()?;
+
+            let pair_state = PairStateFile::new(config.pair_state_path.map(PathBuf::from));
+            let recorder = config
+                .record_events_dir
+                .map(|dir| EventRecorder::new(PathBuf::from(dir), RECORD_MAX_FILE_BYTES))
+                .transpose()?;
+            let engine = TradingEngine::new(pairs, chain, dead_letters)
+                .with_pair_state(pair_state)?
+                .with_recorder(recorder);
+
+            let tenant = Tenant {
+                engine: Arc::new(engine),
+                api_keys: config.api_keys,
+                admin_key: config.admin_key,
+            };
+            tenants.insert(config.name, tenant);
+        }
+
+        Ok(Self { tenants })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tenant> {
+        self.tenants.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Tenant)> {
+        self.tenants.iter()
+    }
+}
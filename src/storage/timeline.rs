@@ -0,0 +1,65 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::storage::trim_front;
+
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationalKind {
+    /// Indexing was paused from the admin API.
+    Paused,
+    Resumed,
+    /// The pair's indexer failed and is restarted after a backoff.
+    IndexerRestart,
+    /// Live subscriptions kept ending before delivering a block.
+    LiveInterrupted,
+    /// Live events flow again after an interruption.
+    LiveRecovered,
+}
+
+/// Something that happened to a pair's indexing, worth showing next to its chart.
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationalEvent {
+    /// Seconds since the epoch.
+    pub at: i64,
+    pub kind: OperationalKind,
+    pub detail: String,
+}
+
+/// The latest operational events per pair, kept in memory only, so a restarted process
+/// starts with an empty timeline.
+#[derive(Debug, Default)]
+pub struct Timeline {
+    events: RwLock<HashMap<String, Vec<OperationalEvent>>>,
+}
+
+impl Timeline {
+    pub fn record(&self, symbol: &str, kind: OperationalKind, detail: impl Into<String>) {
+        let mut events = self.events.write().unwrap();
+        let list = events.entry(symbol.to_string()).or_default();
+        list.push(OperationalEvent {
+            at: chrono::Utc::now().timestamp(),
+            kind,
+            detail: detail.into(),
+        });
+        trim_front(list, MAX_EVENTS);
+    }
+
+    /// Events of `symbol` with `from <= at <= to`, oldest first.
+    pub fn range(&self, symbol: &str, from: i64, to: i64) -> Vec<OperationalEvent> {
+        self.events
+            .read()
+            .unwrap()
+            .get(symbol)
+            .map(|list| {
+                list.iter()
+                    .filter(|event| (from..=to).contains(&event.at))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
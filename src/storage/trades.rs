@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::storage::trim_front;
+
+const MAX_TRADES: usize = 1000000;
+
+/// A raw trade as received from the indexer, before aggregation into candles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub price: u128,
+    pub volume: u128,
+    /// Milliseconds since the epoch.
+    pub event_time: i64,
+    /// Detected as a self-trade, with the same address on both sides.
+    #[serde(default)]
+    pub wash: bool,
+}
+
+/// What a pair does with detected self-trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WashTradePolicy {
+    /// Keep them in the candles and report their volume separately, so clean volume is
+    /// volume minus wash volume.
+    Track,
+    /// Leave them out of the candles altogether.
+    Exclude,
+}
+
+/// Bounds a pair's trade prices must stay within, in display units. Trades outside them
+/// are quarantined rather than applied, so a corrupted event cannot set a candle's high
+/// or low for good.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PriceFilter {
+    /// Largest relative move from the close before the trade, e.g. 0.5 for 50%.
+    #[serde(default)]
+    pub max_deviation: Option<f64>,
+    #[serde(default)]
+    pub min_price: Option<f64>,
+    #[serde(default)]
+    pub max_price: Option<f64>,
+}
+
+impl PriceFilter {
+    /// Why `price` is an outlier given the `previous` close, or `None` if it passes.
+    pub fn check(&self, price: f64, previous: Option<f64>) -> Option<String> {
+        if self.min_price.is_some_and(|min| price < min) {
+            return Some(format!(
+                "price {} below minimum {:?}",
+                price, self.min_price
+            ));
+        }
+        if self.max_price.is_some_and(|max| price > max) {
+            return Some(format!(
+                "price {} above maximum {:?}",
+                price, self.max_price
+            ));
+        }
+        match (self.max_deviation, previous) {
+            (Some(max), Some(previous)) if previous > 0.0 => {
+                let deviation = (price - previous).abs() / previous;
+                (deviation > max).then(|| {
+                    format!(
+                        "price {} deviates {:.4} from previous close {}, above {}",
+                        price, deviation, previous, max
+                    )
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What makes a trade notable enough for a chart mark, in display units. A trade is
+/// notable when it reaches any threshold set.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MarkThresholds {
+    /// Smallest notable size in base-asset units.
+    #[serde(default)]
+    pub min_size: Option<f64>,
+    /// Smallest notable value in quote-asset units.
+    #[serde(default)]
+    pub min_value: Option<f64>,
+}
+
+impl MarkThresholds {
+    pub fn is_notable(&self, size: f64, price: f64) -> bool {
+        self.min_size.is_some_and(|min| size >= min)
+            || self.min_value.is_some_and(|min| size * price >= min)
+    }
+}
+
+/// Bounded archive of raw trades per symbol, ordered by event time.
+#[derive(Debug, Default)]
+pub struct TradeArchive {
+    trades: RwLock<HashMap<String, Vec<Trade>>>,
+    /// Event time of the oldest retained trade once older ones have been dropped.
+    horizons: RwLock<HashMap<String, i64>>,
+    /// Trades dropped to stay within the memory budget.
+    evicted: AtomicU64,
+}
+
+impl TradeArchive {
+    pub fn record(&self, symbol: &str, trade: Trade) {
+        let mut trades = self.trades.write().unwrap();
+        let list = trades.entry(symbol.to_string()).or_default();
+        let index = list.partition_point(|t| t.event_time <= trade.event_time);
+        list.insert(index, trade);
+
+        if trim_front(list, MAX_TRADES) {
+            self.horizons
+                .write()
+                .unwrap()
+                .insert(symbol.to_string(), list[0].event_time);
+        }
+    }
+
+    /// Trades with `from <= event_time < to`.
+    pub fn range(&self, symbol: &str, from: i64, to: i64) -> Vec<Trade> {
+        let trades = self.trades.read().unwrap();
+        let Some(list) = trades.get(symbol) else {
+            return vec![];
+        };
+        let lo = list.partition_point(|t| t.event_time < from);
+        let hi = list.partition_point(|t| t.event_time < to);
+        list[lo..hi.max(lo)].to_vec()
+    }
+
+    /// Replaces the trades with `from <= event_time < to` by `replacement`, as a standby
+    /// takes over the primary's trades of the periods that changed.
+    pub fn replace_range(&self, symbol: &str, from: i64, to: i64, replacement: Vec<Trade>) {
+        let mut trades = self.trades.write().unwrap();
+        let list = trades.entry(symbol.to_string()).or_default();
+        let lo = list.partition_point(|t| t.event_time < from);
+        let hi = list.partition_point(|t| t.event_time < to).max(lo);
+        list.splice(lo..hi, replacement);
+
+        if trim_front(list, MAX_TRADES) {
+            self.horizons
+                .write()
+                .unwrap()
+                .insert(symbol.to_string(), list[0].event_time);
+        }
+    }
+
+    /// Event time of the oldest trade retained for `symbol` once older ones were dropped.
+    pub fn horizon(&self, symbol: &str) -> Option<i64> {
+        self.horizons.read().unwrap().get(symbol).copied()
+    }
+
+    /// Moves the horizon of `symbol` up to `horizon`, as a standby follows the primary's.
+    pub fn restore_horizon(&self, symbol: &str, horizon: i64) {
+        let mut horizons = self.horizons.write().unwrap();
+        let current = horizons.entry(symbol.to_string()).or_insert(horizon);
+        *current = (*current).max(horizon);
+    }
+
+    /// Retained trades and the bytes reserved for them.
+    pub fn footprint(&self) -> (usize, usize) {
+        let trades = self.trades.read().unwrap();
+        trades.values().fold((0, 0), |(count, bytes), list| {
+            (
+                count + list.len(),
+                bytes + list.capacity() * std::mem::size_of::<Trade>(),
+            )
+        })
+    }
+
+    /// Frees about `bytes` by dropping the oldest trades, after which `covers` sends
+    /// queries reaching them back to the candles. Returns the trades dropped.
+    pub fn evict(&self, bytes: usize) -> usize {
+        let mut trades = self.trades.write().unwrap();
+        let mut horizons = self.horizons.write().unwrap();
+        let mut remaining = bytes.div_ceil(std::mem::size_of::<Trade>());
+        let mut evicted = 0;
+
+        for (symbol, list) in trades.iter_mut() {
+            if remaining == 0 {
+                break;
+            }
+            let count = remaining.min(list.len());
+            if count == 0 {
+                continue;
+            }
+            let newest_dropped = list[count - 1].event_time;
+            list.drain(..count);
+            list.shrink_to_fit();
+            let horizon = list.first().map_or(newest_dropped + 1, |t| t.event_time);
+            horizons.insert(symbol.clone(), horizon);
+            remaining -= count;
+            evicted += count;
+        }
+
+        self.evicted.fetch_add(evicted as u64, Ordering::Relaxed);
+        evicted
+    }
+
+    /// Trades dropped so far to stay within the memory budget.
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Whether every trade at or after `from` is still retained.
+    pub fn covers(&self, symbol: &str, from: i64) -> bool {
+        self.horizons
+            .read()
+            .unwrap()
+            .get(symbol)
+            .is_none_or(|&horizon| horizon <= from)
+    }
+}
@@ -1,10 +1,54 @@
+use crate::config::env::ev;
 use crate::error::Error;
+use crate::indexer::control::PairControls;
+use crate::indexer::source::SourceKind;
+use crate::indexer::status::IndexerStatus;
+use crate::indexer::synthetic::SyntheticSettings;
+use crate::storage::candle_events::CandleEvents;
 use crate::storage::candles::CandleStore;
-use serde::Deserialize;
+use crate::storage::dead_letter::DeadLetterStore;
+use crate::storage::interval::{
+    resolution_code, IntervalPyramid, DEFAULT_INTERVALS, SUB_MINUTE_INTERVALS,
+};
+use crate::storage::open_interest::OpenInterest;
+use crate::storage::order_book::OrderBook;
+use crate::storage::pair_state::{PairState, PairStateFile};
+use crate::storage::recorder::EventRecorder;
+use crate::storage::timeline::Timeline;
+use crate::storage::trades::{MarkThresholds, PriceFilter, WashTradePolicy};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// A market contract of a pair and the blocks it serves.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContractRange {
+    pub contract_id: String,
+    pub from_block: i64,
+    /// Last block of the contract; open-ended for the one in use.
+    #[serde(default)]
+    pub to_block: Option<i64>,
+}
+
+/// Settings shared by every pair indexed from one chain.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ChainConfig {
+    /// Confirmations a block needs before its trades enter the candles; defaults to 0.
+    /// Trades from shallower blocks are held back and dropped if the block is replaced.
+    #[serde(default)]
+    pub finality_depth: u64,
+}
+
+/// `config.json` in its object form: the pairs, with settings per chain keyed by the
+/// values `CHAIN` takes. A bare list of pairs leaves every chain at the defaults.
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    chains: HashMap<String, ChainConfig>,
+    pairs: Vec<TradingPairConfig>,
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct TradingPairConfig {
@@ -13,74 +57,541 @@ pub struct TradingPairConfig {
     pub start_block: i64,
     pub description: String,
     pub decimals: i32,
+    /// Contracts the pair was served by in turn, for markets that were redeployed: each
+    /// one's events from its `from_block` to its `to_block` make up one history, and the
+    /// last is followed live. Without them `contract_id` serves every block from
+    /// `start_block`.
+    #[serde(default)]
+    pub contracts: Option<Vec<ContractRange>>,
+    /// Where the pair's events are read from: `pangea` (the default), `fuel_node`,
+    /// `envio`, `subsquid`, `file` or `synthetic`.
+    #[serde(default)]
+    pub source: Option<SourceKind>,
+    /// How the pair's market moves when its events are `synthetic`.
+    #[serde(default)]
+    pub synthetic: Option<SyntheticSettings>,
+    /// Decimals of raw prices; falls back to `decimals`.
+    #[serde(default)]
+    pub price_decimals: Option<i32>,
+    /// Decimals of raw base-asset amounts; falls back to `decimals`.
+    #[serde(default)]
+    pub size_decimals: Option<i32>,
+    /// Store flat candles for periods without trades; defaults to true. Illiquid pairs can
+    /// turn this off and have gaps synthesized at read time instead.
+    #[serde(default)]
+    pub fill_gaps: Option<bool>,
+    /// Seconds without trades after which the market is flagged inactive; defaults to a day.
+    #[serde(default)]
+    pub inactive_after_secs: Option<u64>,
+    /// Also build candles from the mid-price between best bid and ask, served by
+    /// `/history?price_source=mid`; thin markets get misleading wicks from last trades.
+    #[serde(default)]
+    pub mid_price_candles: Option<bool>,
+    /// Perpetual market: also track open interest, served by `/open_interest`.
+    #[serde(default)]
+    pub perpetual: Option<bool>,
+    /// Whether to look for self-trades and `track` or `exclude` them; off by default.
+    #[serde(default)]
+    pub wash_trades: Option<WashTradePolicy>,
+    /// Price bounds outside which trades are quarantined to the dead-letter queue instead
+    /// of entering the candles; off by default.
+    #[serde(default)]
+    pub price_filter: Option<PriceFilter>,
+    /// Candle intervals in seconds; defaults to 1, 5 and 15 seconds plus 1, 3, 5, 15 and
+    /// 30 minutes, 1 hour, 1 day, 1 week and 30 days. Intervals under a minute are written
+    /// per trade and kept briefly; every longer one but the shortest must be an exact
+    /// multiple of a shorter one, from which it is aggregated.
+    #[serde(default)]
+    pub intervals: Option<Vec<u64>>,
+    /// Asset id of the base asset. When set, only Trade events for that asset leg count,
+    /// so a fill emitted for both assets is counted once.
+    #[serde(default)]
+    pub base_asset: Option<String>,
+    /// Asset id of the quote asset.
+    #[serde(default)]
+    pub quote_asset: Option<String>,
+    /// Trades `/marks` shows on charts; no marks without it.
+    #[serde(default)]
+    pub marks: Option<MarkThresholds>,
+    /// Decimal places shown for prices, overriding the server-wide `max_decimals`.
+    #[serde(default)]
+    pub price_display_decimals: Option<u32>,
+    /// Decimal places shown for volumes, overriding the server-wide `max_decimals`.
+    #[serde(default)]
+    pub size_display_decimals: Option<u32>,
+    /// Exchange the pair is listed under in symbol info and search; defaults to
+    /// `CryptoExchange`.
+    #[serde(default)]
+    pub exchange: Option<String>,
+    /// TradingView symbol type; defaults to `crypto`.
+    #[serde(default)]
+    pub symbol_type: Option<String>,
+}
+
+impl TradingPairConfig {
+    pub fn price_decimals(&self) -> u32 {
+        self.price_decimals.unwrap_or(self.decimals) as u32
+    }
+
+    pub fn size_decimals(&self) -> u32 {
+        self.size_decimals.unwrap_or(self.decimals) as u32
+    }
+
+    pub fn fill_gaps(&self) -> bool {
+        self.fill_gaps.unwrap_or(true)
+    }
+
+    pub fn inactive_after_secs(&self) -> u64 {
+        self.inactive_after_secs.unwrap_or(86400)
+    }
+
+    pub fn mid_price_candles(&self) -> bool {
+        self.mid_price_candles.unwrap_or(false)
+    }
+
+    pub fn exchange(&self) -> &str {
+        self.exchange.as_deref().unwrap_or("CryptoExchange")
+    }
+
+    pub fn symbol_type(&self) -> &str {
+        self.symbol_type.as_deref().unwrap_or("crypto")
+    }
+
+    /// The pair's contracts in block order.
+    pub fn contracts(&self) -> Vec<ContractRange> {
+        let mut contracts = self.contracts.clone().unwrap_or_else(|| {
+            vec![ContractRange {
+                contract_id: self.contract_id.clone(),
+                from_block: self.start_block,
+                to_block: None,
+            }]
+        });
+        contracts.sort_by_key(|contract| contract.from_block);
+        contracts
+    }
+
+    /// The pair as read from one of its contracts.
+    pub fn with_contract(&self, contract: &ContractRange) -> Self {
+        let mut config = self.clone();
+        config.contract_id = contract.contract_id.clone();
+        config.contracts = None;
+        config
+    }
+
+    /// The pair as read from its newest contract, which live events come from.
+    pub fn live_contract(&self) -> Self {
+        match self.contracts().last() {
+            Some(contract) => self.with_contract(contract),
+            None => self.clone(),
+        }
+    }
+
+    pub fn perpetual(&self) -> bool {
+        self.perpetual.unwrap_or(false)
+    }
+
+    pub fn source(&self) -> SourceKind {
+        self.source.unwrap_or_default()
+    }
+
+    /// Every interval the pair stores, shortest first.
+    pub fn intervals(&self) -> Vec<u64> {
+        let mut intervals = match &self.intervals {
+            Some(intervals) => intervals.clone(),
+            None => SUB_MINUTE_INTERVALS
+                .iter()
+                .chain(&DEFAULT_INTERVALS)
+                .copied()
+                .collect(),
+        };
+        intervals.sort_unstable();
+        intervals.dedup();
+        intervals
+    }
+
+    /// The pair's intervals split into sub-minute intervals and the minute-and-longer
+    /// pyramid aggregated from them.
+    pub fn interval_levels(&self) -> Result<(Vec<u64>, IntervalPyramid), Error> {
+        let (sub_minute, pyramid): (Vec<u64>, Vec<u64>) = self
+            .intervals()
+            .into_iter()
+            .filter(|&interval| interval > 0)
+            .partition(|&interval| interval < 60);
+        let pyramid = IntervalPyramid::new(&pyramid)
+            .map_err(|e| Error::InvalidIntervals(format!("{}: {}", self.symbol, e)))?;
+        Ok((sub_minute, pyramid))
+    }
+
+    /// TradingView resolution codes of the pair's intervals.
+    pub fn resolutions(&self) -> Vec<String> {
+        self.intervals()
+            .into_iter()
+            .filter_map(resolution_code)
+            .collect()
+    }
+
+    /// The resolution fields of the pair's TradingView symbol info.
+    pub fn resolution_info(&self) -> serde_json::Map<String, serde_json::Value> {
+        let intervals = self.intervals();
+        let multipliers = |unit: u64, range: std::ops::Range<u64>| -> Vec<String> {
+            intervals
+                .iter()
+                .filter(|&&interval| range.contains(&interval) && interval % unit == 0)
+                .map(|interval| (interval / unit).to_string())
+                .collect()
+        };
+        let resolutions = self.resolutions();
+        let suffixed = |unit: char| -> Vec<String> {
+            resolutions
+                .iter()
+                .filter_map(|code| code.strip_suffix(unit))
+                .map(str::to_string)
+                .collect()
+        };
+        // Charts open on the daily bar when it is kept, else on the coarsest one.
+        let default_resolution = resolutions
+            .iter()
+            .find(|code| *code == "1D")
+            .or(resolutions.last());
+        let info = json!({
+            "has_seconds": intervals.iter().any(|&interval| interval < 60),
+            "seconds_multipliers": multipliers(1, 0..60),
+            "has_intraday": intervals.iter().any(|&interval| interval < 86400),
+            "intraday_multipliers": multipliers(60, 60..86400),
+            "has_daily": intervals.iter().any(|&interval| interval >= 86400),
+            "daily_multipliers": suffixed('D'),
+            "has_weekly_and_monthly": intervals.iter().any(|&interval| interval >= 604800),
+            "weekly_multipliers": suffixed('W'),
+            "monthly_multipliers": suffixed('M'),
+            "default_resolution": default_resolution,
+            "supported_resolutions": resolutions,
+        });
+        match info {
+            serde_json::Value::Object(info) => info,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Whether a market is still trading, as opposed to its indexer being broken.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MarketActivity {
+    /// Seconds since the epoch.
+    pub last_trade_at: Option<i64>,
+    pub inactive: bool,
+}
+
+/// What the engine keeps for one pair.
+pub struct Pair {
+    pub config: TradingPairConfig,
+    pub store: Arc<CandleStore>,
+    /// Mid-price candles when `mid_price_candles` is on. Each best bid or ask change is
+    /// written as a zero-volume trade at the new mid-price, from the time the pair's
+    /// order book is complete.
+    pub mid_store: Option<Arc<CandleStore>>,
+    /// Resting orders, from the pair's Open, Cancel and Trade events.
+    pub book: Arc<OrderBook>,
+    /// Open interest when the pair is `perpetual`.
+    pub open_interest: Option<Arc<OpenInterest>>,
+}
+
+impl Pair {
+    /// Builds the pair's stores; bar events of its trade candles go to `events`.
+    pub fn new(config: TradingPairConfig, chain: &ChainConfig, events: &Arc<CandleEvents>) -> Self {
+        let (sub_minute, pyramid) = config.interval_levels().unwrap_or_default();
+        let series = || {
+            CandleStore::new()
+                .with_intervals(sub_minute.clone(), pyramid.clone())
+                .with_fill_gaps(config.fill_gaps())
+        };
+        let store = series()
+            .with_finality_depth(chain.finality_depth)
+            .with_wash_trades(config.wash_trades)
+            .with_base_asset(config.base_asset.clone())
+            .with_price_filter(config.price_filter, config.price_decimals())
+            .with_events(Arc::clone(events));
+        Self {
+            store: Arc::new(store),
+            mid_store: config.mid_price_candles().then(|| Arc::new(series())),
+            book: Arc::new(OrderBook::default()),
+            open_interest: config
+                .perpetual()
+                .then(|| Arc::new(OpenInterest::new(&config.symbol, series()))),
+            config,
+        }
+    }
 }
 
 pub struct TradingEngine {
-    pub stores: HashMap<String, Arc<CandleStore>>,
-    pub configs: HashMap<String, TradingPairConfig>,
+    /// Pairs by symbol. Pairs can be added while the engine runs, e.g. by discovery.
+    pairs: RwLock<HashMap<String, Arc<Pair>>>,
+    chain: ChainConfig,
+    pub dead_letters: Arc<DeadLetterStore>,
+    pub indexer_status: IndexerStatus,
+    pub pair_controls: PairControls,
+    /// Pauses, restarts and interruptions of each pair's indexing.
+    pub timeline: Timeline,
+    /// Copies received events to disk when recording is on.
+    pub recorder: Option<EventRecorder>,
+    /// Bar updates and closes of every pair's trade candles.
+    pub candle_events: Arc<CandleEvents>,
+    pair_state: PairStateFile,
+    /// Pair state as last written, so unchanged state is not rewritten.
+    saved_pair_state: Mutex<HashMap<String, PairState>>,
 }
 
 impl TradingEngine {
-    pub fn new(configs: Vec<TradingPairConfig>) -> Self {
-        let stores = configs
-            .iter()
-            .map(|pair| (pair.symbol.clone(), Arc::new(CandleStore::new())))
-            .collect();
-        let configs = configs
+    pub fn new(
+        configs: Vec<TradingPairConfig>,
+        chain: ChainConfig,
+        dead_letters: DeadLetterStore,
+    ) -> Self {
+        let candle_events = Arc::new(CandleEvents::default());
+        let pairs = configs
             .into_iter()
-            .map(|pair| (pair.symbol.clone(), pair))
+            .map(|config| {
+                let pair = Pair::new(config, &chain, &candle_events);
+                (pair.config.symbol.clone(), Arc::new(pair))
+            })
             .collect();
-        Self { stores, configs }
+        Self {
+            pairs: RwLock::new(pairs),
+            chain,
+            dead_letters: Arc::new(dead_letters),
+            indexer_status: IndexerStatus::default(),
+            pair_controls: PairControls::default(),
+            timeline: Timeline::default(),
+            recorder: None,
+            candle_events,
+            pair_state: PairStateFile::default(),
+            saved_pair_state: Mutex::new(HashMap::new()),
+        }
     }
 
-    pub fn load_config(path: &str) -> Result<Vec<TradingPairConfig>, Error> {
+    pub fn with_recorder(mut self, recorder: Option<EventRecorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    /// Persists per-pair state such as the first trade and the last processed block to
+    /// `file`, seeding the stores from what it already holds.
+    pub fn with_pair_state(mut self, file: PairStateFile) -> Result<Self, Error> {
+        let state = file.load()?;
+        for pair in self.pairs() {
+            if let Some(state) = state.get(&pair.config.symbol) {
+                restore(&pair.store, state);
+            }
+        }
+        self.pair_state = file;
+        *self.saved_pair_state.lock().unwrap() = state;
+        Ok(self)
+    }
+
+    /// Writes the pair state file if any pair's state changed since the last save. The
+    /// state of pairs not added yet, such as markets discovery has still to find after a
+    /// restart, is kept.
+    pub fn save_pair_state(&self) -> Result<(), Error> {
+        let current: HashMap<_, _> = self
+            .pairs()
+            .iter()
+            .map(|pair| {
+                let store = &pair.store;
+                let state = PairState {
+                    first_trade: store.first_trade(),
+                    last_processed_block: Some(store.checkpoint_block()).filter(|&block| block > 0),
+                };
+                (pair.config.symbol.clone(), state)
+            })
+            .collect();
+
+        let mut saved = self.saved_pair_state.lock().unwrap();
+        let mut state = saved.clone();
+        state.extend(current);
+        if *saved == state {
+            return Ok(());
+        }
+        self.pair_state.save(&state)?;
+        *saved = state;
+        Ok(())
+    }
+
+    /// The pairs of the config file at `path` and the settings of the chain named by
+    /// `CHAIN`.
+    pub fn load_config(path: &str) -> Result<(Vec<TradingPairConfig>, ChainConfig), Error> {
         let config_data = fs::read_to_string(path)?;
-        let config: Vec<TradingPairConfig> = serde_json::from_str(&config_data)?;
-        Ok(config)
+        let file = match serde_json::from_str(&config_data)? {
+            serde_json::Value::Array(_) => ConfigFile {
+                chains: HashMap::new(),
+                pairs: serde_json::from_str(&config_data)?,
+            },
+            object => serde_json::from_value(object)?,
+        };
+        for pair in &file.pairs {
+            pair.interval_levels()?;
+        }
+        let chain = ev("CHAIN")
+            .ok()
+            .and_then(|name| file.chains.get(&name).copied())
+            .unwrap_or_default();
+        Ok((file.pairs, chain))
+    }
+
+    /// Adds a pair while the engine runs, restoring its persisted state. Returns `None`
+    /// when the symbol is taken.
+    pub fn add_pair(&self, config: TradingPairConfig) -> Result<Option<Arc<Pair>>, Error> {
+        config.interval_levels()?;
+        let mut pairs = self.pairs.write().unwrap();
+        if pairs.contains_key(&config.symbol) {
+            return Ok(None);
+        }
+        let pair = Arc::new(Pair::new(config, &self.chain, &self.candle_events));
+        if let Some(state) = self
+            .saved_pair_state
+            .lock()
+            .unwrap()
+            .get(&pair.config.symbol)
+        {
+            restore(&pair.store, state);
+        }
+        pairs.insert(pair.config.symbol.clone(), Arc::clone(&pair));
+        Ok(Some(pair))
+    }
+
+    pub fn pair(&self, symbol: &str) -> Option<Arc<Pair>> {
+        self.pairs.read().unwrap().get(symbol).cloned()
+    }
+
+    /// Every pair, by symbol.
+    pub fn pairs(&self) -> Vec<Arc<Pair>> {
+        let mut pairs: Vec<_> = self.pairs.read().unwrap().values().cloned().collect();
+        pairs.sort_by(|a, b| a.config.symbol.cmp(&b.config.symbol));
+        pairs
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.pairs()
+            .iter()
+            .map(|pair| pair.config.symbol.clone())
+            .collect()
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.pairs.read().unwrap().contains_key(symbol)
+    }
+
+    pub fn config(&self, symbol: &str) -> Option<TradingPairConfig> {
+        self.pair(symbol).map(|pair| pair.config.clone())
     }
 
     pub fn get_store(&self, symbol: &str) -> Option<Arc<CandleStore>> {
-        self.stores.get(symbol).cloned()
+        self.pair(symbol).map(|pair| Arc::clone(&pair.store))
+    }
+
+    pub fn mid_store(&self, symbol: &str) -> Option<Arc<CandleStore>> {
+        self.pair(symbol)?.mid_store.clone()
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<Arc<OrderBook>> {
+        self.pair(symbol).map(|pair| Arc::clone(&pair.book))
+    }
+
+    pub fn open_interest(&self, symbol: &str) -> Option<Arc<OpenInterest>> {
+        self.pair(symbol)?.open_interest.clone()
+    }
+
+    pub fn activity(&self, symbol: &str) -> Option<MarketActivity> {
+        let pair = self.pair(symbol)?;
+        let config = &pair.config;
+        let last_trade_at = pair.store.last_trade_at().map(|at| at / 1000);
+        let inactive_since = chrono::Utc::now().timestamp() - config.inactive_after_secs() as i64;
+        Some(MarketActivity {
+            last_trade_at,
+            inactive: last_trade_at.is_none_or(|at| at < inactive_since),
+        })
     }
 
     pub fn get_symbols(&self) -> Vec<serde_json::Value> {
-        self.configs
-            .values()
-            .map(|config| {
-                json!({
+        self.pairs()
+            .iter()
+            .map(|pair| {
+                let config = &pair.config;
+                let activity = self.activity(&config.symbol);
+                let mut symbol = json!({
                     "symbol": config.symbol,
                     "ticker": config.symbol,
                     "name": config.description,
                     "description": config.description,
-                    "type_": "crypto",
-                    "exchange": "CryptoExchange",
+                    "type_": config.symbol_type(),
+                    "exchange": config.exchange(),
                     "timezone": "Etc/UTC",
                     "minmov": 1,
                     "pricescale": 100,
                     "session": "24x7",
-                    "has_intraday": true,
-                    "has_daily": true,
-                    "supported_resolutions": ["1", "5", "15", "30", "60", "D", "W", "M"],
-                    "intraday_multipliers": ["1", "5", "15", "30", "60"],
-                    "format": "price"
-                })
+                    "format": "price",
+                    "inactive": activity.map(|a| a.inactive),
+                    "last_trade_at": activity.and_then(|a| a.last_trade_at),
+                });
+                symbol
+                    .as_object_mut()
+                    .unwrap()
+                    .extend(config.resolution_info());
+                symbol
             })
             .collect()
     }
 
+    /// Resolution codes stored for at least one pair, shortest first.
+    pub fn supported_resolutions(&self) -> Vec<String> {
+        let mut intervals: Vec<u64> = self
+            .pairs()
+            .iter()
+            .flat_map(|pair| pair.config.intervals())
+            .collect();
+        intervals.sort_unstable();
+        intervals.dedup();
+        intervals.into_iter().filter_map(resolution_code).collect()
+    }
+
     pub fn get_symbols_meta(&self) -> serde_json::Value {
         let metadata: Vec<_> = self
-            .configs
-            .values()
-            .map(|config| {
+            .pairs()
+            .iter()
+            .map(|pair| {
+                let config = &pair.config;
+                let first_trade = pair.store.first_trade();
                 json!({
                     "symbol": config.symbol,
-                    "contract_id": config.contract_id,
+                    "contract_id": config.live_contract().contract_id,
+                    "contracts": config.contracts(),
                     "start_block": config.start_block,
                     "description": config.description,
+                    "source": config.source(),
+                    "finality_depth": pair.store.finality_depth(),
+                    "mid_price_candles": config.mid_price_candles(),
+                    "perpetual": config.perpetual(),
+                    "wash_trades": config.wash_trades,
+                    "base_asset": config.base_asset,
+                    "quote_asset": config.quote_asset,
+                    "price_decimals": config.price_decimals(),
+                    "size_decimals": config.size_decimals(),
+                    "price_filter": config.price_filter,
+                    "intervals": config.intervals(),
+                    "first_trade_block": first_trade.map(|first| first.block),
+                    "first_trade_at": first_trade.map(|first| first.event_time / 1000),
+                    "listing_date": first_trade.and_then(|first| first.listing_date()),
                 })
             })
             .collect();
         json!({ "symbols_meta": metadata })
     }
 }
+
+/// Seeds a pair's store from its persisted state.
+fn restore(store: &CandleStore, state: &PairState) {
+    if let Some(first_trade) = state.first_trade {
+        store.restore_first_trade(first_trade);
+    }
+    if let Some(block) = state.last_processed_block {
+        store.mark_block(block);
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage::candles::{Candle, CandleStore};
+use crate::storage::trades::Trade;
+
+/// Symbol fixture trades are recorded under.
+pub const SYMBOL: &str = "FIX-USD";
+/// 2024-01-01T00:00:00Z, a Monday, where fixture trades start.
+pub const START: i64 = 1_704_067_200;
+
+/// The parts of a candle a golden output pins down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldenCandle {
+    pub timestamp: i64,
+    pub open: u128,
+    pub high: u128,
+    pub low: u128,
+    pub close: u128,
+    pub volume: u128,
+    pub trade_count: u64,
+}
+
+impl From<&Candle> for GoldenCandle {
+    fn from(candle: &Candle) -> Self {
+        Self {
+            timestamp: candle.timestamp.timestamp(),
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+            trade_count: candle.trade_count,
+        }
+    }
+}
+
+fn trade(offset_secs: i64, price: u128, volume: u128) -> Trade {
+    Trade {
+        price,
+        volume,
+        event_time: (START + offset_secs) * 1000,
+        wash: false,
+    }
+}
+
+fn golden(timestamp: i64, ohlc: [u128; 4], volume: u128, trade_count: u64) -> GoldenCandle {
+    let [open, high, low, close] = ohlc;
+    GoldenCandle {
+        timestamp: START + timestamp,
+        open,
+        high,
+        low,
+        close,
+        volume,
+        trade_count,
+    }
+}
+
+/// Seven trades over twelve minutes, with quiet minutes in between so gap filling shows.
+pub fn golden_trades() -> Vec<Trade> {
+    vec![
+        trade(5, 100, 2),
+        trade(30, 110, 1),
+        trade(59, 95, 3),
+        trade(65, 105, 1),
+        trade(250, 120, 4),
+        trade(301, 115, 2),
+        trade(719, 90, 5),
+    ]
+}
+
+/// What a gap-filling store holds for `golden_trades` at `interval`; `None` for
+/// intervals without a golden output.
+pub fn golden_candles(interval: u64) -> Option<Vec<GoldenCandle>> {
+    let flat = |timestamp, price| golden(timestamp, [price; 4], 0, 0);
+    Some(match interval {
+        60 => vec![
+            golden(0, [100, 110, 95, 95], 6, 3),
+            golden(60, [105, 105, 105, 105], 1, 1),
+            flat(120, 105),
+            flat(180, 105),
+            golden(240, [120, 120, 120, 120], 4, 1),
+            golden(300, [115, 115, 115, 115], 2, 1),
+            flat(360, 115),
+            flat(420, 115),
+            flat(480, 115),
+            flat(540, 115),
+            flat(600, 115),
+            golden(660, [90, 90, 90, 90], 5, 1),
+        ],
+        300 => vec![
+            golden(0, [100, 120, 95, 120], 11, 5),
+            golden(300, [115, 115, 115, 115], 2, 1),
+            golden(600, [90, 90, 90, 90], 5, 1),
+        ],
+        3600 => vec![golden(0, [100, 120, 90, 90], 18, 7)],
+        _ => return None,
+    })
+}
+
+/// `count` trades within `span_secs` of `START`, in time order, the same for every run
+/// with the same `seed`.
+pub fn random_trades(seed: u64, count: usize, span_secs: i64) -> Vec<Trade> {
+    // xorshift64*, so the corpus never changes with a dependency's generator.
+    let mut state = seed.max(1);
+    let mut next = move || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    };
+    let mut trades: Vec<_> = (0..count)
+        .map(|_| {
+            let offset = (next() % span_secs.max(1) as u64) as i64;
+            trade(
+                offset,
+                900 + (next() % 200) as u128,
+                1 + (next() % 100) as u128,
+            )
+        })
+        .collect();
+    trades.sort_by_key(|trade| trade.event_time);
+    trades
+}
+
+/// A store fed `trades` under `SYMBOL`.
+pub fn store_with(store: CandleStore, trades: &[Trade]) -> CandleStore {
+    for trade in trades {
+        store.add_trade(SYMBOL, trade.price, trade.volume, trade.event_time);
+    }
+    store
+}
+
+/// Every candle of `SYMBOL` at `interval`, oldest first.
+pub fn read_all(store: &CandleStore, interval: u64) -> Vec<Candle> {
+    store.get_candles_in_time_range(SYMBOL, interval, i64::MIN, i64::MAX)
+}
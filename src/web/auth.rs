@@ -0,0 +1,44 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use sha2::{Digest, Sha256};
+
+use crate::config::env::ev;
+use crate::web::tenant::{resolve_tenant, tenant_name};
+
+/// Whether `key` equals `expected`, compared through their SHA-256 digests in constant
+/// time so the time taken does not reveal how much of a guess was right.
+pub fn keys_match(key: &str, expected: &str) -> bool {
+    let (key, expected) = (Sha256::digest(key), Sha256::digest(expected));
+    let diff = key
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+    std::hint::black_box(diff) == 0
+}
+
+/// Request guard for `/admin` routes: the `X-Admin-Key` header must match `ADMIN_API_KEY`,
+/// or the tenant's `admin_key` under `/t/<tenant>/admin`. Admin routes are disabled
+/// entirely while the relevant key is unset.
+pub struct AdminKey;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminKey {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected = if tenant_name(req).is_some() {
+            resolve_tenant(req).and_then(|tenant| tenant.admin_key.clone())
+        } else {
+            ev("ADMIN_API_KEY").ok()
+        };
+        let expected = match expected {
+            Some(key) if !key.is_empty() => key,
+            _ => return Outcome::Error((Status::Forbidden, ())),
+        };
+
+        match req.headers().get_one("X-Admin-Key") {
+            Some(key) if keys_match(key, &expected) => Outcome::Success(AdminKey),
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use serde_json::{json, Value};
+
+use crate::storage::candles::Candle;
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::format::Formatter;
+
+/// Binance interval units in seconds. `M` is the stored 30-day month rather than a
+/// calendar month.
+const UNITS: [(char, u64); 6] = [
+    ('s', 1),
+    ('m', 60),
+    ('h', 3600),
+    ('d', 86400),
+    ('w', 604800),
+    ('M', 2592000),
+];
+
+/// Candle interval in seconds for a Binance interval string such as `1m`, `4h` or `1d`.
+pub fn interval_seconds(interval: &str) -> Option<u64> {
+    let unit = interval.chars().last()?;
+    let count: u64 = interval[..interval.len() - unit.len_utf8()].parse().ok()?;
+    let (_, seconds) = UNITS.iter().find(|(code, _)| *code == unit)?;
+    count.checked_mul(*seconds).filter(|&seconds| seconds > 0)
+}
+
+/// The pair Binance-style clients mean by `symbol`, which they tend to upper-case.
+pub fn resolve_symbol(trading_engine: &TradingEngine, symbol: &str) -> Option<String> {
+    if trading_engine.contains(symbol) {
+        return Some(symbol.to_string());
+    }
+    trading_engine
+        .symbols()
+        .into_iter()
+        .find(|known| known.eq_ignore_ascii_case(symbol))
+}
+
+/// Open and close time of a bar in milliseconds, Binance style: the close is the last
+/// millisecond of the period.
+pub fn bar_times(candle: &Candle, interval: u64) -> (i64, i64) {
+    let open = candle.timestamp.timestamp_millis();
+    (open, open + interval as i64 * 1000 - 1)
+}
+
+/// A bar as a `/api/v3/klines` row. Trades carry no aggressor side, so the taker-buy
+/// volumes are always zero.
+pub fn kline_row(candle: &Candle, interval: u64, formatter: &Formatter) -> Value {
+    let (open_time, close_time) = bar_times(candle, interval);
+    json!([
+        open_time,
+        formatter.price(candle.open).to_string(),
+        formatter.price(candle.high).to_string(),
+        formatter.price(candle.low).to_string(),
+        formatter.price(candle.close).to_string(),
+        formatter.size(candle.volume).to_string(),
+        close_time,
+        formatter.quote(candle.quote_volume).to_string(),
+        candle.trade_count,
+        "0",
+        "0",
+        "0",
+    ])
+}
+
+/// A `kline` stream event for a bar; `closed` is Binance's `x`. Trade ids are not
+/// tracked, so `f` and `L` are always -1.
+pub fn kline_event(
+    symbol: &str,
+    interval: &str,
+    seconds: u64,
+    candle: &Candle,
+    closed: bool,
+    formatter: &Formatter,
+) -> Value {
+    let (open_time, close_time) = bar_times(candle, seconds);
+    json!({
+        "e": "kline",
+        "E": chrono::Utc::now().timestamp_millis(),
+        "s": symbol,
+        "k": {
+            "t": open_time,
+            "T": close_time,
+            "s": symbol,
+            "i": interval,
+            "f": -1,
+            "L": -1,
+            "o": formatter.price(candle.open).to_string(),
+            "c": formatter.price(candle.close).to_string(),
+            "h": formatter.price(candle.high).to_string(),
+            "l": formatter.price(candle.low).to_string(),
+            "v": formatter.size(candle.volume).to_string(),
+            "n": candle.trade_count,
+            "x": closed,
+            "q": formatter.quote(candle.quote_volume).to_string(),
+            "V": "0",
+            "Q": "0",
+            "B": "0",
+        },
+    })
+}
+
+/// Splits a stream name such as `ethusdc@kline_1m` into symbol and interval.
+pub fn kline_stream(name: &str) -> Option<(&str, &str)> {
+    let (symbol, stream) = name.split_once('@')?;
+    Some((symbol, stream.strip_prefix("kline_")?))
+}
+
+/// A Binance error body with its code, sent as `400 Bad Request` like Binance does.
+pub fn error(code: i32, msg: &str) -> (Status, Json<Value>) {
+    (
+        Status::BadRequest,
+        Json(json!({ "code": code, "msg": msg })),
+    )
+}
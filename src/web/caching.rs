@@ -0,0 +1,218 @@
+use chrono::{DateTime, Utc};
+use rocket::http::{Header, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::response::OpenApiResponderInner;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use crate::storage::candles::{Candle, CandleStore};
+use crate::storage::interval::{period_end, period_start};
+use crate::web::tenant::tenant_name;
+
+/// The state of the bars a response was built from: its newest bar and the newest store
+/// revision among its bars, which changes when any of them is corrected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataVersion {
+    pub last_bar: DateTime<Utc>,
+    pub revision: u64,
+    /// When the data last changed, as far as clients can tell: the end of the newest
+    /// bar, or now while it is forming.
+    pub modified: DateTime<Utc>,
+}
+
+impl DataVersion {
+    pub fn of(candles: &[Candle], interval: u64) -> Option<Self> {
+        let last = candles.last()?;
+        Some(Self {
+            last_bar: last.timestamp,
+            revision: candles.iter().map(|c| c.revision).max().unwrap_or_default(),
+            modified: period_end(last.timestamp, interval).min(Utc::now()),
+        })
+    }
+}
+
+/// How long a closed range of bars may be cached, by interval: a minute's bars for an
+/// hour, longer bars for longer, up to a week.
+fn max_age(interval: u64) -> u64 {
+    interval.saturating_mul(60).clamp(3600, 604800)
+}
+
+/// `Cache-Control` for bars at `interval` up to `to`. Ranges ending before the current
+/// bar only change on corrections and may sit in shared caches; ranges reaching the
+/// live bar, as without `to`, must be revalidated on every use. Tenant responses are
+/// kept out of shared caches when sent, see [`Validated`].
+pub fn cache_control(interval: u64, to: Option<i64>, now: DateTime<Utc>) -> String {
+    let closed = to
+        .and_then(|to| DateTime::from_timestamp(to, 0))
+        .is_some_and(|to| period_end(period_start(to, interval), interval) <= now);
+    if closed {
+        format!("public, max-age={}, immutable", max_age(interval))
+    } else {
+        "no-cache".to_string()
+    }
+}
+
+/// An HTTP date such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// A response with an `ETag` and `Last-Modified`, answered with `304 Not Modified` when
+/// the client's `If-None-Match` or, without one, `If-Modified-Since` shows it already
+/// has the data. Under `/t/<tenant>`, where the data is only served for the tenant's
+/// key, a `public` policy is sent as `private` and the response varies on `X-API-Key`.
+pub struct Validated<R> {
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+    cache_control: Option<String>,
+    inner: R,
+}
+
+impl<R> Validated<R> {
+    /// Tags `inner`, built for the request parameters `key` from data at `version`.
+    pub fn new(key: &impl Debug, version: Option<DataVersion>, inner: R) -> Self {
+        let digest = Sha256::digest(format!("{:?}|{:?}", key, version).as_bytes());
+        Self {
+            etag: format!("\"{}\"", hex::encode(&digest[..16])),
+            last_modified: version.map(|version| version.modified),
+            cache_control: None,
+            inner,
+        }
+    }
+
+    pub fn with_cache_control(mut self, cache_control: String) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+
+    fn is_fresh(&self, req: &Request<'_>) -> bool {
+        let headers = req.headers();
+        if let Some(tags) = headers.get_one("If-None-Match") {
+            return tags
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == self.etag);
+        }
+        let since = headers
+            .get_one("If-Modified-Since")
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok());
+        match (since, self.last_modified) {
+            (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+            _ => false,
+        }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Validated<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = if self.is_fresh(req) {
+            Response::build().status(Status::NotModified).finalize()
+        } else {
+            self.inner.respond_to(req)?
+        };
+        response.set_header(Header::new("ETag", self.etag));
+        if let Some(modified) = self.last_modified {
+            response.set_header(Header::new("Last-Modified", http_date(modified)));
+        }
+        let tenant = tenant_name(req).is_some();
+        if let Some(cache_control) = self.cache_control {
+            let cache_control = match cache_control.strip_prefix("public") {
+                Some(rest) if tenant => format!("private{}", rest),
+                _ => cache_control,
+            };
+            response.set_header(Header::new("Cache-Control", cache_control));
+        }
+        if tenant {
+            response.adjoin_header(Header::new("Vary", "X-API-Key"));
+        }
+        Ok(response)
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for Validated<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        R::responses(gen)
+    }
+}
+
+/// Responses kept by `HistoryCache` at most.
+const HISTORY_CACHE_CAPACITY: usize = 1024;
+/// How long a cached response is served without store changes. Responses also depend on
+/// the clock, e.g. for when the forming bar closes, so none is kept for long.
+const HISTORY_CACHE_TTL: Duration = Duration::from_secs(10);
+
+struct CachedHistory<V> {
+    /// The store the response was read from, telling tenants' pairs apart.
+    store: Weak<CandleStore>,
+    revision: u64,
+    cached_at: Instant,
+    value: V,
+}
+
+impl<V> CachedHistory<V> {
+    fn is_current(&self, store: &Arc<CandleStore>) -> bool {
+        self.store.as_ptr() == Arc::as_ptr(store)
+            && self.revision == store.revision()
+            && self.cached_at.elapsed() < HISTORY_CACHE_TTL
+    }
+}
+
+/// Recent `/history` responses by request, to absorb bursts of identical chart loads.
+/// An entry is dropped as soon as its store changes, i.e. on the next trade of the pair.
+pub struct HistoryCache<V> {
+    entries: Mutex<HashMap<String, CachedHistory<V>>>,
+}
+
+impl<V> Default for HistoryCache<V> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<V: Clone> HistoryCache<V> {
+    pub fn get(&self, key: &str, store: &Arc<CandleStore>) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.is_current(store) => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches `value`, read from `store` at `revision`, which should be taken before
+    /// reading so a change during the read invalidates the entry.
+    pub fn insert(&self, key: String, store: &Arc<CandleStore>, revision: u64, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= HISTORY_CACHE_CAPACITY {
+            entries.retain(|_, entry| {
+                entry
+                    .store
+                    .upgrade()
+                    .is_some_and(|store| entry.is_current(&store))
+            });
+            if entries.len() >= HISTORY_CACHE_CAPACITY {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            key,
+            CachedHistory {
+                store: Arc::downgrade(store),
+                revision,
+                cached_at: Instant::now(),
+                value,
+            },
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use plotters::prelude::*;
+
+use crate::error::Error;
+use crate::storage::candles::Candle;
+
+const BACKGROUND: RGBColor = RGBColor(19, 23, 34);
+const RISING: RGBColor = RGBColor(38, 166, 154);
+const FALLING: RGBColor = RGBColor(239, 83, 80);
+
+/// Share of the image height given to the volume pane under the candles.
+const VOLUME_PANE: f64 = 0.2;
+const PADDING: i32 = 8;
+
+/// Renders `candles` (oldest first) as a PNG candle chart with a volume pane. The
+/// image carries no text, so it needs no fonts on the host.
+pub fn render_png(candles: &[Candle], width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let mut pixels = vec![0u8; width as usize * height as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut pixels, (width, height)).into_drawing_area();
+        root.fill(&BACKGROUND).map_err(render_error)?;
+        draw_candles(&root, candles, width as i32, height as i32)?;
+        root.present().map_err(render_error)?;
+    }
+    encode_png(&pixels, width, height)
+}
+
+fn draw_candles<DB: DrawingBackend>(
+    area: &DrawingArea<DB, plotters::coord::Shift>,
+    candles: &[Candle],
+    width: i32,
+    height: i32,
+) -> Result<(), Error> {
+    let (Some(high), Some(low)) = (
+        candles.iter().map(|c| c.high).max(),
+        candles.iter().map(|c| c.low).min(),
+    ) else {
+        return Ok(());
+    };
+    let max_volume = candles.iter().map(|c| c.volume).max().unwrap_or(0).max(1) as f64;
+
+    let volume_height = (height as f64 * VOLUME_PANE) as i32;
+    let price_top = PADDING;
+    let price_bottom = height - volume_height - PADDING;
+    let span = (high - low).max(1) as f64;
+    let y = |price: u128| {
+        price_bottom - ((price - low) as f64 / span * (price_bottom - price_top) as f64) as i32
+    };
+
+    let slot = (width - 2 * PADDING) as f64 / candles.len() as f64;
+    let body_width = ((slot * 0.7) as i32).max(1);
+    for (i, candle) in candles.iter().enumerate() {
+        let center = PADDING + (slot * (i as f64 + 0.5)) as i32;
+        let left = center - body_width / 2;
+        let color = if candle.close >= candle.open {
+            RISING
+        } else {
+            FALLING
+        };
+
+        area.draw(&PathElement::new(
+            vec![(center, y(candle.high)), (center, y(candle.low))],
+            color,
+        ))
+        .map_err(render_error)?;
+        let (top, bottom) = (
+            y(candle.open.max(candle.close)),
+            y(candle.open.min(candle.close)),
+        );
+        area.draw(&Rectangle::new(
+            [(left, top), (left + body_width, bottom.max(top + 1))],
+            color.filled(),
+        ))
+        .map_err(render_error)?;
+
+        let bar = (candle.volume as f64 / max_volume * (volume_height - PADDING) as f64) as i32;
+        area.draw(&Rectangle::new(
+            [(left, height - bar), (left + body_width, height)],
+            color.mix(0.5).filled(),
+        ))
+        .map_err(render_error)?;
+    }
+    Ok(())
+}
+
+fn encode_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(render_error)?;
+    writer.write_image_data(pixels).map_err(render_error)?;
+    writer.finish().map_err(render_error)?;
+    Ok(out)
+}
+
+fn render_error(e: impl std::fmt::Display) -> Error {
+    Error::ChartRenderError(e.to_string())
+}
@@ -0,0 +1,97 @@
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Request, Response};
+use tokio::io::BufReader;
+
+/// Routes whose bodies are compressed: long OHLCV series and exports.
+const COMPRESSED_PATHS: [&str; 4] = ["/history", "/history_batch", "/history_multi", "/candles"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// The encoding to answer an `Accept-Encoding` header with: brotli over gzip when both
+/// are accepted, neither when the client gives them `q=0`.
+fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let accepted = |name: &str| {
+        accept_encoding.split(',').any(|entry| {
+            let mut params = entry.split(';').map(str::trim);
+            let coding = params.next().unwrap_or_default();
+            let refused = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q <= 0.0)
+            });
+            (coding.eq_ignore_ascii_case(name) || coding == "*") && !refused
+        })
+    };
+    [Encoding::Brotli, Encoding::Gzip]
+        .into_iter()
+        .find(|encoding| accepted(encoding.name()))
+}
+
+/// Whether the request was routed to a route with compressible bodies, wherever its
+/// group is mounted.
+fn is_compressed(req: &Request<'_>) -> bool {
+    let Some(route) = req.route() else {
+        return false;
+    };
+    let path = route.uri.unmounted_origin.path();
+    COMPRESSED_PATHS.contains(&path.as_str()) || path.starts_with("/export/")
+}
+
+/// Compresses history, candle and export responses with gzip or brotli as the client's
+/// `Accept-Encoding` allows, streaming the encoder over the original body.
+pub struct Compression;
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Compress candle responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if !is_compressed(req) || !res.status().class().is_success() {
+            return;
+        }
+        res.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+        if res.headers().contains("Content-Encoding") {
+            return;
+        }
+        let Some(encoding) = req.headers().get_one("Accept-Encoding").and_then(negotiate) else {
+            return;
+        };
+
+        let body = BufReader::new(res.body_mut().take());
+        match encoding {
+            Encoding::Brotli => res.set_streamed_body(BrotliEncoder::new(body)),
+            Encoding::Gzip => res.set_streamed_body(GzipEncoder::new(body)),
+        }
+        res.set_header(Header::new("Content-Encoding", encoding.name()));
+        // The encoded bytes differ from the identity ones, so the tag only holds weakly.
+        if let Some(etag) = res
+            .headers()
+            .get_one("ETag")
+            .filter(|etag| !etag.starts_with("W/"))
+        {
+            let weak = format!("W/{}", etag);
+            res.set_header(Header::new("ETag", weak));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::web::tenant::tenant_name;
+
+/// A legacy endpoint kept alive while clients migrate, matched by its path as mounted
+/// (e.g. `/candles`), both at the root and under `/t/<tenant>/`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeprecatedRoute {
+    pub path: String,
+    /// HTTP date sent as the `Sunset` header.
+    #[serde(default)]
+    pub sunset: Option<String>,
+    /// Replacement endpoint, sent as a `successor-version` link.
+    #[serde(default)]
+    pub successor: Option<String>,
+}
+
+pub fn default_deprecated_routes() -> Vec<DeprecatedRoute> {
+    vec![
+        DeprecatedRoute {
+            path: "/candles".to_string(),
+            sunset: None,
+            successor: Some("/history".to_string()),
+        },
+        DeprecatedRoute {
+            path: "/symbols_meta".to_string(),
+            sunset: None,
+            successor: Some("/symbols".to_string()),
+        },
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Caller {
+    route: String,
+    tenant: Option<String>,
+    user_agent: Option<String>,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeprecatedUsage {
+    pub route: String,
+    pub tenant: Option<String>,
+    pub user_agent: Option<String>,
+    /// Only a prefix of the key is kept.
+    pub api_key: Option<String>,
+    pub count: u64,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Calls to deprecated routes, per route and caller, since startup.
+#[derive(Debug, Default)]
+pub struct DeprecationUsage {
+    callers: Mutex<HashMap<Caller, (u64, DateTime<Utc>)>>,
+}
+
+impl DeprecationUsage {
+    fn record(&self, caller: Caller) {
+        let mut callers = self.callers.lock().unwrap();
+        let entry = callers.entry(caller).or_insert((0, Utc::now()));
+        entry.0 += 1;
+        entry.1 = Utc::now();
+    }
+
+    /// Usage entries, most frequent first.
+    pub fn snapshot(&self) -> Vec<DeprecatedUsage> {
+        let callers = self.callers.lock().unwrap();
+        let mut usage: Vec<_> = callers
+            .iter()
+            .map(|(caller, (count, last_seen))| DeprecatedUsage {
+                route: caller.route.clone(),
+                tenant: caller.tenant.clone(),
+                user_agent: caller.user_agent.clone(),
+                api_key: caller.api_key.clone(),
+                count: *count,
+                last_seen: *last_seen,
+            })
+            .collect();
+        usage.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.route.cmp(&b.route)));
+        usage
+    }
+
+    /// Total calls per route.
+    pub fn totals(&self) -> Vec<(String, u64)> {
+        let callers = self.callers.lock().unwrap();
+        let mut totals: HashMap<&str, u64> = HashMap::new();
+        for (caller, (count, _)) in callers.iter() {
+            *totals.entry(caller.route.as_str()).or_default() += count;
+        }
+        let mut totals: Vec<_> = totals
+            .into_iter()
+            .map(|(route, count)| (route.to_string(), count))
+            .collect();
+        totals.sort();
+        totals
+    }
+}
+
+/// Marks responses from deprecated routes with `Deprecation`, `Sunset` and `Link`
+/// headers and records who is still calling them.
+pub struct Deprecation {
+    pub routes: Vec<DeprecatedRoute>,
+    pub usage: Arc<DeprecationUsage>,
+}
+
+#[rocket::async_trait]
+impl Fairing for Deprecation {
+    fn info(&self) -> Info {
+        Info {
+            name: "Flag and count calls to deprecated routes",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(route) = req.route() else {
+            return;
+        };
+        let path = route.uri.unmounted_origin.path();
+        let Some(deprecated) = self.routes.iter().find(|r| r.path == path.as_str()) else {
+            return;
+        };
+
+        res.set_header(Header::new("Deprecation", "true"));
+        if let Some(sunset) = &deprecated.sunset {
+            res.set_header(Header::new("Sunset", sunset.clone()));
+        }
+        if let Some(successor) = &deprecated.successor {
+            res.set_header(Header::new(
+                "Link",
+                format!("<{}>; rel=\"successor-version\"", successor),
+            ));
+        }
+
+        let headers = req.headers();
+        self.usage.record(Caller {
+            route: deprecated.path.clone(),
+            tenant: tenant_name(req).map(str::to_string),
+            user_agent: headers.get_one("User-Agent").map(str::to_string),
+            api_key: headers
+                .get_one("X-API-Key")
+                .map(|key| format!("{}…", key.chars().take(4).collect::<String>())),
+        });
+    }
+}
@@ -0,0 +1,278 @@
+use futures_util::stream::{self, Stream, StreamExt};
+use log::error;
+use parquet::data_type::{DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use rocket::http::{ContentType, Header};
+use rocket::request::Request;
+use rocket::response::stream::ReaderStream;
+use rocket::response::{self, Responder, Response};
+use serde_json::json;
+use std::io::{self, Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::error::Error;
+use crate::storage::candles::{Candle, CandleStore};
+use crate::web::format::Formatter;
+
+/// Candles read from the store per page while exporting.
+const PAGE_SIZE: usize = 1000;
+
+/// The candles of one series in `[from, to]`, read a page at a time so an export of
+/// any length holds one page in memory.
+pub struct CandlePages {
+    store: Arc<CandleStore>,
+    symbol: String,
+    interval: u64,
+    /// Start of the next page; `None` once the last page was read.
+    from: Option<i64>,
+    to: i64,
+}
+
+impl CandlePages {
+    pub fn new(store: Arc<CandleStore>, symbol: &str, interval: u64, from: i64, to: i64) -> Self {
+        Self {
+            store,
+            symbol: symbol.to_string(),
+            interval,
+            from: Some(from),
+            to,
+        }
+    }
+
+    /// The next page, oldest first; empty once the range is exhausted.
+    pub fn next_page(&mut self) -> Vec<Candle> {
+        let Some(from) = self.from.filter(|&from| from <= self.to) else {
+            return vec![];
+        };
+        let page =
+            self.store
+                .get_candles_from(&self.symbol, self.interval, from, self.to, PAGE_SIZE);
+        // A short page is the last one.
+        self.from = match page.last() {
+            Some(last) if page.len() == PAGE_SIZE => last.timestamp.timestamp().checked_add(1),
+            _ => None,
+        };
+        page
+    }
+
+    /// The pages rendered by `render`, e.g. into body chunks, one item per page.
+    pub fn into_stream<F, T>(self, mut render: F) -> impl Stream<Item = T> + Send
+    where
+        F: FnMut(&[Candle]) -> T + Send + 'static,
+    {
+        stream::unfold(self, |mut pages| async move {
+            let page = pages.next_page();
+            (!page.is_empty()).then_some((page, pages))
+        })
+        .map(move |page| render(&page))
+    }
+}
+
+/// A CSV row per candle, with prices and volumes in display units.
+pub fn csv_rows(candles: &[Candle], formatter: &Formatter) -> Vec<u8> {
+    let mut rows = String::new();
+    for candle in candles {
+        rows.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            candle.timestamp.timestamp(),
+            formatter.price(candle.open),
+            formatter.price(candle.high),
+            formatter.price(candle.low),
+            formatter.price(candle.close),
+            formatter.size(candle.volume),
+            formatter.quote(candle.quote_volume),
+            candle.trade_count,
+        ));
+    }
+    rows.into_bytes()
+}
+
+pub const CSV_HEADER: &str = "timestamp,open,high,low,close,volume,quote_volume,trade_count\n";
+
+/// A JSON object per candle and line, with prices and volumes in display units.
+pub fn ndjson_rows(candles: &[Candle], formatter: &Formatter) -> Vec<u8> {
+    let mut rows = Vec::new();
+    for candle in candles {
+        let row = json!({
+            "timestamp": candle.timestamp.timestamp(),
+            "open": formatter.price(candle.open),
+            "high": formatter.price(candle.high),
+            "low": formatter.price(candle.low),
+            "close": formatter.price(candle.close),
+            "volume": formatter.size(candle.volume),
+            "quote_volume": formatter.quote(candle.quote_volume),
+            "trade_count": candle.trade_count,
+        });
+        serde_json::to_writer(&mut rows, &row).expect("JSON values serialize");
+        rows.push(b'\n');
+    }
+    rows
+}
+
+/// Columns of a Parquet export; prices and volumes are in display units.
+const PARQUET_SCHEMA: &str = "
+    message candle {
+        REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+        REQUIRED DOUBLE open;
+        REQUIRED DOUBLE high;
+        REQUIRED DOUBLE low;
+        REQUIRED DOUBLE close;
+        REQUIRED DOUBLE volume;
+        REQUIRED DOUBLE quote_volume;
+        REQUIRED INT64 trade_count;
+    }
+";
+
+/// Where the Parquet writer puts its bytes until the response takes them.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes candles into a Parquet file a row group at a time, handing out the bytes of
+/// each row group as it is done.
+pub struct ParquetWriter {
+    writer: SerializedFileWriter<SharedBuffer>,
+    buffer: SharedBuffer,
+}
+
+impl ParquetWriter {
+    pub fn new() -> Result<Self, Error> {
+        let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+        let buffer = SharedBuffer::default();
+        let writer = SerializedFileWriter::new(
+            buffer.clone(),
+            schema,
+            Arc::new(WriterProperties::builder().build()),
+        )?;
+        Ok(Self { writer, buffer })
+    }
+
+    /// Writes `candles` as one row group and returns the file's bytes so far.
+    pub fn write(&mut self, candles: &[Candle], formatter: &Formatter) -> Result<Vec<u8>, Error> {
+        let doubles: [fn(&Candle, &Formatter) -> f64; 6] = [
+            |c, f| f.price(c.open),
+            |c, f| f.price(c.high),
+            |c, f| f.price(c.low),
+            |c, f| f.price(c.close),
+            |c, f| f.size(c.volume),
+            |c, f| f.quote(c.quote_volume),
+        ];
+        let mut row_group = self.writer.next_row_group()?;
+        let mut column = 0;
+        while let Some(mut writer) = row_group.next_column()? {
+            match column {
+                0 => {
+                    let values: Vec<i64> = candles
+                        .iter()
+                        .map(|c| c.timestamp.timestamp_millis())
+                        .collect();
+                    writer
+                        .typed::<Int64Type>()
+                        .write_batch(&values, None, None)?;
+                }
+                7 => {
+                    let values: Vec<i64> = candles.iter().map(|c| c.trade_count as i64).collect();
+                    writer
+                        .typed::<Int64Type>()
+                        .write_batch(&values, None, None)?;
+                }
+                _ => {
+                    let value = doubles[column - 1];
+                    let values: Vec<f64> = candles.iter().map(|c| value(c, formatter)).collect();
+                    writer
+                        .typed::<DoubleType>()
+                        .write_batch(&values, None, None)?;
+                }
+            }
+            writer.close()?;
+            column += 1;
+        }
+        row_group.close()?;
+        Ok(self.buffer.take())
+    }
+
+    /// Writes the footer and returns the file's remaining bytes.
+    pub fn finish(self) -> Result<Vec<u8>, Error> {
+        self.writer.close()?;
+        Ok(self.buffer.take())
+    }
+}
+
+impl CandlePages {
+    /// The pages as a Parquet file with a row group per page. A failure mid-way is
+    /// logged and ends the body early, as the status has been sent by then.
+    pub fn into_parquet(
+        self,
+        formatter: Formatter,
+    ) -> Result<impl Stream<Item = Vec<u8>> + Send, Error> {
+        let writer = ParquetWriter::new()?;
+        Ok(stream::unfold(
+            Some((self, writer)),
+            move |state| async move {
+                let (mut pages, mut writer) = state?;
+                let page = pages.next_page();
+                let written = if page.is_empty() {
+                    writer.finish().map(|bytes| (bytes, None))
+                } else {
+                    writer
+                        .write(&page, &formatter)
+                        .map(|bytes| (bytes, Some((pages, writer))))
+                };
+                written
+                    .map_err(|e| error!("Parquet export failed: {}", e))
+                    .ok()
+            },
+        ))
+    }
+}
+
+/// A streamed download, sent with chunked transfer encoding as its chunks are produced.
+pub struct Export<S> {
+    content_type: ContentType,
+    filename: String,
+    body: S,
+}
+
+impl<S> Export<S> {
+    pub fn new(content_type: ContentType, filename: String, body: S) -> Self {
+        Self {
+            content_type,
+            filename,
+            body,
+        }
+    }
+}
+
+impl<'r, S> Responder<'r, 'static> for Export<S>
+where
+    S: Stream<Item = Vec<u8>> + Send + 'static,
+{
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        Response::build()
+            .header(self.content_type)
+            .header(Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.filename),
+            ))
+            .streamed_body(ReaderStream::from(self.body.map(Cursor::new)))
+            .ok()
+    }
+}
@@ -0,0 +1,118 @@
+use rocket::FromFormField;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+use crate::storage::trading_engine::TradingPairConfig;
+
+/// Decimals assumed for pairs without a config entry.
+const DEFAULT_DECIMALS: u32 = 9;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, FromFormField, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    #[default]
+    #[field(value = "half_even")]
+    HalfEven,
+    #[field(value = "half_up")]
+    HalfUp,
+    #[field(value = "truncate")]
+    Truncate,
+}
+
+/// How raw fixed-point values are turned into floats in API responses.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct NumberFormat {
+    pub rounding: Rounding,
+    /// Decimal places kept before converting; `None` keeps full precision.
+    pub max_decimals: Option<u32>,
+}
+
+pub fn pow10(exp: u32) -> u128 {
+    10u128.pow(exp)
+}
+
+fn round_div(value: u128, divisor: u128, rounding: Rounding) -> u128 {
+    let quotient = value / divisor;
+    let remainder = value % divisor;
+    match rounding {
+        Rounding::Truncate => quotient,
+        Rounding::HalfUp if remainder * 2 >= divisor => quotient + 1,
+        Rounding::HalfUp => quotient,
+        Rounding::HalfEven => match (remainder * 2).cmp(&divisor) {
+            Ordering::Less => quotient,
+            Ordering::Greater => quotient + 1,
+            Ordering::Equal => quotient + quotient % 2,
+        },
+    }
+}
+
+impl NumberFormat {
+    /// Converts `raw` with `decimals` implied decimal places to a float.
+    pub fn to_f64(&self, raw: u128, decimals: u32) -> f64 {
+        let (value, scale) = match self.max_decimals {
+            Some(max) if max < decimals => {
+                (round_div(raw, pow10(decimals - max), self.rounding), max)
+            }
+            _ => (raw, decimals),
+        };
+        let divisor = pow10(scale);
+        // Split so the integer part never passes through a lossy division.
+        (value / divisor) as f64 + (value % divisor) as f64 / divisor as f64
+    }
+}
+
+/// The single place raw candle values are converted for a response: the pair's
+/// on-chain decimals, its display precision and the rounding policy together.
+#[derive(Debug, Clone, Copy)]
+pub struct Formatter {
+    format: NumberFormat,
+    price_decimals: u32,
+    size_decimals: u32,
+    price_display: Option<u32>,
+    size_display: Option<u32>,
+}
+
+impl Formatter {
+    pub fn new(format: &NumberFormat, pair: Option<&TradingPairConfig>) -> Self {
+        Self {
+            format: *format,
+            price_decimals: pair.map_or(DEFAULT_DECIMALS, |p| p.price_decimals()),
+            size_decimals: pair.map_or(DEFAULT_DECIMALS, |p| p.size_decimals()),
+            price_display: pair.and_then(|p| p.price_display_decimals),
+            size_display: pair.and_then(|p| p.size_display_decimals),
+        }
+    }
+
+    /// Overrides the configured rounding policy, e.g. from a query parameter.
+    pub fn with_rounding(mut self, rounding: Option<Rounding>) -> Self {
+        if let Some(rounding) = rounding {
+            self.format.rounding = rounding;
+        }
+        self
+    }
+
+    fn with_display(&self, display: Option<u32>) -> NumberFormat {
+        NumberFormat {
+            max_decimals: display.or(self.format.max_decimals),
+            ..self.format
+        }
+    }
+
+    pub fn price(&self, raw: u128) -> f64 {
+        self.with_display(self.price_display)
+            .to_f64(raw, self.price_decimals)
+    }
+
+    pub fn size(&self, raw: u128) -> f64 {
+        self.with_display(self.size_display)
+            .to_f64(raw, self.size_decimals)
+    }
+
+    /// Quote amounts carry price and size decimals and are shown at price precision.
+    pub fn quote(&self, raw: u128) -> f64 {
+        self.with_display(self.price_display)
+            .to_f64(raw, self.price_decimals + self.size_decimals)
+    }
+}
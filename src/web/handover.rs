@@ -0,0 +1,159 @@
+use log::{error, info, warn};
+use rocket::fairing::AdHoc;
+use rocket::{Build, Rocket};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::os::fd::FromRawFd;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::JoinSet;
+
+use crate::config::env::ev;
+use crate::error::Error;
+
+/// How long open connections may take to finish once a newer process has taken over.
+const DRAIN_GRACE: Duration = Duration::from_secs(30);
+/// First descriptor systemd passes activated sockets on, after stdin, stdout and stderr.
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// Whether to serve through a listener that can be handed over between versions:
+/// always under systemd socket activation, otherwise with `SOCKET_HANDOVER=true`.
+pub fn enabled() -> bool {
+    systemd_activated() || ev("SOCKET_HANDOVER").is_ok_and(|value| value == "true")
+}
+
+fn systemd_activated() -> bool {
+    let pid = ev("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok());
+    let fds = ev("LISTEN_FDS")
+        .ok()
+        .and_then(|fds| fds.parse::<u32>().ok());
+    pid == Some(std::process::id()) && fds.is_some_and(|fds| fds > 0)
+}
+
+/// The public listener: the socket systemd activated us with, or a `SO_REUSEPORT` bind
+/// that the next version can share while this one drains.
+fn bind_public(port: u16) -> Result<TcpListener, Error> {
+    if systemd_activated() {
+        // SAFETY: with `LISTEN_PID` naming this process, systemd passed it the listening
+        // socket at this descriptor and nothing else owns it.
+        let listener = unsafe { std::net::TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+        listener.set_nonblocking(true)?;
+        return Ok(TcpListener::from_std(listener)?);
+    }
+    let socket = TcpSocket::new_v4()?;
+    socket.set_reuseaddr(true)?;
+    socket.set_reuseport(true)?;
+    socket.bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))?;
+    Ok(socket.listen(1024)?)
+}
+
+/// Serves `rocket` behind a handover listener on `port`. Rocket itself listens on an
+/// ephemeral loopback port and connections are forwarded to it. On `shutdown` the public
+/// listener closes first, so new connections go to the process that took over, and
+/// Rocket stops only once the open connections finished or `DRAIN_GRACE` ran out.
+pub async fn launch(rocket: Rocket<Build>, port: u16, shutdown: broadcast::Receiver<()>) {
+    let public = match bind_public(port) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "Failed to bind the handover listener on port {}: {}",
+                port, e
+            );
+            return;
+        }
+    };
+
+    // Signals are handled by `main`, which closes the public listener before Rocket goes.
+    let figment = rocket
+        .figment()
+        .clone()
+        .merge(("address", Ipv4Addr::LOCALHOST))
+        .merge(("port", 0))
+        .merge(("shutdown.ctrlc", false))
+        .merge(("shutdown.signals", Vec::<String>::new()));
+    let (port_tx, port_rx) = oneshot::channel();
+    let rocket =
+        rocket
+            .configure(figment)
+            .attach(AdHoc::on_liftoff("Handover backend port", |rocket| {
+                Box::pin(async move {
+                    let _ = port_tx.send(rocket.config().port);
+                })
+            }));
+
+    let rocket = match rocket.ignite().await {
+        Ok(rocket) => rocket,
+        Err(e) => {
+            error!("Error launching Rocket server: {:?}", e);
+            return;
+        }
+    };
+    let stop = rocket.shutdown();
+    let server = tokio::spawn(rocket.launch());
+
+    match port_rx.await {
+        Ok(backend_port) => {
+            info!(
+                "Serving port {} through the handover listener to Rocket on {}",
+                port, backend_port
+            );
+            let backend = SocketAddr::from((Ipv4Addr::LOCALHOST, backend_port));
+            forward(public, backend, shutdown).await;
+        }
+        Err(_) => error!("Rocket stopped before it started listening"),
+    }
+
+    stop.notify();
+    match server.await {
+        Ok(Err(e)) => error!("Error launching Rocket server: {:?}", e),
+        Err(e) => error!("Rocket server task failed: {}", e),
+        Ok(Ok(_)) => {}
+    }
+}
+
+async fn forward(
+    listener: TcpListener,
+    backend: SocketAddr,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((client, _)) => {
+                    connections.spawn(proxy(client, backend));
+                }
+                Err(e) => warn!("Failed to accept a connection: {}", e),
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            _ = shutdown.recv() => break,
+        }
+    }
+
+    drop(listener);
+    info!(
+        "Stopped accepting connections, draining {} open ones",
+        connections.len()
+    );
+    let drained = tokio::time::timeout(DRAIN_GRACE, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        warn!(
+            "Closing {} connections still open after the drain period",
+            connections.len()
+        );
+    }
+}
+
+async fn proxy(mut client: TcpStream, backend: SocketAddr) {
+    match TcpStream::connect(backend).await {
+        Ok(mut upstream) => {
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+        }
+        Err(e) => warn!("Failed to reach Rocket on {}: {}", backend, e),
+    }
+}
@@ -1,2 +1,17 @@
+pub mod auth;
+pub mod binance;
+pub mod caching;
+pub mod chart;
+pub mod compression;
+pub mod deprecation;
+pub mod export;
+pub mod format;
+pub mod handover;
+pub mod negotiation;
+pub mod params;
+pub mod range;
 pub mod routes;
 pub mod server;
+pub mod socket;
+pub mod tenant;
+pub mod version;
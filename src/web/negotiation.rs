@@ -0,0 +1,107 @@
+use rocket::http::{ContentType, Header, MediaType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+use rocket::serde::json::Json;
+use rocket::serde::msgpack;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use rocket_okapi::response::OpenApiResponderInner;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io::Cursor;
+
+/// How a response body is encoded, chosen from the request's `Accept` header: JSON by
+/// default, MessagePack for clients that weigh `application/msgpack` (or
+/// `application/x-msgpack`) above JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl Encoding {
+    fn of(req: &Request<'_>) -> Self {
+        let Some(accept) = req.accept() else {
+            return Encoding::Json;
+        };
+        let weight = |accepts: &dyn Fn(&MediaType) -> bool| {
+            accept
+                .iter()
+                .filter(|media| accepts(media.media_type()))
+                .map(|media| media.weight_or(1.0))
+                .fold(0.0, f32::max)
+        };
+        let msgpack = weight(&|media| {
+            media.top() == "application" && (media.sub() == "msgpack" || media.sub() == "x-msgpack")
+        });
+        let json = weight(&|media| {
+            media.top() == "*"
+                || media.top() == "application" && (media.sub() == "json" || media.sub() == "*")
+        });
+        if msgpack > json {
+            Encoding::MessagePack
+        } else {
+            Encoding::Json
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Encoding {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Encoding::of(req))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Encoding {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// A body serialized as the `Encoding` asks, with field names kept in MessagePack so
+/// both encodings have the same shape.
+pub struct Negotiated<T> {
+    encoding: Encoding,
+    value: T,
+}
+
+impl<T> Negotiated<T> {
+    pub fn new(encoding: Encoding, value: T) -> Self {
+        Self { encoding, value }
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for Negotiated<T> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = match self.encoding {
+            Encoding::Json => Json(self.value).respond_to(req)?,
+            Encoding::MessagePack => {
+                let body = msgpack::to_vec(&self.value).map_err(|e| {
+                    log::error!("Failed to encode MessagePack response: {}", e);
+                    Status::InternalServerError
+                })?;
+                Response::build()
+                    .header(ContentType::MsgPack)
+                    .sized_body(body.len(), Cursor::new(body))
+                    .finalize()
+            }
+        };
+        response.adjoin_header(Header::new("Vary", "Accept"));
+        Ok(response)
+    }
+}
+
+impl<T: Serialize + JsonSchema> OpenApiResponderInner for Negotiated<T> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        Json::<T>::responses(gen)
+    }
+}
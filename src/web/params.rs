@@ -0,0 +1,34 @@
+use rocket::FromFormField;
+use schemars::JsonSchema;
+
+/// Denomination of the `v` / `volume` fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField, JsonSchema)]
+#[schemars(rename_all = "lowercase")]
+pub enum VolumeIn {
+    #[default]
+    Base,
+    Quote,
+}
+
+/// Which price the candles are built from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField, JsonSchema)]
+#[schemars(rename_all = "lowercase")]
+pub enum PriceSource {
+    /// Last-trade prices.
+    #[default]
+    Trade,
+    /// The midpoint of best bid and ask, for pairs with `mid_price_candles` on.
+    Mid,
+}
+
+/// Bars as traded, or transformed for charting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField, JsonSchema)]
+#[schemars(rename_all = "snake_case")]
+pub enum CandleType {
+    #[default]
+    #[field(value = "candles")]
+    Candles,
+    /// Heikin-Ashi bars, which average each bar with the one before to smooth trends.
+    #[field(value = "heikin_ashi")]
+    HeikinAshi,
+}
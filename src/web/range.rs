@@ -0,0 +1,94 @@
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use std::io::Cursor;
+
+/// An in-memory body that honours single `Range: bytes=` requests, which some link
+/// preview and mail proxies issue when fetching images.
+pub struct RangedBody {
+    content_type: ContentType,
+    bytes: Vec<u8>,
+}
+
+impl RangedBody {
+    pub fn new(content_type: ContentType, bytes: Vec<u8>) -> Self {
+        Self {
+            content_type,
+            bytes,
+        }
+    }
+}
+
+/// Resolves a `bytes=` range against a body of `len` bytes into an inclusive span.
+/// `Err` means unsatisfiable; `Ok(None)` means serve the whole body, which is also the
+/// answer for multi-range and malformed headers.
+fn resolve_range(header: &str, len: usize) -> Result<Option<(usize, usize)>, ()> {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    let span = if start.is_empty() {
+        let Ok(suffix) = end.parse::<usize>() else {
+            return Ok(None);
+        };
+        if suffix == 0 || len == 0 {
+            return Err(());
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let Ok(start) = start.parse::<usize>() else {
+            return Ok(None);
+        };
+        let end = match end {
+            "" => len.saturating_sub(1),
+            end => match end.parse::<usize>() {
+                Ok(end) => end.min(len.saturating_sub(1)),
+                Err(_) => return Ok(None),
+            },
+        };
+        if start >= len || start > end {
+            return Err(());
+        }
+        (start, end)
+    };
+    Ok(Some(span))
+}
+
+impl<'r> Responder<'r, 'static> for RangedBody {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let len = self.bytes.len();
+        let mut response = Response::build();
+        response
+            .header(self.content_type)
+            .header(Header::new("Accept-Ranges", "bytes"));
+
+        match req
+            .headers()
+            .get_one("Range")
+            .map(|h| resolve_range(h, len))
+        {
+            Some(Ok(Some((start, end)))) => response
+                .status(Status::PartialContent)
+                .header(Header::new(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, end, len),
+                ))
+                .sized_body(
+                    end - start + 1,
+                    Cursor::new(self.bytes[start..=end].to_vec()),
+                ),
+            Some(Err(())) => response
+                .status(Status::RangeNotSatisfiable)
+                .header(Header::new("Content-Range", format!("bytes */{}", len))),
+            _ => response.sized_body(len, Cursor::new(self.bytes)),
+        };
+        response.ok()
+    }
+}
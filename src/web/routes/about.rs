@@ -0,0 +1,11 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+
+use crate::config::server::{AboutConfig, ServerConfig};
+
+#[openapi]
+#[get("/about")]
+pub async fn get_about(server_config: &State<ServerConfig>) -> Json<AboutConfig> {
+    Json(server_config.about.clone())
+}
@@ -0,0 +1,282 @@
+use log::{error, info};
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::ContentType;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::indexer::order_event_handler::{handle_block_events, PangeaOrderEvent};
+use crate::replication::Promotion;
+use crate::storage::candles::StoreDelta;
+use crate::storage::import::{parse_csv, ConflictPolicy, ImportedCandle};
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::auth::AdminKey;
+use crate::web::deprecation::DeprecationUsage;
+use crate::web::tenant::Engine;
+
+#[get("/dead_letter?<symbol>")]
+pub async fn get_dead_letters(
+    _admin: AdminKey,
+    symbol: Option<String>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let entries = trading_engine.dead_letters.list(symbol.as_deref());
+    Json(json!({ "status": "ok", "count": entries.len(), "entries": entries }))
+}
+
+#[post("/dead_letter/replay?<symbol>")]
+pub async fn replay_dead_letters(
+    _admin: AdminKey,
+    symbol: Option<String>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let entries = trading_engine.dead_letters.take(symbol.as_deref());
+    let mut replayed = 0;
+    let mut failed = Vec::new();
+
+    for mut entry in entries {
+        let Some(store) = trading_engine.get_store(&entry.symbol) else {
+            entry.error = format!("Unknown symbol {}", entry.symbol);
+            failed.push(entry);
+            continue;
+        };
+
+        match serde_json::from_str::<PangeaOrderEvent>(&entry.payload) {
+            Ok(event) => {
+                let quarantined =
+                    handle_block_events(store, vec![event], entry.symbol.clone()).await;
+                match quarantined.into_iter().next() {
+                    Some((_, reason)) => {
+                        entry.error = reason;
+                        failed.push(entry);
+                    }
+                    None => replayed += 1,
+                }
+            }
+            Err(e) => {
+                entry.error = e.to_string();
+                failed.push(entry);
+            }
+        }
+    }
+
+    let remaining = failed.len();
+    if let Err(e) = trading_engine.dead_letters.restore(failed) {
+        error!("Failed to persist remaining dead letters: {}", e);
+    }
+    info!(
+        "Dead letter replay: {} replayed, {} still failing",
+        replayed, remaining
+    );
+
+    Json(json!({ "status": "ok", "replayed": replayed, "remaining": remaining }))
+}
+
+#[get("/replication/delta?<symbol>&<since>")]
+pub async fn get_replication_delta(
+    _admin: AdminKey,
+    symbol: String,
+    since: Option<u64>,
+    trading_engine: Engine,
+) -> Option<Json<StoreDelta>> {
+    let store = trading_engine.get_store(&symbol)?;
+    Some(Json(store.delta_since(since.unwrap_or(0))))
+}
+
+#[post("/promote")]
+pub async fn promote(
+    _admin: AdminKey,
+    promotion: &State<Arc<Promotion>>,
+) -> Json<serde_json::Value> {
+    if !promotion.standby {
+        return Json(json!({ "status": "error", "message": "Instance is not a standby" }));
+    }
+    promotion.notify.notify_one();
+    Json(json!({ "status": "ok" }))
+}
+
+/// Who is still calling deprecated routes, to decide when they can be removed.
+#[get("/deprecations")]
+pub async fn get_deprecations(
+    _admin: AdminKey,
+    usage: &State<Arc<DeprecationUsage>>,
+) -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok", "usage": usage.snapshot() }))
+}
+
+/// Checks that derived intervals agree with the candles they are aggregated from.
+#[get("/validate?<symbol>")]
+pub async fn validate(
+    _admin: AdminKey,
+    symbol: Option<String>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let symbols: Vec<_> = match symbol {
+        Some(symbol) if !trading_engine.contains(&symbol) => {
+            return Json(json!({ "status": "error", "message": "Symbol not found" }));
+        }
+        Some(symbol) => vec![symbol],
+        None => trading_engine.symbols(),
+    };
+
+    let mut checked = 0;
+    let mut mismatches = Vec::new();
+    for symbol in symbols {
+        if let Some(store) = trading_engine.get_store(&symbol) {
+            let report = store.validate(&symbol);
+            checked += report.checked;
+            mismatches.extend(report.mismatches);
+        }
+    }
+
+    Json(json!({
+        "status": "ok",
+        "checked": checked,
+        "consistent": mismatches.is_empty(),
+        "mismatches": mismatches,
+    }))
+}
+
+/// Recomputes every interval of `symbol` from the trade archive. When the archive no
+/// longer reaches back to the first trade, history before it would be lost, so that
+/// needs `force=true`.
+#[post("/rebuild?<symbol>&<force>")]
+pub async fn rebuild(
+    _admin: AdminKey,
+    symbol: String,
+    force: Option<bool>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    if !force.unwrap_or(false) && !store.trades.covers(&symbol, i64::MIN) {
+        return Json(json!({
+            "status": "error",
+            "message": "Trade archive is incomplete; pass force=true to drop older candles",
+        }));
+    }
+
+    let rebuild_symbol = symbol.clone();
+    match tokio::task::spawn_blocking(move || store.rebuild(&rebuild_symbol)).await {
+        Ok(report) => {
+            info!(
+                "Rebuilt {} from {} trades into {} candles",
+                symbol, report.trades, report.candles
+            );
+            Json(json!({ "status": "ok", "symbol": symbol, "report": report }))
+        }
+        Err(e) => {
+            error!("Rebuild of {} failed: {}", symbol, e);
+            Json(json!({ "status": "error", "message": e.to_string() }))
+        }
+    }
+}
+
+/// Imports external candles into one interval of `symbol`, from a CSV body when sent as
+/// `text/csv` and a JSON array otherwise. Already stored periods are resolved by `policy`
+/// (`skip`, `overwrite` or `merge_extremes`); `dry_run=true` only reports what would change.
+#[post("/import?<symbol>&<interval>&<policy>&<dry_run>", data = "<body>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn import(
+    _admin: AdminKey,
+    symbol: String,
+    interval: u64,
+    policy: Option<&str>,
+    dry_run: Option<bool>,
+    content_type: Option<&ContentType>,
+    body: Data<'_>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    if !store.intervals().contains(&interval) {
+        return Json(json!({ "status": "error", "message": "Interval not stored" }));
+    }
+    let policy = match policy.map(str::parse::<ConflictPolicy>).transpose() {
+        Ok(policy) => policy.unwrap_or_default(),
+        Err(e) => return Json(json!({ "status": "error", "message": e.to_string() })),
+    };
+
+    let body = match body.open(64.mebibytes()).into_string().await {
+        Ok(body) if body.is_complete() => body.into_inner(),
+        Ok(_) => return Json(json!({ "status": "error", "message": "Body too large" })),
+        Err(e) => return Json(json!({ "status": "error", "message": e.to_string() })),
+    };
+    let candles = if content_type.is_some_and(|ct| ct.is_csv()) {
+        parse_csv(&body)
+    } else {
+        serde_json::from_str::<Vec<ImportedCandle>>(&body).map_err(Into::into)
+    };
+    let candles = match candles {
+        Ok(candles) => candles,
+        Err(e) => return Json(json!({ "status": "error", "message": e.to_string() })),
+    };
+
+    let dry_run = dry_run.unwrap_or(false);
+    let import_symbol = symbol.clone();
+    let import = move || store.import(&import_symbol, interval, &candles, policy, dry_run);
+    match tokio::task::spawn_blocking(import).await {
+        Ok(report) => {
+            if !dry_run {
+                info!(
+                    "Imported into {} {}s: {} inserted, {} overwritten, {} merged, {} skipped, {} rejected",
+                    symbol,
+                    interval,
+                    report.inserted,
+                    report.overwritten,
+                    report.merged,
+                    report.skipped,
+                    report.rejected.len()
+                );
+            }
+            Json(
+                json!({ "status": "ok", "symbol": symbol, "interval": interval, "report": report }),
+            )
+        }
+        Err(e) => {
+            error!("Import into {} failed: {}", symbol, e);
+            Json(json!({ "status": "error", "message": e.to_string() }))
+        }
+    }
+}
+
+/// Stops indexing `symbol` until it is resumed, e.g. while its contract is migrated.
+/// Candles stay served meanwhile.
+#[post("/pairs/<symbol>/pause")]
+pub async fn pause_pair(
+    _admin: AdminKey,
+    symbol: &str,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    set_paused(&trading_engine, symbol, true)
+}
+
+/// Resumes indexing `symbol` from the block it was paused at.
+#[post("/pairs/<symbol>/resume")]
+pub async fn resume_pair(
+    _admin: AdminKey,
+    symbol: &str,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    set_paused(&trading_engine, symbol, false)
+}
+
+fn set_paused(
+    trading_engine: &TradingEngine,
+    symbol: &str,
+    paused: bool,
+) -> Json<serde_json::Value> {
+    if !trading_engine.contains(symbol) {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    }
+    let changed = trading_engine.pair_controls.set_paused(symbol, paused);
+    info!(
+        "Indexing of {} {} from the admin API",
+        symbol,
+        if paused { "paused" } else { "resumed" }
+    );
+    Json(json!({ "status": "ok", "symbol": symbol, "paused": paused, "changed": changed }))
+}
@@ -0,0 +1,205 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::storage::candles::CandleStore;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// An error for ranges starting at `start`, in seconds, before the oldest bar `store`
+/// still holds for `symbol` at `interval`, whose averages would leave out the bars
+/// retention dropped.
+fn before_horizon(
+    store: &CandleStore,
+    symbol: &str,
+    interval: u64,
+    start: i64,
+) -> Option<Json<serde_json::Value>> {
+    let horizon = store.horizon(symbol, interval)?;
+    (start < horizon.timestamp()).then(|| {
+        Json(json!({
+            "status": "error",
+            "message": format!("History before {} is no longer stored", horizon.timestamp()),
+        }))
+    })
+}
+
+fn too_large() -> Json<serde_json::Value> {
+    Json(json!({ "status": "error", "message": "Amounts too large to average" }))
+}
+
+/// Time-weighted average price over `[from, to]`, in seconds. The price is taken to
+/// hold until the next one: each raw trade's while the archive holds every trade of the
+/// range, and otherwise each of the pair's base bars contributes the mean of its open,
+/// high, low and close over its period, then its close. The range opens at the close of
+/// the last bar before `from`; for a pair without one, `priced_from` says where the
+/// first price came in. A `from` before the oldest base bar still held is rejected.
+#[openapi]
+#[get("/twap?<symbol>&<from>&<to>")]
+pub async fn get_twap(
+    symbol: String,
+    from: i64,
+    to: i64,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    if from >= to {
+        return Json(json!({ "status": "error", "message": "from must be before to" }));
+    }
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+
+    // Prices from the time they are set, in milliseconds.
+    let (start, end) = (from.saturating_mul(1000), to.saturating_mul(1000));
+    let interval = store.base_interval();
+    let mut prices: Vec<(i64, u128)> = store
+        .get_candles_until(&symbol, interval, from.saturating_sub(1), 1)
+        .iter()
+        .map(|c| (start, c.close))
+        .collect();
+    let trades = store.archived_trades(&symbol, from, to);
+    let source = if trades.is_some() {
+        "trades"
+    } else {
+        "candles"
+    };
+    match trades {
+        Some(trades) => prices.extend(trades.iter().map(|t| (t.event_time, t.price))),
+        None => {
+            if let Some(response) = before_horizon(&store, &symbol, interval, from) {
+                return response;
+            }
+            for candle in store.get_candles_in_time_range(&symbol, interval, from, to) {
+                if candle.is_gap() {
+                    continue;
+                }
+                let opened = candle.timestamp.timestamp_millis();
+                let Some(mean) = [candle.open, candle.high, candle.low, candle.close]
+                    .into_iter()
+                    .try_fold(0u128, u128::checked_add)
+                else {
+                    return too_large();
+                };
+                prices.push((opened, mean / 4));
+                prices.push((opened + interval as i64 * 1000, candle.close));
+            }
+        }
+    }
+
+    let (mut weighted, mut duration) = (Some(0u128), 0u128);
+    for (i, &(time, price)) in prices.iter().enumerate() {
+        let until = prices.get(i + 1).map_or(end, |&(next, _)| next.min(end));
+        let held = until.saturating_sub(time.max(start));
+        if held > 0 {
+            weighted = weighted.and_then(|sum| sum.checked_add(price.checked_mul(held as u128)?));
+            duration += held as u128;
+        }
+    }
+    let Some(weighted) = weighted else {
+        return too_large();
+    };
+    if duration == 0 {
+        return Json(json!({
+            "status": "no_data",
+            "message": format!("No prices found for symbol={} in [{}, {}]", symbol, from, to),
+        }));
+    }
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "from": from,
+        "to": to,
+        "source": source,
+        "priced_from": prices[0].0.max(start).div_euclid(1000),
+        "twap": formatter.price(weighted / duration),
+    }))
+}
+
+/// Volume-weighted average price anchored at `anchor`, over the trades up to `to` (now
+/// by default). Computed from raw trades while the archive holds every trade of the
+/// range, and otherwise from the pair's base bars starting at or after `anchor`, which
+/// agree with the trades whenever `anchor` falls on a bar boundary. An `anchor` before
+/// the oldest base bar still held is rejected.
+#[openapi]
+#[get("/vwap?<symbol>&<anchor>&<to>")]
+pub async fn get_vwap(
+    symbol: String,
+    anchor: i64,
+    to: Option<i64>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+    let trades = store.archived_trades(&symbol, anchor, to);
+    let source = if trades.is_some() {
+        "trades"
+    } else {
+        "candles"
+    };
+    let totals = match trades {
+        Some(trades) => {
+            trades
+                .iter()
+                .try_fold((0u128, 0u128, 0u64), |(volume, quote, count), t| {
+                    Some((
+                        volume.checked_add(t.volume)?,
+                        quote.checked_add(t.price.checked_mul(t.volume)?)?,
+                        count + 1,
+                    ))
+                })
+        }
+        None => {
+            let interval = store.base_interval();
+            if let Some(response) = before_horizon(&store, &symbol, interval, anchor) {
+                return response;
+            }
+            store
+                .get_candles_in_time_range(&symbol, interval, anchor, to)
+                .iter()
+                .try_fold((0u128, 0u128, 0u64), |(volume, quote, count), c| {
+                    Some((
+                        volume.checked_add(c.volume)?,
+                        quote.checked_add(c.quote_volume)?,
+                        count + c.trade_count,
+                    ))
+                })
+        }
+    };
+    let Some((volume, quote_volume, trade_count)) = totals else {
+        return too_large();
+    };
+    if volume == 0 {
+        return Json(json!({
+            "status": "no_data",
+            "message": format!("No trades found for symbol={} in [{}, {}]", symbol, anchor, to),
+        }));
+    }
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "anchor": anchor,
+        "to": to,
+        "source": source,
+        "vwap": formatter.price(quote_volume / volume),
+        "volume": formatter.size(volume),
+        "quote_volume": formatter.quote(quote_volume),
+        "trade_count": trade_count,
+    }))
+}
@@ -0,0 +1,218 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, Shutdown, State};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::config::server::ServerConfig;
+use crate::storage::candles::Candle;
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::binance::{
+    error, interval_seconds, kline_event, kline_row, kline_stream, resolve_symbol,
+};
+use crate::web::format::{Formatter, NumberFormat};
+use crate::web::socket::{BarFollower, Outbox, Session, Subscriptions, Upgrade, WsHandshake};
+use crate::web::tenant::Engine;
+
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 1000;
+
+/// Candles in the shape of Binance's `GET /api/v3/klines`, for bots and libraries that
+/// speak Binance. `startTime` and `endTime` are in milliseconds; with `startTime` the
+/// first `limit` bars from it are returned, otherwise the last `limit` up to `endTime`.
+#[get("/api/v3/klines?<symbol>&<interval>&<startTime>&<endTime>&<limit>")]
+#[allow(non_snake_case)]
+pub async fn get_klines(
+    symbol: String,
+    interval: String,
+    startTime: Option<i64>,
+    endTime: Option<i64>,
+    limit: Option<usize>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Result<Json<Vec<Value>>, (Status, Json<Value>)> {
+    let symbol = resolve_symbol(&trading_engine, &symbol).ok_or(error(-1121, "Invalid symbol."))?;
+    let store = trading_engine
+        .get_store(&symbol)
+        .ok_or(error(-1121, "Invalid symbol."))?;
+    let seconds = interval_seconds(&interval)
+        .filter(|&seconds| store.stores_interval(seconds))
+        .ok_or(error(-1120, "Invalid interval."))?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+
+    let to = endTime.map_or(i64::MAX, |end| end.div_euclid(1000));
+    let candles = match startTime {
+        Some(start) => store.get_candles_from(&symbol, seconds, start.div_euclid(1000), to, limit),
+        None => store.get_candles_until(&symbol, seconds, to, limit),
+    };
+
+    Ok(Json(
+        candles
+            .iter()
+            .map(|candle| kline_row(candle, seconds, &formatter))
+            .collect(),
+    ))
+}
+
+/// A kline stream a connection can follow.
+struct KlineStream {
+    name: String,
+    symbol: String,
+    interval: String,
+    seconds: u64,
+}
+
+impl KlineStream {
+    fn resolve(trading_engine: &TradingEngine, name: &str) -> Result<Self, String> {
+        let (symbol, interval) =
+            kline_stream(name).ok_or_else(|| format!("Invalid stream name: {}", name))?;
+        let symbol = resolve_symbol(trading_engine, symbol)
+            .ok_or_else(|| format!("Invalid symbol: {}", symbol))?;
+        let seconds = interval_seconds(interval)
+            .filter(|&seconds| {
+                trading_engine
+                    .get_store(&symbol)
+                    .is_some_and(|store| store.stores_interval(seconds))
+            })
+            .ok_or_else(|| format!("Invalid interval: {}", interval))?;
+        Ok(Self {
+            name: format!("{}@kline_{}", symbol.to_lowercase(), interval),
+            symbol,
+            interval: interval.to_string(),
+            seconds,
+        })
+    }
+}
+
+/// A request frame of Binance's WebSocket API.
+#[derive(Deserialize)]
+struct StreamRequest {
+    method: String,
+    #[serde(default)]
+    params: Vec<String>,
+    #[serde(default)]
+    id: Value,
+}
+
+/// A kline stream connection's subscriptions by stream name.
+pub struct KlineSession {
+    trading_engine: Arc<TradingEngine>,
+    number_format: NumberFormat,
+    /// The stream named in the URL, followed from the start.
+    initial: Option<KlineStream>,
+    subscriptions: Subscriptions<String>,
+}
+
+impl Session for KlineSession {
+    fn start(&mut self, outbox: &Outbox) {
+        if let Some(stream) = self.initial.take() {
+            self.subscribe(stream, outbox);
+        }
+    }
+
+    fn handle(&mut self, text: &str, outbox: &Outbox) -> Option<Message> {
+        let reply = match serde_json::from_str::<StreamRequest>(text) {
+            Ok(request) => self.apply(request, outbox),
+            Err(e) => json!({ "error": { "code": 2, "msg": format!("Invalid request: {}", e) } }),
+        };
+        Some(Message::Text(reply.to_string()))
+    }
+}
+
+impl KlineSession {
+    fn apply(&mut self, request: StreamRequest, outbox: &Outbox) -> Value {
+        let id = request.id;
+        match request.method.as_str() {
+            "SUBSCRIBE" => {
+                let streams: Result<Vec<_>, _> = request
+                    .params
+                    .iter()
+                    .map(|name| KlineStream::resolve(&self.trading_engine, name))
+                    .collect();
+                match streams {
+                    Ok(streams) => {
+                        for stream in streams {
+                            self.subscribe(stream, outbox);
+                        }
+                        json!({ "result": null, "id": id })
+                    }
+                    Err(msg) => json!({ "error": { "code": 2, "msg": msg }, "id": id }),
+                }
+            }
+            "UNSUBSCRIBE" => {
+                for name in &request.params {
+                    self.subscriptions.remove(&name.to_lowercase());
+                }
+                json!({ "result": null, "id": id })
+            }
+            "LIST_SUBSCRIPTIONS" => {
+                let mut names: Vec<_> = self.subscriptions.keys().cloned().collect();
+                names.sort();
+                json!({ "result": names, "id": id })
+            }
+            method => json!({
+                "error": { "code": 2, "msg": format!("Unknown method: {}", method) },
+                "id": id,
+            }),
+        }
+    }
+
+    fn subscribe(&mut self, stream: KlineStream, outbox: &Outbox) {
+        if self.subscriptions.contains(&stream.name) {
+            return;
+        }
+        let Some(store) = self.trading_engine.get_store(&stream.symbol) else {
+            return;
+        };
+        let formatter = Formatter::new(
+            &self.number_format,
+            self.trading_engine.config(&stream.symbol).as_ref(),
+        );
+        let KlineStream {
+            name,
+            symbol,
+            interval,
+            seconds,
+        } = stream;
+        let follower = BarFollower {
+            store,
+            symbol: symbol.clone(),
+            interval: seconds,
+            render: Box::new(move |candle: &Candle, closed| {
+                let event = kline_event(&symbol, &interval, seconds, candle, closed, &formatter);
+                Message::Text(event.to_string())
+            }),
+        };
+        let events = self.trading_engine.candle_events.subscribe();
+        let task = tokio::spawn(follower.run(events, outbox.clone()));
+        self.subscriptions.insert(name, task);
+    }
+}
+
+/// Binance-style kline stream at `/ws/<symbol>@kline_<interval>`, e.g.
+/// `/ws/ethusdc@kline_1m`. Events have the shape of Binance's `kline` event, with `x`
+/// set once a bar is closed. More streams can be followed with Binance's `SUBSCRIBE`,
+/// `UNSUBSCRIBE` and `LIST_SUBSCRIPTIONS` requests.
+#[get("/ws/<stream>")]
+pub fn kline_socket(
+    stream: &str,
+    handshake: WsHandshake,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+    shutdown: Shutdown,
+) -> Result<Upgrade<KlineSession>, (Status, Json<Value>)> {
+    let stream = KlineStream::resolve(&trading_engine, stream).map_err(|msg| error(-1121, &msg))?;
+    let session = KlineSession {
+        trading_engine: trading_engine.0,
+        number_format: server_config.number_format,
+        initial: Some(stream),
+        subscriptions: Subscriptions::default(),
+    };
+    Ok(Upgrade::new(handshake, server_config, shutdown, session))
+}
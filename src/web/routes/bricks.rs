@@ -0,0 +1,140 @@
+use rocket::serde::json::Json;
+use rocket::{get, FromForm, FromFormField, State};
+use rocket_okapi::openapi;
+use schemars::JsonSchema;
+use serde_json::json;
+
+use crate::bricks::{range_bars, renko, travel, Tick};
+use crate::config::server::ServerConfig;
+use crate::storage::candles::Candle;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// Seconds covered without `from`.
+const DEFAULT_SPAN: i64 = 86400;
+/// Bricks or bars returned by one request at most.
+const MAX_BRICKS: f64 = 10000.0;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField, JsonSchema, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum BrickType {
+    #[default]
+    Renko,
+    Range,
+}
+
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct BrickQuery {
+    symbol: String,
+    #[field(name = "type")]
+    #[schemars(rename = "type")]
+    kind: Option<BrickType>,
+    /// Brick height or bar range, in display price units.
+    #[field(name = "box")]
+    #[schemars(rename = "box")]
+    box_size: f64,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// The path of a candle's prices: its open, the extreme it most likely reached first,
+/// the other, and its close, which carries the volume.
+fn candle_path(candle: &Candle, formatter: &Formatter) -> [Tick; 4] {
+    let time = candle.timestamp.timestamp();
+    let tick = |price, volume| Tick {
+        time,
+        price: formatter.price(price),
+        volume,
+    };
+    let (first, second) = if candle.close >= candle.open {
+        (candle.low, candle.high)
+    } else {
+        (candle.high, candle.low)
+    };
+    [
+        tick(candle.open, 0.0),
+        tick(first, 0.0),
+        tick(second, 0.0),
+        tick(candle.close, formatter.size(candle.volume)),
+    ]
+}
+
+/// Renko bricks (`type=renko`, the default) or range bars (`type=range`) of `box` over
+/// `[from, to]` (the last 24 hours by default). Built from raw trades while the archive
+/// holds every trade of the range, and otherwise from the pair's base candles, walked
+/// open, nearer extreme, farther extreme, close. Only finished bricks are
+/// returned; a box small enough to make more than 10000 is rejected.
+#[openapi]
+#[get("/bricks?<query..>")]
+pub async fn get_bricks(
+    query: BrickQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let BrickQuery {
+        symbol,
+        kind,
+        box_size,
+        from,
+        to,
+    } = query;
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    if !(box_size.is_finite() && box_size > 0.0) {
+        return Json(json!({ "status": "error", "message": "Box size must be positive" }));
+    }
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = from.unwrap_or(to - DEFAULT_SPAN);
+
+    let trades = store.archived_trades(&symbol, from, to);
+    let source = if trades.is_some() {
+        "trades"
+    } else {
+        "candles"
+    };
+    let ticks: Vec<_> = match trades {
+        Some(trades) => trades
+            .iter()
+            .map(|trade| Tick {
+                time: trade.event_time.div_euclid(1000),
+                price: formatter.price(trade.price),
+                volume: formatter.size(trade.volume),
+            })
+            .collect(),
+        None => store
+            .get_candles_in_time_range(&symbol, store.base_interval(), from, to)
+            .iter()
+            .filter(|c| !c.is_gap())
+            .flat_map(|c| candle_path(c, &formatter))
+            .collect(),
+    };
+    if travel(&ticks) / box_size > MAX_BRICKS {
+        return Json(json!({
+            "status": "error",
+            "message": "Box size too small for the range",
+        }));
+    }
+
+    let kind = kind.unwrap_or_default();
+    let bricks = match kind {
+        BrickType::Renko => renko(&ticks, box_size),
+        BrickType::Range => range_bars(&ticks, box_size),
+    };
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "type": kind,
+        "box": box_size,
+        "source": source,
+        "from": from,
+        "to": to,
+        "bricks": bricks,
+    }))
+}
@@ -0,0 +1,37 @@
+use log::error;
+use rocket::get;
+use rocket::http::{ContentType, Status};
+
+use crate::web::chart::render_png;
+use crate::web::range::RangedBody;
+use crate::web::tenant::Engine;
+
+const DEFAULT_BARS: usize = 100;
+const MAX_BARS: usize = 500;
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+
+/// Server-rendered candle chart for places that can't run a JS chart: bot messages,
+/// OG previews, emails.
+#[get("/chart.png?<symbol>&<interval>&<bars>")]
+pub async fn get_chart_png(
+    symbol: String,
+    interval: u64,
+    bars: Option<usize>,
+    trading_engine: Engine,
+) -> Result<RangedBody, Status> {
+    let store = trading_engine.get_store(&symbol).ok_or(Status::NotFound)?;
+    if !store.intervals().contains(&interval) {
+        return Err(Status::BadRequest);
+    }
+
+    let bars = bars.unwrap_or(DEFAULT_BARS).clamp(1, MAX_BARS);
+    let mut candles = store.get_candles(&symbol, interval, bars);
+    candles.reverse();
+
+    let png = render_png(&candles, WIDTH, HEIGHT).map_err(|e| {
+        error!("Chart for {} failed: {}", symbol, e);
+        Status::InternalServerError
+    })?;
+    Ok(RangedBody::new(ContentType::PNG, png))
+}
@@ -0,0 +1,69 @@
+use rocket::get;
+use rocket::serde::json::Json;
+use rocket_okapi::openapi;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::storage::candles::Candle;
+use crate::web::tenant::Engine;
+
+/// Chains SHA-256 over the candles in order: each step hashes the previous digest with
+/// `timestamp,open,high,low,close,volume,quote_volume,trade_count` in raw units, so a
+/// series that only grew can be checked by extending the hash of its cached prefix.
+fn rolling_hash<'a>(candles: impl IntoIterator<Item = &'a Candle>) -> [u8; 32] {
+    candles.into_iter().fold([0u8; 32], |previous, c| {
+        let mut hasher = Sha256::new();
+        hasher.update(previous);
+        hasher.update(format!(
+            "{},{},{},{},{},{},{},{}",
+            c.timestamp.timestamp(),
+            c.open,
+            c.high,
+            c.low,
+            c.close,
+            c.volume,
+            c.quote_volume,
+            c.trade_count
+        ));
+        hasher.finalize().into()
+    })
+}
+
+/// Checksum of the closed candles in `[from, to]`, for clients to check their cached
+/// history against before downloading it again. The forming candle is left out since it
+/// changes with every trade.
+#[openapi]
+#[get("/checksum?<symbol>&<interval>&<from>&<to>")]
+pub async fn get_checksum(
+    symbol: String,
+    interval: u64,
+    from: Option<i64>,
+    to: Option<i64>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    if !store.intervals().contains(&interval) {
+        return Json(json!({ "status": "error", "message": "Interval not stored" }));
+    }
+
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(chrono::Utc::now().timestamp());
+    let now = chrono::Utc::now();
+    let candles: Vec<_> = store
+        .get_candles_in_time_range(&symbol, interval, from, to)
+        .into_iter()
+        .filter(|c| c.is_closed(interval, now))
+        .collect();
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "interval": interval,
+        "count": candles.len(),
+        "first": candles.first().map(|c| c.timestamp.timestamp()),
+        "last": candles.last().map(|c| c.timestamp.timestamp()),
+        "checksum": hex::encode(rolling_hash(&candles)),
+    }))
+}
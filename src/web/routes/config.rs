@@ -1,33 +1,46 @@
 use rocket::get;
 use rocket::serde::json::Json;
 use rocket_okapi::openapi;
+use serde_json::json;
+use std::collections::BTreeSet;
+
+use crate::storage::trading_engine::TradingPairConfig;
+use crate::web::tenant::Engine;
 
 #[openapi]
 #[get("/config")]
-pub async fn get_config() -> Json<serde_json::Value> {
-    Json(serde_json::json!({
+pub async fn get_config(trading_engine: Engine) -> Json<serde_json::Value> {
+    let pairs = trading_engine.pairs();
+    let names = |name: fn(&TradingPairConfig) -> &str| -> BTreeSet<String> {
+        pairs
+            .iter()
+            .map(|pair| name(&pair.config).to_string())
+            .collect()
+    };
+    let exchanges: Vec<_> =
+        std::iter::once(json!({ "value": "", "name": "All Exchanges", "desc": "" }))
+            .chain(
+                names(TradingPairConfig::exchange).into_iter().map(
+                    |exchange| json!({ "value": exchange, "name": exchange, "desc": exchange }),
+                ),
+            )
+            .collect();
+    let symbols_types: Vec<_> = std::iter::once(json!({ "name": "All types", "value": "" }))
+        .chain(
+            names(TradingPairConfig::symbol_type)
+                .into_iter()
+                .map(|symbol_type| json!({ "name": symbol_type, "value": symbol_type })),
+        )
+        .collect();
+    Json(json!({
         "supports_search": true,
-        "supports_group_request": false,
+        "supports_group_request": true,
         "supports_marks": true,
         "supports_timescale_marks": true,
         "supports_time": true,
-        "supported_resolutions": ["1", "5", "15", "30", "60", "1D", "1W", "1M"],
-        "exchanges": [
-            {
-                "value": "",
-                "name": "All Exchanges",
-                "desc": ""
-            },
-            {
-                "value": "CryptoExchange",
-                "name": "CryptoExchange",
-                "desc": "CryptoExchange"
-            }
-        ],
-        "symbols_types": [
-            { "name": "All types", "value": "" },
-            { "name": "Crypto", "value": "crypto" }
-        ]
+        "supported_resolutions": trading_engine.supported_resolutions(),
+        "exchanges": exchanges,
+        "symbols_types": symbols_types,
     }))
 }
 
@@ -0,0 +1,56 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+const DEFAULT_LEVELS: usize = 20;
+const MAX_LEVELS: usize = 500;
+
+/// Best bid and ask, spread and the best `levels` price levels per side (20 by default)
+/// of the pair's resting orders. `complete` is false while history is still loading and
+/// the book may lack older orders.
+#[openapi]
+#[get("/depth?<symbol>&<levels>")]
+pub async fn get_depth(
+    symbol: String,
+    levels: Option<usize>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(book) = trading_engine.book(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let levels = levels.unwrap_or(DEFAULT_LEVELS).min(MAX_LEVELS);
+    let depth = book.depth(levels);
+    let top = book.top();
+    let side = |levels: &[(u128, u128)]| -> Vec<_> {
+        levels
+            .iter()
+            .map(|&(price, amount)| json!([formatter.price(price), formatter.size(amount)]))
+            .collect()
+    };
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "complete": book.is_complete(),
+        "best_bid": top.bid.map(|bid| formatter.price(bid)),
+        "best_ask": top.ask.map(|ask| formatter.price(ask)),
+        "spread": top
+            .bid
+            .zip(top.ask)
+            .map(|(bid, ask)| formatter.price(ask.saturating_sub(bid))),
+        "mid": top.mid().map(|mid| formatter.price(mid)),
+        "orders": depth.orders,
+        "bids": side(&depth.bids),
+        "asks": side(&depth.asks),
+    }))
+}
@@ -0,0 +1,106 @@
+use futures_util::stream::{self, Stream, StreamExt};
+use log::error;
+use rocket::http::{ContentType, Status};
+use rocket::{get, State};
+
+use crate::config::server::ServerConfig;
+use crate::web::export::{csv_rows, ndjson_rows, CandlePages, Export, CSV_HEADER};
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// The pages of `[from, to]`, to the present by default, of a stored series.
+fn pages(
+    trading_engine: &Engine,
+    symbol: &str,
+    interval: u64,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<CandlePages, Status> {
+    let store = trading_engine.get_store(symbol).ok_or(Status::NotFound)?;
+    if !store.stores_interval(interval) {
+        return Err(Status::BadRequest);
+    }
+    let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    Ok(CandlePages::new(
+        store,
+        symbol,
+        interval,
+        from.unwrap_or(0),
+        to,
+    ))
+}
+
+/// Candles of `[from, to]` as CSV with a header row, streamed page by page for
+/// spreadsheets and dataframes.
+#[get("/export/csv?<symbol>&<interval>&<from>&<to>")]
+pub async fn get_csv(
+    symbol: String,
+    interval: u64,
+    from: Option<i64>,
+    to: Option<i64>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Result<Export<impl Stream<Item = Vec<u8>>>, Status> {
+    let pages = pages(&trading_engine, &symbol, interval, from, to)?;
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let body = stream::once(async { CSV_HEADER.as_bytes().to_vec() })
+        .chain(pages.into_stream(move |page| csv_rows(page, &formatter)));
+    Ok(Export::new(
+        ContentType::CSV,
+        format!("{}_{}.csv", symbol, interval),
+        body,
+    ))
+}
+
+/// Candles of `[from, to]` as newline-delimited JSON, one candle per line, streamed page
+/// by page so ETL jobs can pull full history.
+#[get("/export/ndjson?<symbol>&<interval>&<from>&<to>")]
+pub async fn get_ndjson(
+    symbol: String,
+    interval: u64,
+    from: Option<i64>,
+    to: Option<i64>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Result<Export<impl Stream<Item = Vec<u8>>>, Status> {
+    let pages = pages(&trading_engine, &symbol, interval, from, to)?;
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    Ok(Export::new(
+        ContentType::new("application", "x-ndjson"),
+        format!("{}_{}.ndjson", symbol, interval),
+        pages.into_stream(move |page| ndjson_rows(page, &formatter)),
+    ))
+}
+
+/// Candles of `[from, to]` as a Parquet file, written a row group per page as the body
+/// is sent.
+#[get("/export/parquet?<symbol>&<interval>&<from>&<to>")]
+pub async fn get_parquet(
+    symbol: String,
+    interval: u64,
+    from: Option<i64>,
+    to: Option<i64>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Result<Export<impl Stream<Item = Vec<u8>>>, Status> {
+    let pages = pages(&trading_engine, &symbol, interval, from, to)?;
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let body = pages.into_parquet(formatter).map_err(|e| {
+        error!("Parquet export of {} failed: {}", symbol, e);
+        Status::InternalServerError
+    })?;
+    Ok(Export::new(
+        ContentType::new("application", "vnd.apache.parquet"),
+        format!("{}_{}.parquet", symbol, interval),
+        body,
+    ))
+}
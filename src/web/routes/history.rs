@@ -1,66 +1,415 @@
+use chrono::DateTime;
 use log::warn;
 use rocket::serde::json::Json;
-use rocket::{get, State};
+use rocket::{get, FromForm, State};
 use rocket_okapi::openapi;
 use schemars::JsonSchema;
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use crate::config::server::ServerConfig;
+use crate::storage::candles::{Candle, CandleStore};
+use crate::storage::interval::{period_start, resolution_seconds};
 use crate::storage::trading_engine::TradingEngine;
+use crate::web::caching::{cache_control, DataVersion, HistoryCache, Validated};
+use crate::web::format::{Formatter, NumberFormat, Rounding};
+use crate::web::negotiation::{Encoding, Negotiated};
+use crate::web::params::{CandleType, PriceSource, VolumeIn};
+use crate::web::tenant::Engine;
 
-#[derive(serde::Serialize, JsonSchema)]
+/// Upper bound on bars returned when gaps are synthesized without a `countback`.
+const MAX_SYNTHESIZED_BARS: usize = 50000;
+/// Symbols served by one `/history_batch` request.
+const MAX_BATCH_SYMBOLS: usize = 50;
+/// Resolutions served by one `/history_multi` request.
+const MAX_MULTI_RESOLUTIONS: usize = 10;
+/// Candles per `/candles` page without a `limit`, and the most a `limit` can ask for.
+const DEFAULT_PAGE_SIZE: usize = 1000;
+const MAX_PAGE_SIZE: usize = 10000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub struct AdvancedChartResponse {
-    s: String,
-    t: Vec<u64>,
-    o: Vec<f64>,
-    h: Vec<f64>,
-    l: Vec<f64>,
-    c: Vec<f64>,
-    v: Vec<f64>,
+    pub s: String,
+    pub t: Vec<u64>,
+    pub o: Vec<f64>,
+    pub h: Vec<f64>,
+    pub l: Vec<f64>,
+    pub c: Vec<f64>,
+    pub v: Vec<f64>,
+    /// Trade counts, only with `extended=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<Vec<u64>>,
+    /// Volume-weighted average prices (null for empty bars), only with `extended=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vw: Option<Vec<Option<f64>>>,
+    /// Whether each bar's period has ended, only with `extended=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_closed: Option<Vec<bool>>,
+    /// Volume without detected self-trades, in the same unit as `v`, only with
+    /// `extended=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cv: Option<Vec<f64>>,
+}
+
+impl AdvancedChartResponse {
+    fn empty(status: &str) -> Self {
+        Self {
+            s: status.to_string(),
+            t: vec![],
+            o: vec![],
+            h: vec![],
+            l: vec![],
+            c: vec![],
+            v: vec![],
+            n: None,
+            vw: None,
+            is_closed: None,
+            cv: None,
+        }
+    }
+
+    fn from_candles(
+        candles: &[Candle],
+        interval: u64,
+        formatter: &Formatter,
+        extended: bool,
+        volume_in: VolumeIn,
+    ) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            s: "ok".to_string(),
+            t: candles
+                .iter()
+                .map(|c| c.timestamp.timestamp() as u64)
+                .collect(),
+            o: candles.iter().map(|c| formatter.price(c.open)).collect(),
+            h: candles.iter().map(|c| formatter.price(c.high)).collect(),
+            l: candles.iter().map(|c| formatter.price(c.low)).collect(),
+            c: candles.iter().map(|c| formatter.price(c.close)).collect(),
+            v: candles
+                .iter()
+                .map(|c| match volume_in {
+                    VolumeIn::Base => formatter.size(c.volume),
+                    VolumeIn::Quote => formatter.quote(c.quote_volume),
+                })
+                .collect(),
+            n: extended.then(|| candles.iter().map(|c| c.trade_count).collect()),
+            vw: extended.then(|| {
+                candles
+                    .iter()
+                    .map(|c| c.vwap().map(|vwap| formatter.price(vwap)))
+                    .collect()
+            }),
+            is_closed: extended
+                .then(|| candles.iter().map(|c| c.is_closed(interval, now)).collect()),
+            cv: extended.then(|| {
+                candles
+                    .iter()
+                    .map(|c| {
+                        let (volume, quote_volume) = c.clean_volume();
+                        match volume_in {
+                            VolumeIn::Base => formatter.size(volume),
+                            VolumeIn::Quote => formatter.quote(quote_volume),
+                        }
+                    })
+                    .collect()
+            }),
+        }
+    }
 }
 
+#[derive(Debug, Clone, FromForm, JsonSchema)]
+pub struct HistoryQuery {
+    symbol: String,
+    resolution: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    /// Returns this many bars ending at `to`, ignoring `from`.
+    countback: Option<usize>,
+    /// Adds trade counts (`n`) and VWAP (`vw`) arrays.
+    extended: Option<bool>,
+    volume_in: Option<VolumeIn>,
+    /// Overrides the server's rounding policy for this response.
+    rounding: Option<Rounding>,
+    /// Synthesizes flat bars between trades for pairs and intervals stored without gaps.
+    fill_gaps: Option<bool>,
+    /// Leaves out the still-forming bar.
+    closed_only: Option<bool>,
+    /// Builds bars from last trades (default) or from the mid-price, where tracked.
+    price_source: Option<PriceSource>,
+    /// Transforms the bars, e.g. into Heikin-Ashi bars.
+    candle_type: Option<CandleType>,
+}
+
+impl HistoryQuery {
+    /// The store the bars are read from, as `price_source` picks.
+    fn store(&self, trading_engine: &TradingEngine) -> Option<Arc<CandleStore>> {
+        match self.price_source.unwrap_or_default() {
+            PriceSource::Trade => trading_engine.get_store(&self.symbol),
+            PriceSource::Mid => trading_engine.mid_store(&self.symbol),
+        }
+    }
+}
+
+/// Recent `/history` responses with the version of their bars.
+pub type HistoryResponseCache = HistoryCache<(AdvancedChartResponse, Option<DataVersion>)>;
+
+/// TradingView UDF bars. Responses carry an `ETag` and `Last-Modified` from the
+/// parameters and the newest bar, so refreshing an unchanged range gets `304`, and a
+/// `Cache-Control` letting shared caches keep ranges that ended before the live bar.
+/// `candle_type=heikin_ashi` returns Heikin-Ashi bars, seeded from the first bar of the
+/// range. Sent as MessagePack to clients that prefer `application/msgpack`.
 #[openapi]
-#[get("/history?<symbol>&<resolution>&<from>&<to>&<countback>")]
+#[get("/history?<query..>")]
 pub async fn get_history(
-    symbol: String,
+    query: HistoryQuery,
+    encoding: Encoding,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+    cache: &State<HistoryResponseCache>,
+) -> Validated<Negotiated<AdvancedChartResponse>> {
+    let key = format!("{:?}", query);
+    let store = query.store(&trading_engine);
+    let cached = store.as_ref().and_then(|store| cache.get(&key, store));
+    let (response, version) = match cached {
+        Some(cached) => cached,
+        None => {
+            let revision = store.as_ref().map(|store| store.revision());
+            let built =
+                versioned_history(query.clone(), &trading_engine, &server_config.number_format);
+            if let Some((store, revision)) = store.as_ref().zip(revision) {
+                cache.insert(key, store, revision, built.clone());
+            }
+            built
+        }
+    };
+    // Missing data may still be backfilled, so only bars are cached for long.
+    let policy = match resolution_seconds(query.resolution.as_deref().unwrap_or("60")) {
+        Some(interval) if response.s == "ok" => {
+            cache_control(interval, query.to, chrono::Utc::now())
+        }
+        _ => "no-cache".to_string(),
+    };
+    Validated::new(
+        &(&query, encoding),
+        version,
+        Negotiated::new(encoding, response),
+    )
+    .with_cache_control(policy)
+}
+
+/// Parameters of `/history_batch`: those of `/history`, with comma-separated `symbols`
+/// instead of `symbol`.
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct HistoryBatchQuery {
+    symbols: String,
     resolution: Option<String>,
     from: Option<i64>,
     to: Option<i64>,
     countback: Option<usize>,
-    trading_engine: &State<Arc<TradingEngine>>,
-) -> Json<AdvancedChartResponse> {
+    extended: Option<bool>,
+    volume_in: Option<VolumeIn>,
+    rounding: Option<Rounding>,
+    fill_gaps: Option<bool>,
+    closed_only: Option<bool>,
+    price_source: Option<PriceSource>,
+    candle_type: Option<CandleType>,
+}
+
+impl HistoryBatchQuery {
+    fn for_symbol(&self, symbol: &str) -> HistoryQuery {
+        HistoryQuery {
+            symbol: symbol.to_string(),
+            resolution: self.resolution.clone(),
+            from: self.from,
+            to: self.to,
+            countback: self.countback,
+            extended: self.extended,
+            volume_in: self.volume_in,
+            rounding: self.rounding,
+            fill_gaps: self.fill_gaps,
+            closed_only: self.closed_only,
+            price_source: self.price_source,
+            candle_type: self.candle_type,
+        }
+    }
+}
+
+/// `/history` for up to 50 symbols at once, as a map from symbol to its response, so
+/// dashboards charting many markets need one request.
+#[openapi]
+#[get("/history_batch?<query..>")]
+pub async fn get_history_batch(
+    query: HistoryBatchQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<BTreeMap<String, AdvancedChartResponse>> {
+    let responses = query
+        .symbols
+        .split(',')
+        .map(str::trim)
+        .filter(|symbol| !symbol.is_empty())
+        .take(MAX_BATCH_SYMBOLS)
+        .map(|symbol| {
+            let response = history(
+                query.for_symbol(symbol),
+                &trading_engine,
+                &server_config.number_format,
+            );
+            (symbol.to_string(), response)
+        })
+        .collect();
+    Json(responses)
+}
+
+/// Parameters of `/history_multi`: those of `/history`, with comma-separated
+/// `resolutions` instead of `resolution`.
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct HistoryMultiQuery {
+    symbol: String,
+    resolutions: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    countback: Option<usize>,
+    extended: Option<bool>,
+    volume_in: Option<VolumeIn>,
+    rounding: Option<Rounding>,
+    fill_gaps: Option<bool>,
+    closed_only: Option<bool>,
+    price_source: Option<PriceSource>,
+    candle_type: Option<CandleType>,
+}
+
+impl HistoryMultiQuery {
+    fn for_resolution(&self, resolution: &str) -> HistoryQuery {
+        HistoryQuery {
+            symbol: self.symbol.clone(),
+            resolution: Some(resolution.to_string()),
+            from: self.from,
+            to: self.to,
+            countback: self.countback,
+            extended: self.extended,
+            volume_in: self.volume_in,
+            rounding: self.rounding,
+            fill_gaps: self.fill_gaps,
+            closed_only: self.closed_only,
+            price_source: self.price_source,
+            candle_type: self.candle_type,
+        }
+    }
+}
+
+/// `/history` of one symbol at up to 10 resolutions, as a map from resolution to its
+/// response, for multi-timeframe views. Resolutions that are not stored are aggregated
+/// from a finer stored one.
+#[openapi]
+#[get("/history_multi?<query..>")]
+pub async fn get_history_multi(
+    query: HistoryMultiQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<BTreeMap<String, AdvancedChartResponse>> {
+    let responses = query
+        .resolutions
+        .split(',')
+        .map(str::trim)
+        .filter(|resolution| !resolution.is_empty())
+        .take(MAX_MULTI_RESOLUTIONS)
+        .map(|resolution| {
+            let response = history(
+                query.for_resolution(resolution),
+                &trading_engine,
+                &server_config.number_format,
+            );
+            (resolution.to_string(), response)
+        })
+        .collect();
+    Json(responses)
+}
+
+fn history(
+    query: HistoryQuery,
+    trading_engine: &TradingEngine,
+    number_format: &NumberFormat,
+) -> AdvancedChartResponse {
+    versioned_history(query, trading_engine, number_format).0
+}
+
+/// `/history` and the version of the bars it returns, if any.
+fn versioned_history(
+    query: HistoryQuery,
+    trading_engine: &TradingEngine,
+    number_format: &NumberFormat,
+) -> (AdvancedChartResponse, Option<DataVersion>) {
+    let store = query.store(trading_engine);
+    let HistoryQuery {
+        symbol,
+        resolution,
+        from,
+        to,
+        countback,
+        extended,
+        volume_in,
+        rounding,
+        fill_gaps,
+        closed_only,
+        price_source: _,
+        candle_type,
+    } = query;
     let resolution = resolution.unwrap_or_else(|| "60".to_string());
     let from = from.unwrap_or(0);
     let to = to.unwrap_or(chrono::Utc::now().timestamp());
 
-    let interval = match resolution.as_str() {
-        "1" => 60,
-        "5" => 300,
-        "15" => 900,
-        "30" => 1800,
-        "60" => 3600,
-        "1D" => 86400,
-        "1W" => 604800,
-        _ => {
-            warn!("Unsupported resolution: {}", resolution);
-            return Json(AdvancedChartResponse {
-                s: "error".to_string(),
-                t: vec![],
-                o: vec![],
-                h: vec![],
-                l: vec![],
-                c: vec![],
-                v: vec![],
-            });
-        }
+    let Some(interval) = resolution_seconds(&resolution) else {
+        warn!("Unsupported resolution: {}", resolution);
+        return (AdvancedChartResponse::empty("error"), None);
     };
 
-    if let Some(store) = trading_engine.get_store(&symbol) {
-        let config = trading_engine.configs.get(&symbol);
-        let decimals = config.map(|cfg| cfg.decimals).unwrap_or(9); // Дефолтное значение decimals = 9
-        let divisor = 10u64.pow(decimals as u32) as f64;
+    if let Some(store) = store {
+        let Some(source) = store.source_interval(interval) else {
+            warn!("Resolution {} is not stored for {}", resolution, symbol);
+            return (AdvancedChartResponse::empty("error"), None);
+        };
+        // Unstored resolutions are aggregated from the coarsest stored one dividing them.
+        let ratio = (interval / source) as usize;
+        let formatter = Formatter::new(number_format, trading_engine.config(&symbol).as_ref())
+            .with_rounding(rounding);
 
-        let mut candles = store.get_candles_in_time_range(&symbol, interval, from, to);
+        // TradingView's countback asks for that many bars up to `to`, whatever `from`
+        // says; one more is read when the forming bar may be dropped.
+        let mut candles = match countback {
+            Some(countback) => store.get_candles_until(
+                &symbol,
+                source,
+                to,
+                countback
+                    .saturating_add(closed_only.unwrap_or(false) as usize)
+                    .saturating_add((ratio > 1) as usize)
+                    .saturating_mul(ratio),
+            ),
+            // The first aggregated bar is read whole.
+            None if ratio > 1 => {
+                let from = DateTime::from_timestamp(from, 0)
+                    .map_or(from, |from| period_start(from, interval).timestamp());
+                store.get_candles_in_time_range(&symbol, source, from, to)
+            }
+            None => store.get_candles_in_time_range(&symbol, interval, from, to),
+        };
+        if ratio > 1 {
+            candles = CandleStore::resample(&candles, interval);
+        }
+        if closed_only.unwrap_or(false) {
+            let now = chrono::Utc::now();
+            candles.retain(|c| c.is_closed(interval, now));
+        }
+        if fill_gaps.unwrap_or(false) {
+            let limit = countback.unwrap_or(MAX_SYNTHESIZED_BARS);
+            candles = CandleStore::synthesize_gaps(&candles, interval, limit);
+        }
+        if candle_type == Some(CandleType::HeikinAshi) {
+            candles = CandleStore::heikin_ashi(&candles);
+        }
 
         if let Some(countback) = countback {
             if candles.len() > countback {
@@ -69,64 +418,113 @@ pub async fn get_history(
         }
 
         if candles.is_empty() {
-            return Json(AdvancedChartResponse {
-                s: "no_data".to_string(),
-                t: vec![],
-                o: vec![],
-                h: vec![],
-                l: vec![],
-                c: vec![],
-                v: vec![],
-            });
-        }
-
-        let t: Vec<u64> = candles
-            .iter()
-            .map(|c| c.timestamp.timestamp() as u64)
-            .collect();
-        let o: Vec<f64> = candles.iter().map(|c| c.open / divisor).collect();
-        let h: Vec<f64> = candles.iter().map(|c| c.high / divisor).collect();
-        let l: Vec<f64> = candles.iter().map(|c| c.low / divisor).collect();
-        let c: Vec<f64> = candles.iter().map(|c| c.close / divisor).collect();
-        let v: Vec<f64> = candles.iter().map(|c| c.volume / divisor).collect();
+            return (AdvancedChartResponse::empty("no_data"), None);
+        }
 
-        return Json(AdvancedChartResponse {
-            s: "ok".to_string(),
-            t,
-            o,
-            h,
-            l,
-            c,
-            v,
-        });
+        let response = AdvancedChartResponse::from_candles(
+            &candles,
+            interval,
+            &formatter,
+            extended.unwrap_or(false),
+            volume_in.unwrap_or_default(),
+        );
+        return (response, DataVersion::of(&candles, interval));
     }
 
-    Json(AdvancedChartResponse {
-        s: "error".to_string(),
-        t: vec![],
-        o: vec![],
-        h: vec![],
-        l: vec![],
-        c: vec![],
-        v: vec![],
-    })
+    (AdvancedChartResponse::empty("error"), None)
 }
 
+/// Timestamp of the first bar `/history` can return for `symbol` at `resolution`, so
+/// charts need not probe for the start of the data. `earliest` is null without data.
 #[openapi]
-#[get("/candles?<symbol>&<interval>")]
+#[get("/earliest?<symbol>&<resolution>")]
+pub async fn get_earliest(
+    symbol: String,
+    resolution: String,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(interval) = resolution_seconds(&resolution) else {
+        return Json(json!({ "status": "error", "message": "Unsupported resolution" }));
+    };
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    if !store.stores_interval(interval) {
+        return Json(json!({ "status": "error", "message": "Unsupported resolution" }));
+    }
+    let earliest = store
+        .earliest_candle(&symbol, interval)
+        .map(|timestamp| timestamp.timestamp());
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "resolution": resolution,
+        "earliest": earliest,
+    }))
+}
+
+/// Candles of `symbol` at `interval`, newest first, `limit` per page. `next_cursor` is
+/// set while older candles remain; passing it back as `cursor` returns the candles that
+/// start before it. `candle_type=heikin_ashi` returns Heikin-Ashi bars, seeded from the
+/// oldest bar of the page. Sent as MessagePack to clients that prefer
+/// `application/msgpack`.
+#[openapi]
+#[get(
+    "/candles?<symbol>&<interval>&<volume_in>&<rounding>&<closed_only>&<limit>&<cursor>&<candle_type>"
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_all_candles(
     symbol: String,
     interval: u64,
-    trading_engine: &State<Arc<TradingEngine>>,
-) -> Json<serde_json::Value> {
+    volume_in: Option<VolumeIn>,
+    rounding: Option<Rounding>,
+    closed_only: Option<bool>,
+    limit: Option<usize>,
+    cursor: Option<i64>,
+    candle_type: Option<CandleType>,
+    encoding: Encoding,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Negotiated<serde_json::Value> {
     if let Some(store) = trading_engine.get_store(&symbol) {
-        let candles = store.get_candles(&symbol, interval, usize::MAX);
+        let formatter = Formatter::new(
+            &server_config.number_format,
+            trading_engine.config(&symbol).as_ref(),
+        )
+        .with_rounding(rounding);
+        let volume_in = volume_in.unwrap_or_default();
+
+        let now = chrono::Utc::now();
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let closed_only = closed_only.unwrap_or(false);
+        // One more candle tells whether another page follows, and one more again
+        // stands in for a forming candle that is dropped.
+        let mut candles = store.get_candles_until(
+            &symbol,
+            interval,
+            cursor.map_or(i64::MAX, |cursor| cursor.saturating_sub(1)),
+            limit + 1 + closed_only as usize,
+        );
+        candles.reverse();
+        if closed_only {
+            candles.retain(|c| c.is_closed(interval, now));
+        }
+        let next_cursor = (candles.len() > limit).then(|| candles[limit - 1].timestamp.timestamp());
+        candles.truncate(limit);
+        if candle_type == Some(CandleType::HeikinAshi) {
+            candles.reverse();
+            candles = CandleStore::heikin_ashi(&candles);
+            candles.reverse();
+        }
 
         if candles.is_empty() {
-            return Json(json!({
-                "status": "no_data",
-                "message": format!("No candles found for symbol={}, interval={}", symbol, interval),
-            }));
+            return Negotiated::new(
+                encoding,
+                json!({
+                    "status": "no_data",
+                    "message": format!("No candles found for symbol={}, interval={}", symbol, interval),
+                }),
+            );
         }
 
         let candles_json: Vec<_> = candles
@@ -134,22 +532,35 @@ pub async fn get_all_candles(
             .map(|c| {
                 json!({
                     "timestamp": c.timestamp.timestamp(),
-                    "open": c.open,
-                    "high": c.high,
-                    "low": c.low,
-                    "close": c.close,
-                    "volume": c.volume,
+                    "open": formatter.price(c.open),
+                    "high": formatter.price(c.high),
+                    "low": formatter.price(c.low),
+                    "close": formatter.price(c.close),
+                    "volume": match volume_in {
+                        VolumeIn::Base => formatter.size(c.volume),
+                        VolumeIn::Quote => formatter.quote(c.quote_volume),
+                    },
+                    "trade_count": c.trade_count,
+                    "vwap": c.vwap().map(|vwap| formatter.price(vwap)),
+                    "is_closed": c.is_closed(interval, now),
                 })
             })
             .collect();
 
-        return Json(json!({
-            "status": "ok",
-            "symbol": symbol,
-            "interval": interval,
-            "candles": candles_json,
-        }));
+        return Negotiated::new(
+            encoding,
+            json!({
+                "status": "ok",
+                "symbol": symbol,
+                "interval": interval,
+                "candles": candles_json,
+                "next_cursor": next_cursor,
+            }),
+        );
     }
 
-    Json(json!({ "status": "error", "message": "Symbol not found" }))
+    Negotiated::new(
+        encoding,
+        json!({ "status": "error", "message": "Symbol not found" }),
+    )
 }
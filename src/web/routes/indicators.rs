@@ -0,0 +1,320 @@
+use rocket::serde::json::Json;
+use rocket::{get, FromForm, FromFormField, State};
+use rocket_okapi::openapi;
+use schemars::JsonSchema;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::indicators::{bollinger, ema, macd, rsi, sma};
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// Bars returned without `from`, and the most returned at all.
+const DEFAULT_BARS: i64 = 500;
+const MAX_BARS: usize = 10000;
+const MAX_PERIOD: usize = 1000;
+/// Periods of bars read ahead of the range for indicators built on EMAs, which remember
+/// all earlier bars; a few periods bring them close to the values of the full series.
+const EMA_WARMUP_PERIODS: usize = 4;
+
+/// Rejects periods outside `1..=MAX_PERIOD`, before any warm-up is computed from them.
+fn check_period(period: usize) -> Result<(), Json<serde_json::Value>> {
+    if period == 0 || period > MAX_PERIOD {
+        return Err(Json(
+            json!({ "status": "error", "message": "Invalid period" }),
+        ));
+    }
+    Ok(())
+}
+
+/// The closes of a stored series in `[from, to]` in display units, preceded by up to
+/// `warmup` earlier closes so indicators are defined from the first bar of the range.
+struct Closes {
+    /// Bar starts of the range.
+    t: Vec<i64>,
+    /// Warm-up closes, then those of the range.
+    values: Vec<f64>,
+}
+
+impl Closes {
+    #[allow(clippy::too_many_arguments)]
+    fn read(
+        trading_engine: &Engine,
+        server_config: &ServerConfig,
+        symbol: &str,
+        interval: u64,
+        from: Option<i64>,
+        to: Option<i64>,
+        period: usize,
+        warmup: usize,
+    ) -> Result<Self, Json<serde_json::Value>> {
+        let error = |message: &str| Json(json!({ "status": "error", "message": message }));
+        check_period(period)?;
+        let store = trading_engine
+            .get_store(symbol)
+            .ok_or_else(|| error("Symbol not found"))?;
+        if !store.stores_interval(interval) {
+            return Err(error("Interval not stored"));
+        }
+        let formatter = Formatter::new(
+            &server_config.number_format,
+            trading_engine.config(symbol).as_ref(),
+        );
+        let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+        let from = from.unwrap_or(to.saturating_sub(DEFAULT_BARS * interval as i64));
+        let lookback = (warmup as i64).saturating_mul(interval as i64);
+        let candles =
+            store.get_candles_in_time_range(symbol, interval, from.saturating_sub(lookback), to);
+        let in_range = candles.partition_point(|c| c.timestamp.timestamp() < from);
+        // Long ranges keep their newest bars.
+        let start = in_range.max(candles.len().saturating_sub(MAX_BARS));
+        Ok(Self {
+            t: candles[start..]
+                .iter()
+                .map(|c| c.timestamp.timestamp())
+                .collect(),
+            values: candles[start.saturating_sub(warmup)..]
+                .iter()
+                .map(|c| formatter.price(c.close))
+                .collect(),
+        })
+    }
+
+    /// The values of an indicator over `values` that fall in the range.
+    fn in_range(&self, series: Vec<Option<f64>>) -> Vec<Option<f64>> {
+        series[self.values.len() - self.t.len()..].to_vec()
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField, JsonSchema, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MaType {
+    #[default]
+    Sma,
+    Ema,
+}
+
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct MaQuery {
+    symbol: String,
+    interval: u64,
+    /// Bars averaged over.
+    period: usize,
+    #[field(name = "type")]
+    #[schemars(rename = "type")]
+    kind: Option<MaType>,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// Simple or exponential moving average of the closes of a stored series, one value per
+/// bar of `[from, to]` (the last 500 bars by default). Earlier bars warm the average up,
+/// so values are null only where the pair has less history than `period`.
+#[openapi]
+#[get("/indicators/ma?<query..>")]
+pub async fn get_ma(
+    query: MaQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    if let Err(error) = check_period(query.period) {
+        return error;
+    }
+    let kind = query.kind.unwrap_or_default();
+    let warmup = match kind {
+        MaType::Sma => query.period.saturating_sub(1),
+        MaType::Ema => query.period * EMA_WARMUP_PERIODS,
+    };
+    let closes = match Closes::read(
+        &trading_engine,
+        server_config,
+        &query.symbol,
+        query.interval,
+        query.from,
+        query.to,
+        query.period,
+        warmup,
+    ) {
+        Ok(closes) => closes,
+        Err(error) => return error,
+    };
+    let values = match kind {
+        MaType::Sma => sma(&closes.values, query.period),
+        MaType::Ema => ema(&closes.values, query.period),
+    };
+
+    Json(json!({
+        "status": "ok",
+        "symbol": query.symbol,
+        "interval": query.interval,
+        "type": kind,
+        "period": query.period,
+        "values": closes.in_range(values),
+        "t": closes.t,
+    }))
+}
+
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct RsiQuery {
+    symbol: String,
+    interval: u64,
+    /// Changes averaged over, 14 by default.
+    period: Option<usize>,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// Wilder's relative strength index (0 to 100) of the closes of a stored series, one
+/// value per bar of `[from, to]` (the last 500 bars by default).
+#[openapi]
+#[get("/indicators/rsi?<query..>")]
+pub async fn get_rsi(
+    query: RsiQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let period = query.period.unwrap_or(14);
+    if let Err(error) = check_period(period) {
+        return error;
+    }
+    let closes = match Closes::read(
+        &trading_engine,
+        server_config,
+        &query.symbol,
+        query.interval,
+        query.from,
+        query.to,
+        period,
+        period * EMA_WARMUP_PERIODS,
+    ) {
+        Ok(closes) => closes,
+        Err(error) => return error,
+    };
+    let values = rsi(&closes.values, period);
+
+    Json(json!({
+        "status": "ok",
+        "symbol": query.symbol,
+        "interval": query.interval,
+        "period": period,
+        "values": closes.in_range(values),
+        "t": closes.t,
+    }))
+}
+
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct MacdQuery {
+    symbol: String,
+    interval: u64,
+    /// Period of the fast EMA, 12 by default.
+    fast: Option<usize>,
+    /// Period of the slow EMA, 26 by default.
+    slow: Option<usize>,
+    /// Period of the signal line's EMA, 9 by default.
+    signal: Option<usize>,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// MACD line, signal line and histogram of the closes of a stored series, one value per
+/// bar of `[from, to]` (the last 500 bars by default).
+#[openapi]
+#[get("/indicators/macd?<query..>")]
+pub async fn get_macd(
+    query: MacdQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let (fast, slow, signal) = (
+        query.fast.unwrap_or(12),
+        query.slow.unwrap_or(26),
+        query.signal.unwrap_or(9),
+    );
+    if fast == 0 || fast >= slow || slow > MAX_PERIOD || signal == 0 || signal > MAX_PERIOD {
+        return Json(json!({ "status": "error", "message": "Invalid periods" }));
+    }
+    let closes = match Closes::read(
+        &trading_engine,
+        server_config,
+        &query.symbol,
+        query.interval,
+        query.from,
+        query.to,
+        slow,
+        (slow + signal) * EMA_WARMUP_PERIODS,
+    ) {
+        Ok(closes) => closes,
+        Err(error) => return error,
+    };
+    let lines = macd(&closes.values, fast, slow, signal);
+
+    Json(json!({
+        "status": "ok",
+        "symbol": query.symbol,
+        "interval": query.interval,
+        "fast": fast,
+        "slow": slow,
+        "signal_period": signal,
+        "macd": closes.in_range(lines.macd),
+        "signal": closes.in_range(lines.signal),
+        "histogram": closes.in_range(lines.histogram),
+        "t": closes.t,
+    }))
+}
+
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct BandsQuery {
+    symbol: String,
+    interval: u64,
+    /// Bars of the moving average, 20 by default.
+    period: Option<usize>,
+    /// Distance of the bands from the average in standard deviations, 2 by default.
+    width: Option<f64>,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// Bollinger Bands of the closes of a stored series: the simple moving average and bands
+/// `width` standard deviations above and below it, one value per bar of `[from, to]`
+/// (the last 500 bars by default).
+#[openapi]
+#[get("/indicators/bbands?<query..>")]
+pub async fn get_bbands(
+    query: BandsQuery,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let period = query.period.unwrap_or(20);
+    let width = query.width.unwrap_or(2.0);
+    if !width.is_finite() || width < 0.0 {
+        return Json(json!({ "status": "error", "message": "Invalid width" }));
+    }
+    let closes = match Closes::read(
+        &trading_engine,
+        server_config,
+        &query.symbol,
+        query.interval,
+        query.from,
+        query.to,
+        period,
+        period.saturating_sub(1),
+    ) {
+        Ok(closes) => closes,
+        Err(error) => return error,
+    };
+    let bands = bollinger(&closes.values, period, width);
+
+    Json(json!({
+        "status": "ok",
+        "symbol": query.symbol,
+        "interval": query.interval,
+        "period": period,
+        "width": width,
+        "middle": closes.in_range(bands.middle),
+        "upper": closes.in_range(bands.upper),
+        "lower": closes.in_range(bands.lower),
+        "t": closes.t,
+    }))
+}
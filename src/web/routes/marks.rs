@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::config::server::ServerConfig;
+use crate::storage::interval::{period_start, resolution_seconds};
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// Bars with marks returned at most, the latest kept.
+const MAX_MARKS: usize = 500;
+
+/// Notable trades of one bar.
+struct BarMark {
+    count: usize,
+    /// Size and price of the largest notable trade.
+    largest: (f64, f64),
+}
+
+/// Chart marks for notable trades in `[from, to]`, as the pair's `marks` thresholds
+/// define them, grouped into one mark per bar of `resolution`. Only trades still in the
+/// trade archive are considered.
+#[openapi]
+#[get("/marks?<symbol>&<from>&<to>&<resolution>")]
+pub async fn get_marks(
+    symbol: String,
+    from: i64,
+    to: i64,
+    resolution: Option<String>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let resolution = resolution.unwrap_or_else(|| "60".to_string());
+    let Some(interval) = resolution_seconds(&resolution) else {
+        return Json(json!({ "status": "error", "message": "Unsupported resolution" }));
+    };
+    let config = trading_engine.config(&symbol);
+    let thresholds = config.as_ref().and_then(|config| config.marks);
+    let formatter = Formatter::new(&server_config.number_format, config.as_ref());
+
+    let mut bars: BTreeMap<DateTime<Utc>, BarMark> = BTreeMap::new();
+    if let Some(thresholds) = thresholds {
+        let trades = store.trades.range(
+            &symbol,
+            from.saturating_mul(1000),
+            to.saturating_add(1).saturating_mul(1000),
+        );
+        for trade in trades {
+            let (size, price) = (formatter.size(trade.volume), formatter.price(trade.price));
+            if !thresholds.is_notable(size, price) {
+                continue;
+            }
+            let Some(time) = DateTime::from_timestamp_millis(trade.event_time) else {
+                continue;
+            };
+            let bar = bars.entry(period_start(time, interval)).or_insert(BarMark {
+                count: 0,
+                largest: (size, price),
+            });
+            bar.count += 1;
+            if size > bar.largest.0 {
+                bar.largest = (size, price);
+            }
+        }
+    }
+
+    let bars: Vec<_> = bars.into_iter().rev().take(MAX_MARKS).rev().collect();
+    let text = |bar: &BarMark| {
+        let (size, price) = bar.largest;
+        match bar.count {
+            1 => format!("Large trade: {} at {}", size, price),
+            count => format!("{} large trades, largest {} at {}", count, size, price),
+        }
+    };
+    Json(json!({
+        "id": bars.iter().map(|(time, _)| time.timestamp()).collect::<Vec<_>>(),
+        "time": bars.iter().map(|(time, _)| time.timestamp()).collect::<Vec<_>>(),
+        "color": bars.iter().map(|_| "blue").collect::<Vec<_>>(),
+        "text": bars.iter().map(|(_, bar)| text(bar)).collect::<Vec<_>>(),
+        "label": bars.iter().map(|_| "L").collect::<Vec<_>>(),
+        "labelFontColor": bars.iter().map(|_| "white").collect::<Vec<_>>(),
+        "minSize": bars.iter().map(|_| 14).collect::<Vec<_>>(),
+    }))
+}
@@ -0,0 +1,162 @@
+use rocket::http::ContentType;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::web::deprecation::DeprecationUsage;
+use crate::web::tenant::Engine;
+
+#[openapi]
+#[get("/sla?<symbol>&<within>")]
+pub async fn get_sla(
+    symbol: String,
+    within: Option<f64>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+
+    let within = within.unwrap_or(10.0);
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "latency": store.latency.snapshot(),
+        "within_seconds": within,
+        "share_within": store.latency.share_within(within),
+    }))
+}
+
+/// Prometheus text exposition, scraped by the chart's ServiceMonitor.
+#[get("/metrics")]
+pub async fn get_metrics(
+    trading_engine: Engine,
+    deprecation_usage: &State<Arc<DeprecationUsage>>,
+) -> (ContentType, String) {
+    let mut out = String::new();
+
+    out.push_str(
+        "# HELP spark_candles_event_latency_seconds Delay from block timestamp to candle update.\n",
+    );
+    out.push_str("# TYPE spark_candles_event_latency_seconds histogram\n");
+    let pairs = trading_engine.pairs();
+    let symbols: Vec<_> = pairs
+        .iter()
+        .map(|pair| (&pair.config.symbol, &pair.store))
+        .collect();
+    for (symbol, store) in &symbols {
+        for (bound, total) in store.latency.cumulative() {
+            let le = bound.map_or("+Inf".to_string(), |b| b.to_string());
+            let _ = writeln!(
+                out,
+                "spark_candles_event_latency_seconds_bucket{{symbol=\"{}\",le=\"{}\"}} {}",
+                symbol, le, total
+            );
+        }
+        let _ = writeln!(
+            out,
+            "spark_candles_event_latency_seconds_sum{{symbol=\"{}\"}} {}",
+            symbol,
+            store.latency.sum_seconds()
+        );
+        let _ = writeln!(
+            out,
+            "spark_candles_event_latency_seconds_count{{symbol=\"{}\"}} {}",
+            symbol,
+            store.latency.count()
+        );
+    }
+
+    out.push_str("# HELP spark_candles_memory_bytes Approximate memory held by a pair's store.\n");
+    out.push_str("# TYPE spark_candles_memory_bytes gauge\n");
+    for (symbol, store) in &symbols {
+        let _ = writeln!(
+            out,
+            "spark_candles_memory_bytes{{symbol=\"{}\"}} {}",
+            symbol,
+            store.footprint().bytes
+        );
+    }
+
+    out.push_str(
+        "# HELP spark_candles_evicted_candles_total Candles dropped to stay within the memory budget.\n",
+    );
+    out.push_str("# TYPE spark_candles_evicted_candles_total counter\n");
+    for (symbol, store) in &symbols {
+        let _ = writeln!(
+            out,
+            "spark_candles_evicted_candles_total{{symbol=\"{}\"}} {}",
+            symbol,
+            store.evicted_candles()
+        );
+    }
+
+    out.push_str(
+        "# HELP spark_candles_evicted_trades_total Archived trades dropped to stay within the memory budget.\n",
+    );
+    out.push_str("# TYPE spark_candles_evicted_trades_total counter\n");
+    for (symbol, store) in &symbols {
+        let _ = writeln!(
+            out,
+            "spark_candles_evicted_trades_total{{symbol=\"{}\"}} {}",
+            symbol,
+            store.trades.evicted()
+        );
+    }
+
+    out.push_str(
+        "# HELP spark_candles_quarantined_trades_total Trades kept out of the candles by the price filter.\n",
+    );
+    out.push_str("# TYPE spark_candles_quarantined_trades_total counter\n");
+    for (symbol, store) in &symbols {
+        let _ = writeln!(
+            out,
+            "spark_candles_quarantined_trades_total{{symbol=\"{}\"}} {}",
+            symbol,
+            store.quarantined_trades()
+        );
+    }
+
+    let indexer_status = trading_engine.indexer_status.snapshot();
+    out.push_str(
+        "# HELP spark_candles_reconnects_total Live subscriptions re-established after closing or going stale.\n",
+    );
+    out.push_str("# TYPE spark_candles_reconnects_total counter\n");
+    for (symbol, status) in &indexer_status {
+        let _ = writeln!(
+            out,
+            "spark_candles_reconnects_total{{symbol=\"{}\"}} {}",
+            symbol, status.reconnects
+        );
+    }
+
+    out.push_str(
+        "# HELP spark_candles_reconnect_failures Live subscriptions in a row that ended before applying a block.\n",
+    );
+    out.push_str("# TYPE spark_candles_reconnect_failures gauge\n");
+    for (symbol, status) in &indexer_status {
+        let _ = writeln!(
+            out,
+            "spark_candles_reconnect_failures{{symbol=\"{}\"}} {}",
+            symbol, status.consecutive_failures
+        );
+    }
+
+    out.push_str("# HELP spark_candles_deprecated_requests_total Calls to deprecated routes.\n");
+    out.push_str("# TYPE spark_candles_deprecated_requests_total counter\n");
+    for (route, count) in deprecation_usage.totals() {
+        let _ = writeln!(
+            out,
+            "spark_candles_deprecated_requests_total{{route=\"{}\"}} {}",
+            route, count
+        );
+    }
+
+    (
+        ContentType::new("text", "plain").with_params(("version", "0.0.4")),
+        out,
+    )
+}
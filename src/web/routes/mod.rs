@@ -1,20 +1,105 @@
+pub mod about;
+pub mod admin;
+pub mod averages;
+pub mod binance;
+pub mod bricks;
+pub mod chart;
+pub mod checksum;
 pub mod config;
+pub mod depth;
+pub mod export;
 pub mod history;
+pub mod indicators;
+pub mod marks;
+pub mod metrics;
+pub mod open_interest;
+pub mod quotes;
+pub mod reconcile;
 pub mod search;
+pub mod seasonality;
+pub mod sparkline;
+pub mod status;
+pub mod stream;
 pub mod symbols;
+pub mod ticker;
+pub mod timescale_marks;
+pub mod volume_profile;
+pub mod ws;
 
-use rocket::Route;
+use rocket::{routes, Route};
 use rocket_okapi::{openapi_get_routes, swagger_ui::SwaggerUIConfig};
 
 pub fn get_routes() -> Vec<Route> {
     openapi_get_routes![
+        about::get_about,
+        averages::get_twap,
+        averages::get_vwap,
+        bricks::get_bricks,
+        checksum::get_checksum,
         config::get_config,
         config::get_time,
+        depth::get_depth,
         history::get_history,
+        history::get_history_batch,
+        history::get_history_multi,
         history::get_all_candles,
+        history::get_earliest,
+        indicators::get_bbands,
+        indicators::get_ma,
+        indicators::get_macd,
+        indicators::get_rsi,
+        marks::get_marks,
+        metrics::get_sla,
+        open_interest::get_open_interest,
+        quotes::get_quotes,
+        reconcile::reconcile,
         search::search,
+        seasonality::get_seasonality,
+        sparkline::get_sparkline,
+        status::get_status,
         symbols::get_symbols,
         symbols::get_symbols_meta,
+        symbols::get_symbol_info,
+        ticker::get_stats,
+        ticker::get_ticker,
+        ticker::get_tickers,
+        timescale_marks::get_timescale_marks,
+        volume_profile::get_volume_profile,
+    ]
+}
+
+pub fn get_metrics_routes() -> Vec<Route> {
+    routes![metrics::get_metrics]
+}
+
+pub fn get_chart_routes() -> Vec<Route> {
+    routes![chart::get_chart_png]
+}
+
+pub fn get_export_routes() -> Vec<Route> {
+    routes![export::get_csv, export::get_ndjson, export::get_parquet]
+}
+
+pub fn get_binance_routes() -> Vec<Route> {
+    routes![binance::get_klines, binance::kline_socket]
+}
+
+pub fn get_stream_routes() -> Vec<Route> {
+    routes![stream::stream_candles, ws::candle_socket]
+}
+
+pub fn get_admin_routes() -> Vec<Route> {
+    routes![
+        admin::get_dead_letters,
+        admin::replay_dead_letters,
+        admin::get_replication_delta,
+        admin::promote,
+        admin::get_deprecations,
+        admin::validate,
+        admin::rebuild,
+        admin::import,
+        admin::pause_pair,
+        admin::resume_pair,
     ]
 }
 
@@ -0,0 +1,57 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// Open interest of a perpetual pair per `interval` seconds between `from` and `to`, as
+/// open, high, low and close in base units. `current` is the open interest now.
+#[openapi]
+#[get("/open_interest?<symbol>&<interval>&<from>&<to>")]
+pub async fn get_open_interest(
+    symbol: String,
+    interval: u64,
+    from: Option<i64>,
+    to: Option<i64>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(open_interest) = trading_engine.open_interest(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found or not perpetual" }));
+    };
+    if !open_interest.series.stores_interval(interval) {
+        return Json(json!({ "status": "error", "message": "Interval not stored" }));
+    }
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(chrono::Utc::now().timestamp());
+
+    let series: Vec<_> = open_interest
+        .series
+        .get_candles_in_time_range(&symbol, interval, from, to)
+        .iter()
+        .map(|c| {
+            json!({
+                "timestamp": c.timestamp.timestamp(),
+                "open": formatter.size(c.open),
+                "high": formatter.size(c.high),
+                "low": formatter.size(c.low),
+                "close": formatter.size(c.close),
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "status": if series.is_empty() { "no_data" } else { "ok" },
+        "symbol": symbol,
+        "interval": interval,
+        "current": formatter.size(open_interest.current()),
+        "open_interest": series,
+    }))
+}
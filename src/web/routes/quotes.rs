@@ -0,0 +1,72 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::format::{Formatter, NumberFormat};
+use crate::web::routes::ticker::DayStats;
+use crate::web::tenant::Engine;
+
+/// The UDF quote of one symbol: `{"s": "ok", "n": symbol, "v": values}`.
+fn quote(
+    trading_engine: &TradingEngine,
+    number_format: &NumberFormat,
+    symbol: &str,
+) -> serde_json::Value {
+    let (Some(config), Some(store)) = (
+        trading_engine.config(symbol),
+        trading_engine.get_store(symbol),
+    ) else {
+        return json!({ "s": "error", "n": symbol, "v": {} });
+    };
+    let formatter = Formatter::new(number_format, Some(&config));
+    let stats = DayStats::of(&store, symbol);
+    let (day, last, open) = (stats.window.as_ref(), stats.last, stats.open());
+    let change = stats.change(&formatter);
+    // Bid and ask are left out while the book may still lack older orders.
+    let top = trading_engine
+        .book(symbol)
+        .filter(|book| book.is_complete())
+        .map(|book| book.top());
+
+    json!({
+        "s": "ok",
+        "n": symbol,
+        "v": {
+            "short_name": symbol,
+            "exchange": symbol,
+            "description": config.description,
+            "lp": last.map(|last| formatter.price(last)),
+            "ch": change.map(|(change, _)| change),
+            "chp": change.map(|(_, percent)| percent),
+            "bid": top.and_then(|top| top.bid).map(|bid| formatter.price(bid)),
+            "ask": top.and_then(|top| top.ask).map(|ask| formatter.price(ask)),
+            "open_price": open.map(|open| formatter.price(open)),
+            "high_price": day.map(|day| formatter.price(day.high)),
+            "low_price": day.map(|day| formatter.price(day.low)),
+            "prev_close_price": open.map(|open| formatter.price(open)),
+            "volume": day.map_or(0.0, |day| formatter.size(day.volume)),
+        },
+    })
+}
+
+/// UDF quotes for TradingView watchlists: last price, change over the last 24 hours,
+/// best bid and ask, and the 24-hour high, low and volume of each of the comma-separated
+/// `symbols`. The 24-hour figures start with the first trade of the window.
+#[openapi]
+#[get("/quotes?<symbols>")]
+pub async fn get_quotes(
+    symbols: String,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let quotes: Vec<_> = symbols
+        .split(',')
+        .map(str::trim)
+        .filter(|symbol| !symbol.is_empty())
+        .map(|symbol| quote(&trading_engine, &server_config.number_format, symbol))
+        .collect();
+    Json(json!({ "s": "ok", "d": quotes }))
+}
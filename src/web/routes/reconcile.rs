@@ -0,0 +1,88 @@
+use rocket::get;
+use rocket::serde::json::Json;
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::storage::interval::period_end;
+use crate::web::tenant::Engine;
+
+/// Compares the raw trade archive against candle sums for every interval. Only buckets
+/// lying entirely inside `[from, to)` are checked, against the trades in the same span.
+/// Raw sums are returned as strings since they can exceed the JSON integer range.
+#[openapi]
+#[get("/reconcile?<symbol>&<from>&<to>")]
+pub async fn reconcile(
+    symbol: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(chrono::Utc::now().timestamp());
+
+    let mut discrepancies = 0;
+    let intervals: Vec<_> = store
+        .intervals()
+        .into_iter()
+        .map(|interval| {
+            let candles: Vec<_> = store
+                .get_candles_in_time_range(&symbol, interval, from, to)
+                .into_iter()
+                .filter(|c| period_end(c.timestamp, interval).timestamp() <= to)
+                .collect();
+            let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+                return json!({ "interval": interval, "candles": 0 });
+            };
+            let span_from = first.timestamp.timestamp();
+            let span_to = period_end(last.timestamp, interval).timestamp();
+
+            let trades = store
+                .trades
+                .range(&symbol, span_from * 1000, span_to * 1000);
+            let archive_volume: u128 = trades.iter().map(|t| t.volume).sum();
+            let archive_quote_volume: u128 = trades
+                .iter()
+                .map(|t| t.price.saturating_mul(t.volume))
+                .sum();
+            let candle_volume: u128 = candles.iter().map(|c| c.volume).sum();
+            let candle_quote_volume: u128 = candles.iter().map(|c| c.quote_volume).sum();
+            let candle_trades: u64 = candles.iter().map(|c| c.trade_count).sum();
+
+            let matches = archive_volume == candle_volume
+                && archive_quote_volume == candle_quote_volume
+                && trades.len() as u64 == candle_trades;
+            if !matches {
+                discrepancies += 1;
+            }
+
+            json!({
+                "interval": interval,
+                "from": span_from,
+                "to": span_to,
+                "candles": candles.len(),
+                "archive_complete": store.trades.covers(&symbol, span_from * 1000),
+                "archive_trades": trades.len(),
+                "candle_trades": candle_trades,
+                "archive_volume": archive_volume.to_string(),
+                "candle_volume": candle_volume.to_string(),
+                "volume_diff": (archive_volume as i128 - candle_volume as i128).to_string(),
+                "archive_quote_volume": archive_quote_volume.to_string(),
+                "candle_quote_volume": candle_quote_volume.to_string(),
+                "matches": matches,
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "from": from,
+        "to": to,
+        "discrepancies": discrepancies,
+        "intervals": intervals,
+    }))
+}
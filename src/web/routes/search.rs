@@ -1,11 +1,30 @@
+use rocket::get;
 use rocket::serde::json::Json;
-use rocket::{get, State};
 use rocket_okapi::openapi;
 use serde_json::json;
-use std::sync::Arc;
 
-use crate::storage::trading_engine::TradingEngine;
+use crate::storage::trading_engine::TradingPairConfig;
+use crate::web::tenant::Engine;
 
+/// How well a pair matches a lower-cased query, best first; `None` when it does not.
+fn relevance(config: &TradingPairConfig, query: &str) -> Option<u8> {
+    let symbol = config.symbol.to_lowercase();
+    if symbol == query {
+        Some(0)
+    } else if symbol.starts_with(query) {
+        Some(1)
+    } else if symbol.contains(query) {
+        Some(2)
+    } else if config.description.to_lowercase().contains(query) {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// UDF symbol search: pairs whose symbol or description contains `query`, exact and
+/// prefix symbol matches first, filtered by the `type` and `exchange` the pairs are
+/// configured with.
 #[openapi]
 #[get("/search?<query>&<type_>&<exchange>&<limit>")]
 pub async fn search(
@@ -13,31 +32,37 @@ pub async fn search(
     type_: Option<String>,
     exchange: Option<String>,
     limit: Option<usize>,
-    trading_engine: &State<Arc<TradingEngine>>,
+    trading_engine: Engine,
 ) -> Json<serde_json::Value> {
-    let configs = &trading_engine.configs;
+    let pairs = trading_engine.pairs();
 
     let query = query.unwrap_or_default().to_lowercase();
     let type_ = type_.unwrap_or_default();
     let exchange = exchange.unwrap_or_default();
     let limit = limit.unwrap_or(30);
 
-    let results: Vec<_> = configs
-        .values()
+    let mut matches: Vec<_> = pairs
+        .iter()
+        .map(|pair| &pair.config)
         .filter(|config| {
-            (config.symbol.to_lowercase().contains(&query)
-                || config.description.to_lowercase().contains(&query))
-                && (type_.is_empty() || type_ == "crypto")
-                && (exchange.is_empty() || exchange == "CryptoExchange")
+            (type_.is_empty() || type_ == config.symbol_type())
+                && (exchange.is_empty() || exchange == config.exchange())
         })
+        .filter_map(|config| Some((relevance(config, &query)?, config)))
+        .collect();
+    // Pairs come sorted by symbol, which the stable sort keeps within a rank.
+    matches.sort_by_key(|(rank, _)| *rank);
+
+    let results: Vec<_> = matches
+        .into_iter()
         .take(limit)
-        .map(|config| {
+        .map(|(_, config)| {
             json!({
                 "symbol": config.symbol,
-                "full_name": format!("CryptoExchange:{}", config.symbol),
+                "full_name": format!("{}:{}", config.exchange(), config.symbol),
                 "description": config.description,
-                "exchange": "CryptoExchange",
-                "type": "crypto"
+                "exchange": config.exchange(),
+                "type": config.symbol_type(),
             })
         })
         .collect();
@@ -0,0 +1,119 @@
+use chrono::{Datelike, Duration, DurationRound, Timelike, Utc};
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+const HOUR: u64 = 3600;
+const DEFAULT_DAYS: u32 = 30;
+const MAX_DAYS: u32 = 365;
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Sums for one bucket. Hours without trades count towards the volume average as zero
+/// but are left out of the volatility average, which has nothing to measure there.
+#[derive(Default, Clone, Copy)]
+struct Bucket {
+    hours: u32,
+    volume: f64,
+    traded_hours: u32,
+    range: f64,
+}
+
+impl Bucket {
+    /// `hours_per_sample` is the span the volume is averaged over: 1 for an hour of the
+    /// day, 24 for a day of the week. A window starting mid-day covers part of a day,
+    /// which is weighted by the hours it has.
+    fn to_json(self, hours_per_sample: u32) -> serde_json::Value {
+        let samples = self.hours as f64 / hours_per_sample as f64;
+        json!({
+            "avg_volume": if self.hours == 0 { 0.0 } else { self.volume / samples },
+            "avg_volatility": if self.traded_hours == 0 { 0.0 } else { self.range / self.traded_hours as f64 },
+            "samples": samples,
+        })
+    }
+}
+
+/// Trading activity by hour of day and day of week (UTC) over the last `days` full
+/// hours, from the hourly candles. Volume is the average per hour for `hour_of_day` and
+/// per day for `day_of_week`; volatility is the average hourly high-low range relative
+/// to the low.
+#[openapi]
+#[get("/seasonality?<symbol>&<days>")]
+pub async fn get_seasonality(
+    symbol: String,
+    days: Option<u32>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let days = days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+
+    let to = Utc::now().duration_trunc(Duration::hours(1)).unwrap();
+    let from = to - Duration::days(days as i64);
+    let candles =
+        store.get_candles_in_time_range(&symbol, HOUR, from.timestamp(), to.timestamp() - 1);
+
+    let mut by_hour = [Bucket::default(); 24];
+    let mut by_weekday = [Bucket::default(); 7];
+    let mut hour = from;
+    while hour < to {
+        by_hour[hour.hour() as usize].hours += 1;
+        by_weekday[hour.weekday().num_days_from_monday() as usize].hours += 1;
+        hour += Duration::hours(1);
+    }
+    for candle in candles.iter().filter(|c| !c.is_gap()) {
+        let volume = formatter.size(candle.volume);
+        let range = if candle.low == 0 {
+            0.0
+        } else {
+            (candle.high - candle.low) as f64 / candle.low as f64
+        };
+        for bucket in [
+            &mut by_hour[candle.timestamp.hour() as usize],
+            &mut by_weekday[candle.timestamp.weekday().num_days_from_monday() as usize],
+        ] {
+            bucket.volume += volume;
+            bucket.traded_hours += 1;
+            bucket.range += range;
+        }
+    }
+
+    let hour_of_day: Vec<_> = by_hour
+        .iter()
+        .enumerate()
+        .map(|(hour, bucket)| {
+            let mut value = bucket.to_json(1);
+            value["hour"] = json!(hour);
+            value
+        })
+        .collect();
+    let day_of_week: Vec<_> = by_weekday
+        .iter()
+        .zip(WEEKDAYS)
+        .map(|(bucket, day)| {
+            let mut value = bucket.to_json(24);
+            value["day"] = json!(day);
+            value
+        })
+        .collect();
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "days": days,
+        "from": from.timestamp(),
+        "to": to.timestamp(),
+        "hour_of_day": hour_of_day,
+        "day_of_week": day_of_week,
+    }))
+}
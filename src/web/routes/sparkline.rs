@@ -0,0 +1,75 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::storage::interval::window_seconds;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+const DEFAULT_POINTS: usize = 50;
+const MAX_POINTS: usize = 1000;
+
+/// `points` closes (50 by default) evenly spaced over the last `window` (`24h` by
+/// default, e.g. `1h`, `7d`), for the mini-charts of list views. Each point is the last
+/// close at the end of its step, read from the coarsest stored interval that fits a
+/// step; points before the pair's first trade are null.
+#[openapi]
+#[get("/sparkline?<symbol>&<points>&<window>")]
+pub async fn get_sparkline(
+    symbol: String,
+    points: Option<usize>,
+    window: Option<String>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let Some(window) = window_seconds(window.as_deref().unwrap_or("24h")) else {
+        return Json(json!({ "status": "error", "message": "Invalid window" }));
+    };
+    let points = points.unwrap_or(DEFAULT_POINTS).clamp(1, MAX_POINTS) as u64;
+    let step = window.div_ceil(points);
+    let intervals = store.intervals();
+    let interval = intervals
+        .iter()
+        .copied()
+        .filter(|&interval| interval <= step)
+        .max()
+        .unwrap_or(intervals[0]);
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+
+    let to = chrono::Utc::now().timestamp();
+    let from = to - (step * points) as i64;
+    let candles = store.get_candles_in_time_range(&symbol, interval, from, to);
+    // Gap candles repeat the previous close, so the close before the window carries into
+    // its first steps.
+    let mut close = store
+        .get_candles_until(&symbol, interval, from - 1, 1)
+        .last()
+        .map(|c| c.close);
+    let mut candles = candles.iter().peekable();
+    let closes: Vec<_> = (1..=points)
+        .map(|point| {
+            let end = from + (step * point) as i64;
+            while let Some(candle) = candles.next_if(|c| c.timestamp.timestamp() < end) {
+                close = Some(candle.close);
+            }
+            close.map(|close| formatter.price(close))
+        })
+        .collect();
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "from": from,
+        "to": to,
+        "step": step,
+        "closes": closes,
+    }))
+}
@@ -0,0 +1,32 @@
+use rocket::get;
+use rocket::serde::json::Json;
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::web::tenant::Engine;
+
+/// Indexer task state per pair: whether it runs or waits to be restarted, how often it
+/// failed and with what error, how far its backfill got and how many blocks it
+/// trails the chain head by. Pairs without a task, e.g. on a standby, are left out.
+#[openapi]
+#[get("/status")]
+pub async fn get_status(trading_engine: Engine) -> Json<serde_json::Value> {
+    let pairs: Vec<_> = trading_engine
+        .indexer_status
+        .snapshot()
+        .into_iter()
+        .map(|(symbol, status)| {
+            let blocks = trading_engine
+                .get_store(&symbol)
+                .map(|store| (store.last_block(), store.chain_head()));
+            let mut value = json!(status);
+            value["symbol"] = json!(symbol);
+            value["last_block"] = json!(blocks.map(|(last_block, _)| last_block));
+            value["head_block"] = json!(blocks.map(|(_, head)| head));
+            value["lag_blocks"] =
+                json!(blocks.map(|(last_block, head)| (head - last_block).max(0)));
+            value
+        })
+        .collect();
+    Json(json!({ "status": "ok", "pairs": pairs }))
+}
@@ -0,0 +1,94 @@
+use rocket::http::Status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::{get, Shutdown, State};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep_until, Duration, Instant};
+
+use crate::config::server::ServerConfig;
+use crate::storage::candle_events::CandleEvent;
+use crate::storage::candles::CandleStore;
+use crate::storage::interval::resolution_seconds;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// The newest candle as a `candle` event; heartbeats repeat it with a fresh `updated_at`.
+fn candle_event(
+    store: &CandleStore,
+    symbol: &str,
+    resolution: &str,
+    interval: u64,
+    formatter: &Formatter,
+    heartbeat: bool,
+) -> Option<Event> {
+    let candle = store.get_candles(symbol, interval, 1).into_iter().next()?;
+    let now = chrono::Utc::now();
+    let data = json!({
+        "symbol": symbol,
+        "resolution": resolution,
+        "t": candle.timestamp.timestamp(),
+        "o": formatter.price(candle.open),
+        "h": formatter.price(candle.high),
+        "l": formatter.price(candle.low),
+        "c": formatter.price(candle.close),
+        "v": formatter.size(candle.volume),
+        "is_closed": candle.is_closed(interval, now),
+        "updated_at": now.timestamp(),
+        "heartbeat": heartbeat,
+    });
+    Some(Event::json(&data).event("candle"))
+}
+
+/// Server-sent events carrying the newest candle of `symbol` after every update. With
+/// `heartbeat_secs` configured, quiet periods are filled with heartbeats.
+#[get("/stream?<symbol>&<resolution>")]
+pub async fn stream_candles(
+    symbol: String,
+    resolution: String,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![], Status> {
+    let store = trading_engine.get_store(&symbol).ok_or(Status::NotFound)?;
+    let interval = resolution_seconds(&resolution)
+        .filter(|&interval| store.stores_interval(interval))
+        .ok_or(Status::BadRequest)?;
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let heartbeat = server_config
+        .heartbeat_secs
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs);
+    let mut events = trading_engine.candle_events.subscribe();
+
+    Ok(EventStream! {
+        let event = |heartbeat| {
+            candle_event(&store, &symbol, &resolution, interval, &formatter, heartbeat)
+        };
+        if let Some(event) = event(false) {
+            yield event;
+        }
+
+        let mut heartbeat_at = heartbeat.map(|every| Instant::now() + every);
+        loop {
+            let is_heartbeat = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(CandleEvent::Updated { symbol: updated, interval: level, .. })
+                        if updated == symbol && level == interval => false,
+                    Ok(_) => continue,
+                    // Missed updates are covered by the candle as it stands now.
+                    Err(RecvError::Lagged(_)) => false,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = sleep_until(heartbeat_at.unwrap_or_else(Instant::now)), if heartbeat_at.is_some() => true,
+                _ = &mut shutdown => break,
+            };
+            heartbeat_at = heartbeat.map(|every| Instant::now() + every);
+            if let Some(event) = event(is_heartbeat) {
+                yield event;
+            }
+        }
+    })
+}
@@ -1,38 +1,39 @@
+use rocket::get;
 use rocket::serde::json::Json;
-use rocket::{get, State};
 use rocket_okapi::openapi;
 use serde_json::json;
-use std::sync::Arc;
 
-use crate::storage::trading_engine::TradingEngine;
+use crate::web::tenant::Engine;
 
 #[openapi]
 #[get("/symbols?<symbol>")]
 pub async fn get_symbols(
     symbol: Option<String>,
-    trading_engine: &State<Arc<TradingEngine>>,
+    trading_engine: Engine,
 ) -> Json<serde_json::Value> {
     if let Some(symbol) = symbol {
-        if let Some(config) = trading_engine.configs.get(&symbol) {
-            let symbol_data = json!({
+        if let Some(config) = trading_engine.config(&symbol) {
+            let activity = trading_engine.activity(&symbol);
+            let mut symbol_data = json!({
                 "symbol": config.symbol,
                 "ticker": config.symbol,
                 "name": config.symbol,
                 "description": config.symbol,
-                "type_": "crypto",
-                "exchange": config.symbol,
+                "type_": config.symbol_type(),
+                "exchange": config.exchange(),
                 "timezone": "UTC",
                 "minmov": 1,
                 "pricescale": 100,
                 "session": "0000-2400",
-                "has_intraday": true,
-                "has_daily": true,
-                "supported_resolutions": ["1", "5", "15", "30", "60", "D", "W", "M"],
-                "intraday_multipliers": ["1", "5", "15", "30", "60"],
-                "default_resolution": "D",
                 "pricescale": 100000,
-                "format": "price"
+                "format": "price",
+                "inactive": activity.map(|a| a.inactive),
+                "last_trade_at": activity.and_then(|a| a.last_trade_at),
             });
+            symbol_data
+                .as_object_mut()
+                .unwrap()
+                .extend(config.resolution_info());
             return Json(symbol_data);
         } else {
             return Json(json!({ "status": "error", "message": "Symbol not found" }));
@@ -48,9 +49,54 @@ pub async fn get_symbols(
 
 #[openapi]
 #[get("/symbols_meta")]
-pub async fn get_symbols_meta(
-    trading_engine: &State<Arc<TradingEngine>>,
-) -> Json<serde_json::Value> {
+pub async fn get_symbols_meta(trading_engine: Engine) -> Json<serde_json::Value> {
     let symbols_meta = trading_engine.get_symbols_meta();
     Json(json!({ "status": "ok", "metadata": symbols_meta }))
 }
+
+/// UDF group fields and the `/symbols` field each is taken from.
+const GROUP_FIELDS: [(&str, &str); 17] = [
+    ("symbol", "symbol"),
+    ("ticker", "ticker"),
+    ("description", "description"),
+    ("exchange-listed", "exchange"),
+    ("exchange-traded", "exchange"),
+    ("type", "type_"),
+    ("timezone", "timezone"),
+    ("session-regular", "session"),
+    ("minmovement", "minmov"),
+    ("pricescale", "pricescale"),
+    ("has-seconds", "has_seconds"),
+    ("seconds-multipliers", "seconds_multipliers"),
+    ("has-intraday", "has_intraday"),
+    ("intraday-multipliers", "intraday_multipliers"),
+    ("has-daily", "has_daily"),
+    ("has-weekly-and-monthly", "has_weekly_and_monthly"),
+    ("supported-resolutions", "supported_resolutions"),
+];
+
+/// UDF group request: the symbol info of every pair of the `group` exchange, one array
+/// per field with an entry per symbol, so TradingView loads all metadata at once.
+#[openapi]
+#[get("/symbol_info?<group>")]
+pub async fn get_symbol_info(group: String, trading_engine: Engine) -> Json<serde_json::Value> {
+    let symbols: Vec<_> = trading_engine
+        .get_symbols()
+        .into_iter()
+        .filter(|symbol| symbol["exchange"] == group.as_str())
+        .collect();
+    if symbols.is_empty() {
+        return Json(json!({ "s": "error", "errmsg": "Unknown group" }));
+    }
+
+    let mut info = serde_json::Map::new();
+    for (field, source) in GROUP_FIELDS {
+        let values: Vec<_> = symbols
+            .iter()
+            .map(|symbol| symbol[source].clone())
+            .collect();
+        info.insert(field.to_string(), json!(values));
+    }
+    info.insert("minmovement2".to_string(), json!(vec![0; symbols.len()]));
+    Json(serde_json::Value::Object(info))
+}
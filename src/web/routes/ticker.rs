@@ -0,0 +1,164 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::storage::candles::{Candle, CandleStore};
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// Candles of the forming bar in `/ticker` without an `interval`.
+const DEFAULT_INTERVAL: u64 = 60;
+const DAY: u64 = 86400;
+
+/// The trades of a pair over the last 24 hours, and its last price, behind every
+/// 24-hour figure the API serves.
+pub(crate) struct DayStats {
+    /// `None` without trades in the window.
+    pub window: Option<Candle>,
+    pub last: Option<u128>,
+}
+
+impl DayStats {
+    pub fn of(store: &CandleStore, symbol: &str) -> Self {
+        Self {
+            window: store.rolling_window(symbol, DAY, chrono::Utc::now()),
+            last: store.last_price(symbol),
+        }
+    }
+
+    /// Without trades in the last day the price has not changed.
+    pub fn open(&self) -> Option<u128> {
+        self.window.as_ref().map(|day| day.open).or(self.last)
+    }
+
+    /// Change of the last price over the window, in price units and in percent.
+    pub fn change(&self, formatter: &Formatter) -> Option<(f64, f64)> {
+        let (last, open) = self.last.zip(self.open())?;
+        let change = formatter.price(last) - formatter.price(open);
+        if open == 0 {
+            return Some((change, 0.0));
+        }
+        Some((change, change / formatter.price(open) * 100.0))
+    }
+
+    pub fn change_percent(&self, formatter: &Formatter) -> Option<f64> {
+        self.change(formatter).map(|(_, percent)| percent)
+    }
+}
+
+/// Last trade price and time of a pair, with the bar of `interval` seconds (a minute by
+/// default) still forming, for price headers that need no history. `candle` is null
+/// when nothing traded in the current period. `first_trade` (in seconds) and
+/// `listing_date` are those of `/symbols_meta`.
+#[openapi]
+#[get("/ticker?<symbol>&<interval>")]
+pub async fn get_ticker(
+    symbol: String,
+    interval: Option<u64>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let interval = interval.unwrap_or(DEFAULT_INTERVAL);
+    if !store.stores_interval(interval) {
+        return Json(json!({ "status": "error", "message": "Interval not stored" }));
+    }
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let now = chrono::Utc::now();
+    let first_trade = store.first_trade();
+    let forming = store
+        .get_candles(&symbol, interval, 1)
+        .pop()
+        .filter(|c| !c.is_closed(interval, now) && !c.is_gap());
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "last_price": store.last_price(&symbol).map(|price| formatter.price(price)),
+        "last_trade_at": store.last_trade_at().map(|at| at / 1000),
+        "first_trade": first_trade.map(|first| first.event_time / 1000),
+        "listing_date": first_trade.and_then(|first| first.listing_date()),
+        "candle": forming.map(|c| json!({
+            "interval": interval,
+            "timestamp": c.timestamp.timestamp(),
+            "open": formatter.price(c.open),
+            "high": formatter.price(c.high),
+            "low": formatter.price(c.low),
+            "close": formatter.price(c.close),
+            "volume": formatter.size(c.volume),
+            "trade_count": c.trade_count,
+        })),
+    }))
+}
+
+/// Open, high, low, close, volume and percent change of a pair over the last 24 hours,
+/// the daily stats block of exchange UIs. The window starts with its first trade; without
+/// trades in it, open and close are the last price and high and low are null.
+#[openapi]
+#[get("/stats?<symbol>")]
+pub async fn get_stats(
+    symbol: String,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let day = DayStats::of(&store, &symbol);
+    let window = day.window.as_ref();
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "open": day.open().map(|open| formatter.price(open)),
+        "high": window.map(|window| formatter.price(window.high)),
+        "low": window.map(|window| formatter.price(window.low)),
+        "close": day.last.map(|last| formatter.price(last)),
+        "volume": window.map_or(0.0, |window| formatter.size(window.volume)),
+        "quote_volume": window.map_or(0.0, |window| formatter.quote(window.quote_volume)),
+        "trade_count": window.map_or(0, |window| window.trade_count),
+        "change_percent": day.change_percent(&formatter),
+    }))
+}
+
+/// Last price, 24-hour percent change and 24-hour volume of every pair in one call, for
+/// market overview tables, with the `inactive` flag of `/symbols`.
+#[openapi]
+#[get("/tickers")]
+pub async fn get_tickers(
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let tickers: Vec<_> = trading_engine
+        .pairs()
+        .iter()
+        .map(|pair| {
+            let symbol = &pair.config.symbol;
+            let formatter = Formatter::new(&server_config.number_format, Some(&pair.config));
+            let day = DayStats::of(&pair.store, symbol);
+            let window = day.window.as_ref();
+            let activity = trading_engine.activity(symbol);
+            json!({
+                "symbol": symbol,
+                "last_price": day.last.map(|last| formatter.price(last)),
+                "change_percent": day.change_percent(&formatter),
+                "volume": window.map_or(0.0, |window| formatter.size(window.volume)),
+                "quote_volume": window.map_or(0.0, |window| formatter.quote(window.quote_volume)),
+                "inactive": activity.map(|a| a.inactive),
+            })
+        })
+        .collect();
+
+    Json(json!({ "status": "ok", "tickers": tickers }))
+}
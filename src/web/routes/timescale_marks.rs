@@ -0,0 +1,64 @@
+use rocket::get;
+use rocket::serde::json::Json;
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::storage::timeline::OperationalKind;
+use crate::web::tenant::Engine;
+
+/// Color and label letter of a timescale mark.
+fn style(kind: OperationalKind) -> (&'static str, &'static str) {
+    match kind {
+        OperationalKind::Paused => ("red", "P"),
+        OperationalKind::Resumed => ("green", "R"),
+        OperationalKind::IndexerRestart => ("orange", "E"),
+        OperationalKind::LiveInterrupted => ("red", "I"),
+        OperationalKind::LiveRecovered => ("green", "R"),
+    }
+}
+
+/// Timescale marks in `[from, to]` giving the chart operational context: the pair's
+/// first trade, plus pauses, indexer restarts and live-stream interruptions since the
+/// process started. The `resolution` TradingView sends is not needed.
+#[openapi]
+#[get("/timescale_marks?<symbol>&<from>&<to>")]
+pub async fn get_timescale_marks(
+    symbol: String,
+    from: i64,
+    to: i64,
+    trading_engine: Engine,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+
+    let mut marks = Vec::new();
+    if let Some(first_trade) = store.first_trade() {
+        let at = first_trade.event_time / 1000;
+        if (from..=to).contains(&at) {
+            marks.push(json!({
+                "id": "listing",
+                "time": at,
+                "color": "blue",
+                "label": "L",
+                "tooltip": [format!("First trade in block {}", first_trade.block)],
+            }));
+        }
+    }
+    for (index, event) in trading_engine
+        .timeline
+        .range(&symbol, from, to)
+        .into_iter()
+        .enumerate()
+    {
+        let (color, label) = style(event.kind);
+        marks.push(json!({
+            "id": format!("{}-{}", event.at, index),
+            "time": event.at,
+            "color": color,
+            "label": label,
+            "tooltip": [event.detail],
+        }));
+    }
+    Json(json!(marks))
+}
@@ -0,0 +1,135 @@
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::config::server::ServerConfig;
+use crate::web::format::Formatter;
+use crate::web::tenant::Engine;
+
+/// Seconds covered without `from`.
+const DEFAULT_SPAN: i64 = 86400;
+const DEFAULT_BUCKETS: usize = 50;
+const MAX_BUCKETS: usize = 1000;
+
+/// Traded volume by price level over `[from, to]` (the last 24 hours by default), in
+/// `buckets` (50 by default) equal levels from the lowest to the highest price of the
+/// range. Built from raw trades while the archive holds every trade of the range, and
+/// otherwise from the pair's base candles, each spreading its volume evenly from its
+/// low to its high. `poc`, the point of control, is the middle of the level
+/// with the most volume.
+#[openapi]
+#[get("/volume_profile?<symbol>&<from>&<to>&<buckets>")]
+pub async fn get_volume_profile(
+    symbol: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    buckets: Option<usize>,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+) -> Json<serde_json::Value> {
+    let Some(store) = trading_engine.get_store(&symbol) else {
+        return Json(json!({ "status": "error", "message": "Symbol not found" }));
+    };
+    let formatter = Formatter::new(
+        &server_config.number_format,
+        trading_engine.config(&symbol).as_ref(),
+    );
+    let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = from.unwrap_or(to - DEFAULT_SPAN);
+    let buckets = buckets.unwrap_or(DEFAULT_BUCKETS).clamp(1, MAX_BUCKETS);
+
+    // Each span trades its volume evenly from its low to its high price; a trade's
+    // span is a single price.
+    let (spans, source): (Vec<(f64, f64, f64)>, _) = match store.archived_trades(&symbol, from, to)
+    {
+        Some(trades) => (
+            trades
+                .iter()
+                .map(|trade| {
+                    let price = formatter.price(trade.price);
+                    (price, price, formatter.size(trade.volume))
+                })
+                .collect(),
+            "trades",
+        ),
+        None => (
+            store
+                .get_candles_in_time_range(&symbol, store.base_interval(), from, to)
+                .iter()
+                .filter(|c| !c.is_gap())
+                .map(|c| {
+                    (
+                        formatter.price(c.low),
+                        formatter.price(c.high),
+                        formatter.size(c.volume),
+                    )
+                })
+                .collect(),
+            "candles",
+        ),
+    };
+    if spans.is_empty() {
+        return Json(json!({
+            "status": "no_data",
+            "message": format!("No trades found for symbol={} in [{}, {}]", symbol, from, to),
+        }));
+    }
+
+    let low = spans
+        .iter()
+        .map(|span| span.0)
+        .fold(f64::INFINITY, f64::min);
+    let high = spans
+        .iter()
+        .map(|span| span.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let step = (high - low) / buckets as f64;
+    let bucket = |price: f64| {
+        if step > 0.0 {
+            (((price - low) / step) as usize).min(buckets - 1)
+        } else {
+            0
+        }
+    };
+    let mut volumes = vec![0.0; buckets];
+    for &(span_low, span_high, volume) in &spans {
+        let (first, last) = (bucket(span_low), bucket(span_high));
+        if first == last {
+            volumes[first] += volume;
+            continue;
+        }
+        for (i, level) in volumes.iter_mut().enumerate().take(last + 1).skip(first) {
+            let overlap = (low + step * (i + 1) as f64).min(span_high)
+                - (low + step * i as f64).max(span_low);
+            *level += volume * overlap.max(0.0) / (span_high - span_low);
+        }
+    }
+
+    let poc = (0..buckets)
+        .max_by(|&a, &b| volumes[a].total_cmp(&volumes[b]))
+        .map(|i| low + step * (i as f64 + 0.5));
+    let levels: Vec<_> = volumes
+        .iter()
+        .enumerate()
+        .map(|(i, volume)| {
+            json!({
+                "low": low + step * i as f64,
+                "high": low + step * (i + 1) as f64,
+                "volume": volume,
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "status": "ok",
+        "symbol": symbol,
+        "from": from,
+        "to": to,
+        "source": source,
+        "low": low,
+        "high": high,
+        "poc": poc,
+        "levels": levels,
+    }))
+}
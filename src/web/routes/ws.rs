@@ -0,0 +1,188 @@
+use rocket::{get, Shutdown, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::config::server::ServerConfig;
+use crate::storage::candles::Candle;
+use crate::storage::interval::resolution_seconds;
+use crate::storage::trading_engine::TradingEngine;
+use crate::web::format::{Formatter, NumberFormat};
+use crate::web::socket::{BarFollower, Outbox, Session, Subscriptions, Upgrade, WsHandshake};
+use crate::web::tenant::Engine;
+
+/// What a client sends over `/ws`, as JSON text frames.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsRequest {
+    Subscribe { symbol: String, resolution: String },
+    Unsubscribe { symbol: String, resolution: String },
+}
+
+/// A bar in the units of the REST endpoints; `t` is the bar's open in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsBar {
+    pub t: i64,
+    pub o: f64,
+    pub h: f64,
+    pub l: f64,
+    pub c: f64,
+    pub v: f64,
+}
+
+impl WsBar {
+    fn new(candle: &Candle, formatter: &Formatter) -> Self {
+        Self {
+            t: candle.timestamp.timestamp(),
+            o: formatter.price(candle.open),
+            h: formatter.price(candle.high),
+            l: formatter.price(candle.low),
+            c: formatter.price(candle.close),
+            v: formatter.size(candle.volume),
+        }
+    }
+}
+
+/// What the server sends over `/ws`, as JSON text frames.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsMessage {
+    Subscribed {
+        symbol: String,
+        resolution: String,
+    },
+    Unsubscribed {
+        symbol: String,
+        resolution: String,
+    },
+    /// The newest bar, sent on subscribing and after every change to it.
+    Bar {
+        symbol: String,
+        resolution: String,
+        bar: WsBar,
+    },
+    /// The final state of a bar, sent once the next bar has opened.
+    BarClose {
+        symbol: String,
+        resolution: String,
+        bar: WsBar,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A `/ws` connection's subscriptions.
+pub struct CandleSession {
+    trading_engine: Arc<TradingEngine>,
+    number_format: NumberFormat,
+    subscriptions: Subscriptions<(String, String)>,
+}
+
+impl Session for CandleSession {
+    fn handle(&mut self, text: &str, outbox: &Outbox) -> Option<Message> {
+        let reply = match serde_json::from_str(text) {
+            Ok(WsRequest::Subscribe { symbol, resolution }) => {
+                self.subscribe(symbol, resolution, outbox)
+            }
+            Ok(WsRequest::Unsubscribe { symbol, resolution }) => {
+                let key = (symbol, resolution);
+                self.subscriptions.remove(&key);
+                let (symbol, resolution) = key;
+                Some(WsMessage::Unsubscribed { symbol, resolution })
+            }
+            Err(e) => Some(WsMessage::Error {
+                message: format!("Invalid request: {}", e),
+            }),
+        };
+        reply.map(|reply| frame(&reply))
+    }
+}
+
+impl CandleSession {
+    fn subscribe(
+        &mut self,
+        symbol: String,
+        resolution: String,
+        outbox: &Outbox,
+    ) -> Option<WsMessage> {
+        let key = (symbol.clone(), resolution.clone());
+        if self.subscriptions.contains(&key) {
+            return None;
+        }
+        let Some(store) = self.trading_engine.get_store(&symbol) else {
+            return Some(WsMessage::Error {
+                message: format!("Symbol not found: {}", symbol),
+            });
+        };
+        let Some(interval) =
+            resolution_seconds(&resolution).filter(|&interval| store.stores_interval(interval))
+        else {
+            return Some(WsMessage::Error {
+                message: format!("Unsupported resolution: {}", resolution),
+            });
+        };
+        let formatter = Formatter::new(
+            &self.number_format,
+            self.trading_engine.config(&symbol).as_ref(),
+        );
+        let (bar_symbol, bar_resolution) = key.clone();
+        let follower = BarFollower {
+            store,
+            symbol: symbol.clone(),
+            interval,
+            render: Box::new(move |candle: &Candle, closed| {
+                let symbol = bar_symbol.clone();
+                let resolution = bar_resolution.clone();
+                let bar = WsBar::new(candle, &formatter);
+                frame(&if closed {
+                    WsMessage::BarClose {
+                        symbol,
+                        resolution,
+                        bar,
+                    }
+                } else {
+                    WsMessage::Bar {
+                        symbol,
+                        resolution,
+                        bar,
+                    }
+                })
+            }),
+        };
+        let events = self.trading_engine.candle_events.subscribe();
+        // Queued ahead of the follower's first bar, so `subscribed` arrives first.
+        let subscribed = frame(&WsMessage::Subscribed { symbol, resolution });
+        let outbox = outbox.clone();
+        let task = tokio::spawn(async move {
+            if outbox.send(subscribed).await.is_ok() {
+                follower.run(events, outbox).await;
+            }
+        });
+        self.subscriptions.insert(key, task);
+        None
+    }
+}
+
+fn frame(message: &WsMessage) -> Message {
+    Message::Text(serde_json::to_string(message).expect("WebSocket messages serialize"))
+}
+
+/// WebSocket feed of live bars. Clients send `{"type": "subscribe", "symbol": ...,
+/// "resolution": ...}` for each series they follow and get the newest bar as `bar`
+/// after every change, plus a `bar_close` with a bar's final state once the next one
+/// opens. With `heartbeat_secs` configured, idle connections are pinged.
+#[get("/ws")]
+pub fn candle_socket(
+    handshake: WsHandshake,
+    trading_engine: Engine,
+    server_config: &State<ServerConfig>,
+    shutdown: Shutdown,
+) -> Upgrade<CandleSession> {
+    let session = CandleSession {
+        trading_engine: trading_engine.0,
+        number_format: server_config.number_format,
+        subscriptions: Subscriptions::default(),
+    };
+    Upgrade::new(handshake, server_config, shutdown, session)
+}
@@ -1,8 +1,18 @@
 use std::net::Ipv4Addr;
 use std::sync::Arc;
 
+use crate::config::server::ServerConfig;
+use crate::replication::Promotion;
+use crate::storage::tenants::TenantRegistry;
 use crate::storage::trading_engine::TradingEngine;
-use crate::web::routes::{get_docs, get_routes};
+use crate::web::compression::Compression;
+use crate::web::deprecation::{Deprecation, DeprecationUsage};
+use crate::web::routes::history::HistoryResponseCache;
+use crate::web::routes::{
+    get_admin_routes, get_binance_routes, get_chart_routes, get_docs, get_export_routes,
+    get_metrics_routes, get_routes, get_stream_routes,
+};
+use crate::web::version::{ApiVersion, Versioning};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::Header;
 use rocket::{Build, Config, Rocket};
@@ -28,21 +38,93 @@ impl Fairing for CORS {
         ));
         res.set_header(Header::new(
             "Access-Control-Allow-Headers",
-            "Content-Type, Authorization",
+            "Content-Type, Authorization, X-API-Key",
         ));
     }
 }
 
-pub fn rocket(port: u16, trading_engine: Arc<TradingEngine>) -> Rocket<Build> {
+/// Adds the operator-configured `response_headers` (attribution, terms) to every response.
+pub struct ResponseHeaders(pub Vec<(String, String)>);
+
+#[rocket::async_trait]
+impl Fairing for ResponseHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Add configured attribution headers to responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, _: &'r Request<'_>, res: &mut Response<'r>) {
+        for (name, value) in &self.0 {
+            res.set_header(Header::new(name.clone(), value.clone()));
+        }
+    }
+}
+
+/// Mounts the API's route groups under `base`, or at the root when it is empty.
+fn mount_api(rocket: Rocket<Build>, base: &str) -> Rocket<Build> {
+    let root = if base.is_empty() { "/" } else { base };
+    rocket
+        .mount(root, get_routes())
+        .mount(root, get_metrics_routes())
+        .mount(root, get_chart_routes())
+        .mount(root, get_stream_routes())
+        .mount(root, get_binance_routes())
+        .mount(root, get_export_routes())
+        .mount(format!("{}/admin", base), get_admin_routes())
+}
+
+pub fn rocket(
+    port: u16,
+    trading_engine: Arc<TradingEngine>,
+    tenants: TenantRegistry,
+    server_config: ServerConfig,
+    promotion: Arc<Promotion>,
+) -> Rocket<Build> {
     let config = Config {
         address: Ipv4Addr::new(0, 0, 0, 0).into(),
         port,
         ..Config::default()
     };
 
-    rocket::custom(config)
+    let tenant_names: Vec<String> = tenants.iter().map(|(name, _)| name.clone()).collect();
+
+    let response_headers = server_config
+        .response_headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    let deprecation = Deprecation {
+        routes: server_config.deprecated_routes.clone(),
+        usage: Arc::new(DeprecationUsage::default()),
+    };
+
+    let mut rocket = rocket::custom(config)
         .manage(trading_engine)
-        .mount("/", get_routes())
+        .manage(tenants)
+        .manage(server_config)
+        .manage(promotion)
+        .manage(HistoryResponseCache::default())
+        .manage(deprecation.usage.clone())
         .mount("/swagger", make_swagger_ui(&get_docs()))
         .attach(CORS)
+        .attach(Compression)
+        .attach(ResponseHeaders(response_headers))
+        .attach(deprecation)
+        .attach(Versioning);
+
+    // Every route is served unversioned and under each version's prefix, at the root
+    // and per tenant.
+    let mut bases = vec![String::new()];
+    bases.extend(tenant_names.iter().map(|name| format!("/t/{}", name)));
+    for base in bases {
+        rocket = mount_api(rocket, &base);
+        for version in ApiVersion::ALL {
+            rocket = mount_api(rocket, &format!("{}{}", base, version.prefix()));
+        }
+    }
+
+    rocket
 }
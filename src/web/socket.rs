@@ -0,0 +1,225 @@
+use futures_util::{SinkExt, StreamExt};
+use rocket::data::{IoHandler, IoStream};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+use rocket::Shutdown;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, Duration, Instant};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::config::server::ServerConfig;
+use crate::storage::candle_events::CandleEvent;
+use crate::storage::candles::{Candle, CandleStore};
+
+/// Frames queued per connection before the slowest subscription waits.
+const OUTBOX_CAPACITY: usize = 64;
+
+/// Where a connection's subscriptions queue frames for the client.
+pub type Outbox = mpsc::Sender<Message>;
+
+/// What a WebSocket endpoint does with the text frames of one connection.
+pub trait Session: Send + Unpin + 'static {
+    /// Called once the connection is open, e.g. to start subscriptions named in the URL.
+    fn start(&mut self, _outbox: &Outbox) {}
+
+    /// Applies a text frame from the client, returning the reply to send.
+    fn handle(&mut self, text: &str, outbox: &Outbox) -> Option<Message>;
+}
+
+/// The `Sec-WebSocket-Accept` answer to a WebSocket handshake request.
+pub struct WsHandshake(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WsHandshake {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let upgrade = req
+            .headers()
+            .get("Upgrade")
+            .any(|value| value.eq_ignore_ascii_case("websocket"));
+        match req.headers().get_one("Sec-WebSocket-Key") {
+            Some(key) if upgrade => {
+                Outcome::Success(WsHandshake(derive_accept_key(key.as_bytes())))
+            }
+            _ => Outcome::Error((Status::UpgradeRequired, ())),
+        }
+    }
+}
+
+/// Upgrades the request to a WebSocket served by `session`. With `heartbeat_secs`
+/// configured, idle connections are pinged.
+pub struct Upgrade<S> {
+    accept: String,
+    heartbeat: Option<Duration>,
+    shutdown: Shutdown,
+    session: S,
+}
+
+impl<S: Session> Upgrade<S> {
+    pub fn new(
+        handshake: WsHandshake,
+        server_config: &ServerConfig,
+        shutdown: Shutdown,
+        session: S,
+    ) -> Self {
+        Self {
+            accept: handshake.0,
+            heartbeat: server_config
+                .heartbeat_secs
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            shutdown,
+            session,
+        }
+    }
+
+    async fn serve(mut self, socket: WebSocketStream<IoStream>) {
+        let (mut sink, mut requests) = socket.split();
+        let (outbox, mut frames) = mpsc::channel(OUTBOX_CAPACITY);
+        let mut ping_at = self.heartbeat.map(|every| Instant::now() + every);
+        self.session.start(&outbox);
+
+        loop {
+            let frame = tokio::select! {
+                request = requests.next() => match request {
+                    Some(Ok(Message::Text(text))) => match self.session.handle(&text, &outbox) {
+                        Some(reply) => reply,
+                        None => continue,
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                },
+                Some(frame) = frames.recv() => frame,
+                _ = sleep_until(ping_at.unwrap_or_else(Instant::now)), if ping_at.is_some() => {
+                    ping_at = self.heartbeat.map(|every| Instant::now() + every);
+                    Message::Ping(Vec::new())
+                }
+                _ = &mut self.shutdown => break,
+            };
+            if sink.send(frame).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.send(Message::Close(None)).await;
+    }
+}
+
+impl<'r, S: Session> Responder<'r, 'static> for Upgrade<S> {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        Response::build()
+            .raw_header("Sec-WebSocket-Accept", self.accept.clone())
+            .upgrade("websocket", self)
+            .ok()
+    }
+}
+
+#[rocket::async_trait]
+impl<S: Session> IoHandler for Upgrade<S> {
+    async fn io(self: Pin<Box<Self>>, io: IoStream) -> io::Result<()> {
+        let socket = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+        Pin::into_inner(self).serve(socket).await;
+        Ok(())
+    }
+}
+
+/// A connection's running subscriptions by key, stopped when removed or dropped with
+/// the connection.
+pub struct Subscriptions<K>(HashMap<K, JoinHandle<()>>);
+
+impl<K> Default for Subscriptions<K> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<K: Eq + Hash> Subscriptions<K> {
+    pub fn contains(&self, key: &K) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: K, task: JoinHandle<()>) {
+        if let Some(replaced) = self.0.insert(key, task) {
+            replaced.abort();
+        }
+    }
+
+    /// Stops the subscription under `key`; returns whether there was one.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.0.remove(key).map(|task| task.abort()).is_some()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.0.keys()
+    }
+}
+
+impl<K> Drop for Subscriptions<K> {
+    fn drop(&mut self) {
+        for task in self.0.values() {
+            task.abort();
+        }
+    }
+}
+
+/// Turns a bar into a frame; the flag tells a closed bar from an update.
+pub type RenderBar = Box<dyn Fn(&Candle, bool) -> Message + Send + Sync>;
+
+/// Sends the bars of one series to a connection as they change: the newest bar first,
+/// then every update and close from the engine's candle events.
+pub struct BarFollower {
+    pub store: Arc<CandleStore>,
+    pub symbol: String,
+    pub interval: u64,
+    pub render: RenderBar,
+}
+
+impl BarFollower {
+    /// Follows `events`, which should be subscribed before the follower is spawned so
+    /// no change falls between reading the first bar and the first event.
+    pub async fn run(self, mut events: broadcast::Receiver<CandleEvent>, outbox: Outbox) {
+        if !self.send_newest(&outbox).await {
+            return;
+        }
+        loop {
+            let sent = match events.recv().await {
+                Ok(event) if event.is_for(&self.symbol, self.interval) => {
+                    let closed = matches!(event, CandleEvent::Closed { .. });
+                    outbox
+                        .send((self.render)(event.candle(), closed))
+                        .await
+                        .is_ok()
+                }
+                Ok(_) => true,
+                // Missed updates are covered by the bar as it stands now.
+                Err(RecvError::Lagged(_)) => self.send_newest(&outbox).await,
+                Err(RecvError::Closed) => false,
+            };
+            if !sent {
+                return;
+            }
+        }
+    }
+
+    async fn send_newest(&self, outbox: &Outbox) -> bool {
+        match self
+            .store
+            .get_candles(&self.symbol, self.interval, 1)
+            .first()
+        {
+            Some(newest) => outbox.send((self.render)(newest, false)).await.is_ok(),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::storage::tenants::{Tenant, TenantRegistry};
+use crate::storage::trading_engine::TradingEngine;
+
+/// Returns the tenant name when the request is served under `/t/<tenant>/`.
+pub fn tenant_name<'r>(req: &'r Request<'_>) -> Option<&'r str> {
+    let mut segments = req.uri().path().segments();
+    match (segments.next(), segments.next()) {
+        (Some("t"), Some(name)) => Some(name),
+        _ => None,
+    }
+}
+
+pub fn resolve_tenant<'r>(req: &'r Request<'_>) -> Option<&'r Tenant> {
+    let name = tenant_name(req)?;
+    req.rocket().state::<TenantRegistry>()?.get(name)
+}
+
+/// The `TradingEngine` serving this request: the tenant's engine under
+/// `/t/<tenant>/`, the default engine everywhere else.
+pub struct Engine(pub Arc<TradingEngine>);
+
+impl Deref for Engine {
+    type Target = TradingEngine;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Engine {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if tenant_name(req).is_some() {
+            return match resolve_tenant(req) {
+                Some(tenant) if tenant.authorize(req.headers().get_one("X-API-Key")) => {
+                    Outcome::Success(Engine(Arc::clone(&tenant.engine)))
+                }
+                Some(_) => Outcome::Error((Status::Unauthorized, ())),
+                None => Outcome::Error((Status::NotFound, ())),
+            };
+        }
+
+        match req.rocket().state::<Arc<TradingEngine>>() {
+            Some(engine) => Outcome::Success(Engine(Arc::clone(engine))),
+            None => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Engine {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
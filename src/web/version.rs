@@ -0,0 +1,107 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::Response;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+
+/// Versions of the API's response shapes. Routes are mounted under each version's
+/// prefix, and unprefixed as aliases of the current one while clients migrate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    V1,
+}
+
+impl ApiVersion {
+    pub const CURRENT: ApiVersion = ApiVersion::V1;
+    pub const ALL: [ApiVersion; 1] = [ApiVersion::V1];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+        }
+    }
+
+    /// The mount prefix, e.g. `/v1`.
+    pub fn prefix(self) -> String {
+        format!("/{}", self.name())
+    }
+
+    /// Parses `v1` or `1`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let number = version
+            .strip_prefix('v')
+            .or_else(|| version.strip_prefix('V'))
+            .unwrap_or(version);
+        Self::ALL
+            .into_iter()
+            .find(|known| &known.name()[1..] == number)
+    }
+
+    /// The version named by the mount the request was routed through, if it has one.
+    fn of_mount(req: &Request<'_>) -> Option<Self> {
+        let base = req.route()?.uri.base();
+        let last = base.rsplit('/').next()?;
+        Self::ALL.into_iter().find(|known| known.name() == last)
+    }
+}
+
+/// The API version a request is served at: the one in its path, else the one asked for
+/// with `Accept-Version`, else the current one. An unknown `Accept-Version` is refused
+/// with `406 Not Acceptable`.
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiVersion {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if let Some(version) = ApiVersion::of_mount(req) {
+            return Outcome::Success(version);
+        }
+        match req.headers().get_one("Accept-Version") {
+            Some(asked) => match ApiVersion::parse(asked) {
+                Some(version) => Outcome::Success(version),
+                None => Outcome::Error((Status::NotAcceptable, ())),
+            },
+            None => Outcome::Success(ApiVersion::CURRENT),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for ApiVersion {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// Tells clients which API version answered with an `API-Version` header.
+pub struct Versioning;
+
+#[rocket::async_trait]
+impl Fairing for Versioning {
+    fn info(&self) -> Info {
+        Info {
+            name: "Report the API version of responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if req.route().is_none() {
+            return;
+        }
+        let version = match ApiVersion::of_mount(req) {
+            Some(version) => version,
+            None => req
+                .headers()
+                .get_one("Accept-Version")
+                .and_then(ApiVersion::parse)
+                .unwrap_or(ApiVersion::CURRENT),
+        };
+        res.set_header(Header::new("API-Version", version.name()));
+    }
+}
@@ -0,0 +1,68 @@
+use spark_candles::bricks::{range_bars, renko, travel, Tick};
+
+/// One tick per price, a second apart, each trading one unit.
+fn path(prices: &[f64]) -> Vec<Tick> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| Tick {
+            time: i as i64,
+            price,
+            volume: 1.0,
+        })
+        .collect()
+}
+
+fn spans(bricks: &[spark_candles::bricks::Brick]) -> Vec<(f64, f64)> {
+    bricks.iter().map(|b| (b.open, b.close)).collect()
+}
+
+#[test]
+fn renko_continues_after_one_box_and_reverses_after_two() {
+    let bricks = renko(&path(&[10.5, 11.2, 13.0, 12.1, 11.0, 10.9, 9.0]), 1.0);
+    assert_eq!(
+        spans(&bricks),
+        [
+            (10.0, 11.0),
+            (11.0, 12.0),
+            (12.0, 13.0),
+            (12.0, 11.0),
+            (11.0, 10.0),
+            (10.0, 9.0)
+        ]
+    );
+    // Volume lands on the brick its tick finished, and every tick is counted once.
+    assert_eq!(bricks[0].volume, 2.0);
+    assert_eq!(bricks[1].volume, 1.0);
+    assert_eq!(bricks[2].volume, 0.0);
+    assert_eq!(bricks.iter().map(|b| b.volume).sum::<f64>(), 7.0);
+    assert_eq!((bricks[3].start, bricks[3].end), (2, 4));
+}
+
+#[test]
+fn range_bars_close_at_their_limit() {
+    let bars = range_bars(&path(&[10.0, 10.5, 9.8, 11.0, 10.1, 7.5]), 1.0);
+    let limits: Vec<_> = bars
+        .iter()
+        .map(|b| (b.open, b.high, b.low, b.close))
+        .collect();
+    assert_eq!(
+        limits,
+        [
+            (10.0, 10.8, 9.8, 10.8),
+            (10.8, 11.0, 10.0, 10.0),
+            (10.0, 10.0, 9.0, 9.0),
+            (9.0, 9.0, 8.0, 8.0),
+        ]
+    );
+    assert!(bars.iter().all(|b| b.high - b.low <= 1.0 + 1e-9));
+}
+
+#[test]
+fn travel_bounds_the_number_of_bricks() {
+    let ticks = path(&[100.0, 104.0, 98.0, 107.0, 95.0]);
+    assert_eq!(travel(&ticks), 31.0);
+    assert!(renko(&ticks, 1.0).len() as f64 <= travel(&ticks));
+    assert!(range_bars(&ticks, 1.0).len() as f64 <= travel(&ticks));
+    assert!(renko(&path(&[100.0]), 1.0).is_empty());
+}
@@ -0,0 +1,39 @@
+use spark_candles::storage::candles::CandleStore;
+use spark_candles::testkit::{self, GoldenCandle};
+
+#[test]
+fn gap_filled_store_matches_golden_candles() {
+    let store = testkit::store_with(CandleStore::new(), &testkit::golden_trades());
+    for interval in [60, 300, 3600] {
+        let expected = testkit::golden_candles(interval).unwrap();
+        let actual: Vec<GoldenCandle> = testkit::read_all(&store, interval)
+            .iter()
+            .map(GoldenCandle::from)
+            .collect();
+        assert_eq!(actual, expected, "interval {}", interval);
+    }
+}
+
+#[test]
+fn sparse_store_synthesizes_the_golden_gaps() {
+    let store = testkit::store_with(
+        CandleStore::new().with_fill_gaps(false),
+        &testkit::golden_trades(),
+    );
+    let sparse = testkit::read_all(&store, 60);
+    assert_eq!(sparse.iter().filter(|c| c.is_gap()).count(), 0);
+
+    let filled: Vec<GoldenCandle> = CandleStore::synthesize_gaps(&sparse, 60, usize::MAX)
+        .iter()
+        .map(GoldenCandle::from)
+        .collect();
+    assert_eq!(filled, testkit::golden_candles(60).unwrap());
+}
+
+#[test]
+fn golden_store_passes_validation() {
+    let store = testkit::store_with(CandleStore::new(), &testkit::golden_trades());
+    let report = store.validate(testkit::SYMBOL);
+    assert!(report.checked > 0);
+    assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+}
@@ -0,0 +1,81 @@
+use spark_candles::indicators::{bollinger, ema, macd, rsi, sma};
+
+/// Closes of the worked RSI example in Wilder's "New Concepts in Technical Trading
+/// Systems", as tabulated by StockCharts.
+const WILDER_CLOSES: [f64; 33] = [
+    44.3389, 44.0902, 44.1497, 43.6124, 44.3278, 44.8264, 45.0955, 45.4245, 45.8433, 46.0826,
+    45.8931, 46.0328, 45.6140, 46.2820, 46.2820, 46.0028, 46.0328, 46.4116, 46.2222, 45.6439,
+    46.2122, 46.2521, 45.7137, 46.4515, 45.7835, 45.3548, 44.0288, 44.1783, 44.2181, 44.5672,
+    43.4205, 42.6628, 43.1314,
+];
+
+/// The 14-period RSI of `WILDER_CLOSES` from the 15th close on, to two decimals.
+const WILDER_RSI: [f64; 19] = [
+    70.53, 66.32, 66.55, 69.41, 66.36, 57.97, 62.93, 63.26, 56.06, 62.38, 54.71, 50.42, 39.99,
+    41.46, 41.87, 45.46, 37.30, 33.08, 37.77,
+];
+
+fn round(value: Option<f64>, decimals: i32) -> Option<f64> {
+    let scale = 10f64.powi(decimals);
+    value.map(|value| (value * scale).round() / scale)
+}
+
+/// `0, 1, 2, ...`: its EMA over `n` values trails it by exactly `(n - 1) / 2`.
+fn line(len: usize) -> Vec<f64> {
+    (0..len).map(|i| i as f64).collect()
+}
+
+#[test]
+fn sma_averages_the_last_period_values() {
+    assert_eq!(
+        sma(&[1.0, 2.0, 3.0, 4.0, 5.0], 3),
+        [None, None, Some(2.0), Some(3.0), Some(4.0)]
+    );
+}
+
+#[test]
+fn ema_of_a_line_lags_by_half_the_period() {
+    let averages = ema(&line(40), 9);
+    assert!(averages[..8].iter().all(Option::is_none));
+    for (i, average) in averages.iter().enumerate().skip(8) {
+        assert_eq!(*average, Some(i as f64 - 4.0));
+    }
+}
+
+#[test]
+fn rsi_matches_wilders_worked_example() {
+    let strengths = rsi(&WILDER_CLOSES, 14);
+    assert!(strengths[..14].iter().all(Option::is_none));
+    let rounded: Vec<_> = strengths[14..].iter().map(|&rsi| round(rsi, 2)).collect();
+    assert_eq!(rounded, WILDER_RSI.map(Some));
+}
+
+#[test]
+fn rsi_of_a_rising_series_is_100() {
+    assert_eq!(rsi(&line(20), 14)[19], Some(100.0));
+}
+
+#[test]
+fn macd_of_a_line_is_the_gap_between_the_lags() {
+    let lines = macd(&line(60), 12, 26, 9);
+    // Defined from the 26th value, its signal from the 9th value after that.
+    assert!(lines.macd[..25].iter().all(Option::is_none));
+    assert!(lines.signal[..33].iter().all(Option::is_none));
+    for i in 25..60 {
+        assert_eq!(lines.macd[i], Some(7.0));
+    }
+    for i in 33..60 {
+        assert_eq!(lines.signal[i], Some(7.0));
+        assert_eq!(lines.histogram[i], Some(0.0));
+    }
+}
+
+#[test]
+fn bollinger_bands_use_the_population_deviation() {
+    let bands = bollinger(&[1.0, 2.0, 3.0, 4.0, 5.0], 5, 2.0);
+    assert_eq!(bands.middle[4], Some(3.0));
+    // The deviation of 1..=5 is the square root of 2.
+    assert_eq!(round(bands.upper[4], 6), Some(5.828427));
+    assert_eq!(round(bands.lower[4], 6), Some(0.171573));
+    assert!(bands.upper[..4].iter().all(Option::is_none));
+}
@@ -0,0 +1,104 @@
+use chrono::DateTime;
+use proptest::prelude::*;
+use spark_candles::storage::candles::CandleStore;
+use spark_candles::storage::interval::{
+    period_end, period_start, DEFAULT_INTERVALS, SUB_MINUTE_INTERVALS,
+};
+use spark_candles::testkit;
+
+fn any_interval() -> impl Strategy<Value = u64> {
+    prop::sample::select(
+        SUB_MINUTE_INTERVALS
+            .iter()
+            .chain(DEFAULT_INTERVALS.iter())
+            .copied()
+            .collect::<Vec<_>>(),
+    )
+}
+
+proptest! {
+    #[test]
+    fn period_start_contains_the_timestamp(
+        timestamp in 0i64..4_000_000_000,
+        interval in any_interval(),
+    ) {
+        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
+        let start = period_start(datetime, interval);
+        prop_assert!(start <= datetime);
+        prop_assert!(datetime < period_end(start, interval));
+        prop_assert_eq!(period_start(start, interval), start);
+    }
+
+    #[test]
+    fn every_interval_accounts_for_every_trade(
+        seed in any::<u64>(),
+        count in 1usize..300,
+        span_secs in 1i64..20_000,
+        fill_gaps in any::<bool>(),
+    ) {
+        let trades = testkit::random_trades(seed, count, span_secs);
+        let store = testkit::store_with(CandleStore::new().with_fill_gaps(fill_gaps), &trades);
+        let volume: u128 = trades.iter().map(|t| t.volume).sum();
+        let quote_volume: u128 = trades.iter().map(|t| t.price * t.volume).sum();
+
+        for interval in store.intervals() {
+            let candles = testkit::read_all(&store, interval);
+            prop_assert!(candles.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+            for candle in &candles {
+                prop_assert_eq!(period_start(candle.timestamp, interval), candle.timestamp);
+                prop_assert!(candle.low <= candle.open.min(candle.close));
+                prop_assert!(candle.high >= candle.open.max(candle.close));
+            }
+            prop_assert_eq!(candles.iter().map(|c| c.volume).sum::<u128>(), volume);
+            prop_assert_eq!(candles.iter().map(|c| c.quote_volume).sum::<u128>(), quote_volume);
+            prop_assert_eq!(candles.iter().map(|c| c.trade_count).sum::<u64>(), count as u64);
+        }
+        let report = store.validate(testkit::SYMBOL);
+        prop_assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+    }
+
+    #[test]
+    fn synthesized_gaps_are_contiguous_and_flat(
+        seed in any::<u64>(),
+        count in 1usize..100,
+        span_secs in 1i64..20_000,
+    ) {
+        let trades = testkit::random_trades(seed, count, span_secs);
+        let store = testkit::store_with(CandleStore::new().with_fill_gaps(false), &trades);
+        let sparse = testkit::read_all(&store, 60);
+        let filled = CandleStore::synthesize_gaps(&sparse, 60, usize::MAX);
+
+        prop_assert!(filled
+            .windows(2)
+            .all(|w| (w[1].timestamp - w[0].timestamp).num_seconds() == 60));
+        for pair in filled.windows(2).filter(|w| w[1].is_gap()) {
+            let (previous, gap) = (&pair[0], &pair[1]);
+            prop_assert_eq!(gap.volume, 0);
+            prop_assert_eq!([gap.open, gap.high, gap.low], [previous.close; 3]);
+            prop_assert_eq!(gap.close, previous.close);
+        }
+        let traded: Vec<_> = filled.iter().filter(|c| !c.is_gap()).map(|c| c.timestamp).collect();
+        prop_assert_eq!(traded, sparse.iter().map(|c| c.timestamp).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn heikin_ashi_bars_stay_within_their_range(
+        seed in any::<u64>(),
+        count in 1usize..300,
+        span_secs in 1i64..20_000,
+    ) {
+        let trades = testkit::random_trades(seed, count, span_secs);
+        let store = testkit::store_with(CandleStore::new(), &trades);
+        let candles = testkit::read_all(&store, 60);
+        let smoothed = CandleStore::heikin_ashi(&candles);
+
+        prop_assert_eq!(smoothed.len(), candles.len());
+        for (bar, candle) in smoothed.iter().zip(&candles) {
+            prop_assert_eq!(bar.timestamp, candle.timestamp);
+            prop_assert_eq!(bar.volume, candle.volume);
+            prop_assert!(bar.low <= bar.open.min(bar.close));
+            prop_assert!(bar.high >= bar.open.max(bar.close));
+            prop_assert!(candle.low <= bar.close && bar.close <= candle.high);
+        }
+    }
+}
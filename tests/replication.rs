@@ -0,0 +1,149 @@
+use spark_candles::replication::standby::resume_configs;
+use spark_candles::storage::candles::CandleStore;
+use spark_candles::storage::dead_letter::DeadLetterStore;
+use spark_candles::storage::trades::Trade;
+use spark_candles::storage::trading_engine::{ChainConfig, TradingEngine, TradingPairConfig};
+use spark_candles::testkit::{self, START};
+
+const CHAIN: ChainConfig = ChainConfig { finality_depth: 3 };
+
+fn engine() -> TradingEngine {
+    let config: TradingPairConfig = serde_json::from_value(serde_json::json!({
+        "symbol": testkit::SYMBOL,
+        "contract_id": "0x1",
+        "start_block": 100,
+        "description": "Fixture",
+        "decimals": 0
+    }))
+    .unwrap();
+    TradingEngine::new(vec![config], CHAIN, DeadLetterStore::new(None))
+}
+
+/// Delivers the one trade of `block`, a second after the previous block's.
+fn deliver(store: &CandleStore, block: i64) {
+    let trade = Trade {
+        price: 100 + block as u128 % 7,
+        volume: 1,
+        event_time: (START + block) * 1000,
+        wash: false,
+    };
+    store.add_block_trades(
+        testkit::SYMBOL,
+        block,
+        &format!("0x{:x}", block),
+        vec![(format!("{}:0", block), trade)],
+    );
+    store.mark_block(block);
+}
+
+fn totals(store: &CandleStore) -> (u128, u64) {
+    testkit::read_all(store, 60)
+        .iter()
+        .fold((0, 0), |(volume, count), c| {
+            (volume + c.volume, count + c.trade_count)
+        })
+}
+
+#[test]
+fn standby_promoted_mid_backfill_with_held_back_trades_leaves_no_gap() {
+    let history: Vec<i64> = (100..200).collect();
+    let live: Vec<i64> = (200..=212).collect();
+
+    // The primary follows live blocks from 200 while history up to 199 loads behind
+    // them; it has applied history up to 150, and blocks from 210 are not final yet.
+    let primary = engine();
+    let store = primary.get_store(testkit::SYMBOL).unwrap();
+    store.observe_head(199);
+    store.set_backfill_checkpoint(Some(99));
+    store.set_backfill_target(Some(199));
+    for &block in &live {
+        deliver(&store, block);
+    }
+    for &block in history.iter().filter(|&&block| block <= 150) {
+        deliver(&store, block);
+    }
+    store.set_backfill_checkpoint(Some(150));
+    store.observe_head(212);
+    assert_eq!(store.pending_blocks().len(), 3);
+
+    let standby = engine();
+    let replica = standby.get_store(testkit::SYMBOL).unwrap();
+    replica.apply_delta(store.delta_since(0));
+
+    // Promoted, the standby backfills from its checkpoint up to the primary's target
+    // and follows live blocks after the last one its candles hold.
+    let start_block = resume_configs(&standby)[0].start_block;
+    let target = replica.backfill_target().unwrap();
+    let live_after = target.max(replica.last_block());
+    assert_eq!((start_block, target, live_after), (151, 199, 209));
+    for &block in history.iter().filter(|&&block| block >= start_block) {
+        deliver(&replica, block);
+    }
+    for &block in live.iter().filter(|&&block| block > live_after) {
+        deliver(&replica, block);
+    }
+    replica.observe_head(220);
+
+    let reference = engine();
+    let expected = reference.get_store(testkit::SYMBOL).unwrap();
+    for &block in history.iter().chain(&live) {
+        deliver(&expected, block);
+    }
+    expected.observe_head(220);
+
+    assert_eq!(totals(&replica), totals(&expected));
+    assert_eq!(totals(&replica).1, (history.len() + live.len()) as u64);
+}
+
+fn archived(store: &CandleStore) -> usize {
+    store
+        .trades
+        .range(testkit::SYMBOL, i64::MIN, i64::MAX)
+        .len()
+}
+
+fn bars(store: &CandleStore) -> Vec<(i64, u128, u64)> {
+    testkit::read_all(store, 60)
+        .iter()
+        .map(|c| (c.timestamp.timestamp(), c.volume, c.trade_count))
+        .collect()
+}
+
+#[test]
+fn standby_follows_removed_candles_trades_and_first_trade() {
+    let primary = engine();
+    let store = primary.get_store(testkit::SYMBOL).unwrap();
+    let standby = engine();
+    let replica = standby.get_store(testkit::SYMBOL).unwrap();
+
+    for block in 100..110 {
+        store.observe_first_trade(block, (START + block * 60) * 1000);
+        store.add_trade(testkit::SYMBOL, 100, 1, (START + block * 60) * 1000);
+    }
+    let delta = store.delta_since(0);
+    let cursor = delta.revision;
+    replica.apply_delta(delta);
+    assert_eq!(bars(&replica), bars(&store));
+
+    // Later trades arrive as changes; a rebuild from an archive that lost its oldest
+    // trades removes candles, which the standby only follows by a full resync.
+    for block in 110..115 {
+        store.add_trade(testkit::SYMBOL, 100, 1, (START + block * 60) * 1000);
+    }
+    let delta = store.delta_since(cursor);
+    assert!(!delta.full);
+    let cursor = delta.revision;
+    replica.apply_delta(delta);
+    assert_eq!(archived(&replica), 15);
+    store.trades.evict(3 * std::mem::size_of::<Trade>());
+    store.rebuild(testkit::SYMBOL);
+    let delta = store.delta_since(cursor);
+    assert!(delta.full);
+    replica.apply_delta(delta);
+
+    assert_eq!(bars(&replica), bars(&store));
+    assert_eq!(bars(&replica).len(), 12);
+    assert_eq!(archived(&replica), archived(&store));
+    assert!(!replica.trades.covers(testkit::SYMBOL, START * 1000));
+    assert_eq!(replica.first_trade(), store.first_trade());
+}